@@ -6,17 +6,29 @@ async fn main() -> std::io::Result<()> {
     use actix_web::*;
     use leptos::prelude::*;
     use leptos_actix::{generate_route_list, LeptosRoutes};
+    use leptos_sse::SseRegistry;
+    use std::time::Duration;
 
     let conf = get_configuration(None).unwrap();
     let addr = conf.leptos_options.site_addr;
     // Generate the list of routes in your Leptos App
     let routes = generate_route_list(|| view! { <App/> });
 
+    // Counts up every second, while at least one client is connected
+    let registry = SseRegistry::new();
+    let mut value = 0;
+    registry.spawn_interval("counter", Duration::from_secs(1), move || {
+        let curr = value;
+        value += 1;
+        Count { value: curr }
+    });
+
     HttpServer::new(move || {
         let leptos_options = &conf.leptos_options;
         let site_root = &leptos_options.site_root;
 
         App::new()
+            .app_data(web::Data::new(registry.clone()))
             .route("/api/{tail:.*}", leptos_actix::handle_server_fns())
             .route("/sse", web::get().to(handle_sse))
             .leptos_routes(
@@ -40,24 +52,13 @@ pub fn main() {
 }
 
 #[cfg(feature = "ssr")]
-pub async fn handle_sse() -> impl actix_web::Responder {
+pub async fn handle_sse(
+    registry: actix_web::web::Data<leptos_sse::SseRegistry>,
+) -> impl actix_web::Responder {
     use actix_example::app::Count;
     use actix_web_lab::sse;
-    use futures::stream;
-    use leptos_sse::ServerSentEvents;
     use std::time::Duration;
-    use tokio_stream::StreamExt as _;
 
-    let mut value = 0;
-    let stream = ServerSentEvents::new(
-        "counter",
-        stream::repeat_with(move || {
-            let curr = value;
-            value += 1;
-            Ok(Count { value: curr })
-        })
-        .throttle(Duration::from_secs(1)),
-    )
-    .unwrap();
+    let stream = registry.events::<Count>("counter").unwrap();
     sse::Sse::from_stream(stream).with_keep_alive(Duration::from_secs(5))
 }
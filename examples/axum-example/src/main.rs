@@ -2,6 +2,7 @@
 #[tokio::main]
 async fn main() {
     use axum::{
+        middleware,
         routing::{get, post},
         Router,
     };
@@ -9,7 +10,8 @@ async fn main() {
     use axum_example::fileserv::file_and_error_handler;
     use leptos::prelude::*;
     use leptos_axum::{generate_route_list, LeptosRoutes};
-    use tower_http::cors::{CorsLayer, Any};
+    use leptos_sse::AllowedOrigins;
+    use std::time::Duration;
 
     simple_logger::init_with_level(log::Level::Debug).expect("couldn't initialize logging");
 
@@ -22,19 +24,31 @@ async fn main() {
     let leptos_options = conf.leptos_options;
     let addr = leptos_options.site_addr;
     let routes = generate_route_list(|| view! { <App/> });
+    // Only pages served by this app may read the SSE stream
+    let allowed_origins = AllowedOrigins::new([format!("http://{addr}")]);
+
+    // Counts up every second, while at least one client is connected
+    let registry = SseRegistry::new();
+    let mut value = 0;
+    registry.spawn_interval("counter", Duration::from_secs(1), move || {
+        let curr = value;
+        value += 1;
+        log::debug!("Sending count: {}", curr);
+        Count { value: curr }
+    });
 
     // build our application with a route
     let app = Router::new()
         .route("/api/{{*fn_name}}", post(leptos_axum::handle_server_fns))
         // SSE route must be before the leptos routes to avoid being caught by fallback
-        .route("/sse", get(handle_sse))
-        // Apply CORS to allow SSE connections
-        .layer(
-            CorsLayer::new()
-                .allow_origin(Any)
-                .allow_methods(Any)
-                .allow_headers(Any)
+        .route(
+            "/sse",
+            get(handle_sse).layer(middleware::from_fn_with_state(
+                allowed_origins,
+                AllowedOrigins::middleware,
+            )),
         )
+        .layer(Extension(registry))
         .leptos_routes(&leptos_options, routes, || view! { <App/> })
         .fallback(file_and_error_handler)
         .with_state(leptos_options);
@@ -57,33 +71,24 @@ pub fn main() {
 
 #[cfg(feature = "ssr")]
 use {
-    axum::response::sse::{Event, KeepAlive, Sse},
+    axum::{
+        response::sse::{Event, KeepAlive, Sse},
+        Extension,
+    },
     futures::stream::Stream,
+    leptos_sse::SseRegistry,
 };
 
 #[cfg(feature = "ssr")]
-async fn handle_sse() -> Sse<impl Stream<Item = Result<Event, axum::BoxError>>> {
+async fn handle_sse(
+    Extension(registry): Extension<SseRegistry>,
+) -> Sse<impl Stream<Item = Result<Event, axum::BoxError>>> {
     use axum_example::app::Count;
-    use futures::stream;
-    use leptos_sse::ServerSentEvents;
-    use std::time::Duration;
-    use tokio_stream::StreamExt as _;
     use futures::StreamExt;
 
     log::info!("SSE connection established");
 
-    let mut value = 0;
-    let stream = ServerSentEvents::new(
-        "counter",
-        stream::repeat_with(move || {
-            let curr = value;
-            value += 1;
-            log::debug!("Sending count: {}", curr);
-            Ok(Count { value: curr })
-        })
-        .throttle(Duration::from_secs(1)),
-    )
-    .unwrap();
+    let stream = registry.events::<Count>("counter").unwrap();
     
     // Log the first few events for debugging
     let stream = stream.inspect(|event| {
@@ -0,0 +1,46 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, DeriveInput, LitStr};
+
+/// Derives `SignalName`, naming the signal after the type, or after the `#[sse(name = "...")]`
+/// attribute.
+#[proc_macro_derive(SignalName, attributes(sse))]
+pub fn derive_signal_name(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    signal_name(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn signal_name(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let mut name = None;
+    for attr in &input.attrs {
+        if !attr.path().is_ident("sse") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                name = Some(meta.value()?.parse::<LitStr>()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `name`"))
+            }
+        })?;
+    }
+    // Without a name, the default of the trait names the signal after the type
+    let body = name.map(|name| {
+        quote! {
+            fn signal_name() -> ::std::borrow::Cow<'static, str> {
+                ::std::borrow::Cow::Borrowed(#name)
+            }
+        }
+    });
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::leptos_sse::SignalName for #ident #ty_generics #where_clause {
+            #body
+        }
+    })
+}
@@ -0,0 +1,170 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+/// The query parameter with the id a client acknowledges updates with.
+pub(crate) const CLIENT_PARAM: &str = "sse_client";
+
+/// The acknowledgement of the updates a client applied.
+///
+/// Clients with an [`ack_url`](crate::SseOptions::ack_url) post it there shortly after
+/// applying updates. The server passes it to `SseRegistry::ack`, which records the updates the
+/// connections of the client received.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SseAck {
+    /// The id of the client, see [`SseClientId`].
+    pub client: String,
+    /// The sequence number of the last update applied, by signal name.
+    pub seqs: BTreeMap<String, u64>,
+}
+
+/// The signals a client subscribed to at runtime.
+///
+/// Clients with a [`subscription_url`](crate::SseOptions::subscription_url) post it there
+/// whenever a signal created with
+/// [`create_sse_dynamic_signal`](crate::create_sse_dynamic_signal) is the first or last one of
+/// its name, and when the connection opens. The server passes it to
+/// `SseRegistry::update_subscription`, which opens and closes the streams of the signals in
+/// the response of `SseRegistry::dynamic_events`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SseSubscription {
+    /// The id of the client, see [`SseClientId`].
+    pub client: String,
+    /// The names of every signal the client subscribed to, replacing the previous ones.
+    pub signals: BTreeSet<String>,
+}
+
+/// The id of a client acknowledging updates, from the query string of an SSE request.
+///
+/// Extract it in the SSE handler and pass it to `SseConnection::set_client`, so that the
+/// acknowledgements of the client are recorded for the connection. It is `None` for clients
+/// with neither an [`ack_url`](crate::SseOptions::ack_url) nor a
+/// [`subscription_url`](crate::SseOptions::subscription_url).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SseClientId(pub Option<String>);
+
+impl SseClientId {
+    /// Reads the id of the client from the query string of an SSE request.
+    ///
+    /// ```
+    /// use leptos_sse::SseClientId;
+    ///
+    /// let client = SseClientId::from_query(Some("sse_client=4f2a"));
+    /// assert_eq!(client.0.as_deref(), Some("4f2a"));
+    /// ```
+    pub fn from_query(query: Option<&str>) -> Self {
+        let client = query
+            .unwrap_or_default()
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == CLIENT_PARAM)
+            .map(|(_, client)| client.to_string());
+        SseClientId(client)
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "client"))]
+pub(crate) mod beacon {
+    use std::cell::{Cell, OnceCell};
+    use std::collections::BTreeSet;
+    use std::time::Duration;
+
+    use js_sys::Array;
+    use leptos::prelude::set_timeout;
+    use wasm_bindgen::JsValue;
+    use web_sys::{Blob, BlobPropertyBag};
+
+    use serde::Serialize;
+
+    use super::{SseAck, SseSubscription, CLIENT_PARAM};
+    use crate::{connection, SIGNAL_META};
+
+    /// How long to wait after an update before acknowledging it, so bursts of updates are
+    /// acknowledged at once.
+    const ACK_DELAY: Duration = Duration::from_millis(250);
+
+    thread_local! {
+        static CLIENT: OnceCell<String> = const { OnceCell::new() };
+        static PENDING: Cell<bool> = const { Cell::new(false) };
+    }
+
+    /// Returns the random id of this client, which lasts until the page is reloaded.
+    fn client() -> String {
+        CLIENT.with(|client| {
+            let id = || format!("{:013x}", (js_sys::Math::random() * 2f64.powi(52)) as u64);
+            client.get_or_init(id).clone()
+        })
+    }
+
+    /// Appends the id of this client to the SSE url.
+    pub(crate) fn identify(url: &str) -> String {
+        let separator = if url.contains('?') { '&' } else { '?' };
+        format!("{url}{separator}{CLIENT_PARAM}={}", client())
+    }
+
+    /// Schedules the last applied updates to be acknowledged, if there is an ack url.
+    pub(crate) fn schedule() {
+        if connection::ack_url().is_none() || PENDING.with(|pending| pending.replace(true)) {
+            return;
+        }
+        set_timeout(
+            || {
+                PENDING.with(|pending| pending.set(false));
+                if let Err(err) = send() {
+                    leptos::logging::warn!("failed to acknowledge updates: {:?}", err);
+                }
+            },
+            ACK_DELAY,
+        );
+    }
+
+    fn send() -> Result<(), JsValue> {
+        let Some(url) = connection::ack_url() else {
+            return Ok(());
+        };
+        let seqs = SIGNAL_META.with(|meta| {
+            let meta = meta.borrow();
+            let seqs = meta
+                .iter()
+                .filter_map(|(name, meta)| Some((name.to_string(), meta.seq?)));
+            seqs.collect()
+        });
+        let ack = SseAck {
+            client: client(),
+            seqs,
+        };
+        post(&url, &ack)
+    }
+
+    /// Posts the names of the signals this client subscribed to, if there is a subscription
+    /// url.
+    pub(crate) fn subscribe(signals: BTreeSet<String>) {
+        let Some(url) = connection::subscription_url() else {
+            return;
+        };
+        let subscription = SseSubscription {
+            client: client(),
+            signals,
+        };
+        if let Err(err) = post(&url, &subscription) {
+            leptos::logging::warn!("failed to update the subscriptions: {:?}", err);
+        }
+    }
+
+    /// Posts `message` as json to `url` with `navigator.sendBeacon`.
+    fn post(url: &str, message: &impl Serialize) -> Result<(), JsValue> {
+        let json =
+            serde_json::to_string(message).map_err(|err| JsValue::from_str(&err.to_string()))?;
+        let options = BlobPropertyBag::new();
+        options.set_type("application/json");
+        let blob = Blob::new_with_str_sequence_and_options(
+            &Array::of1(&JsValue::from_str(&json)),
+            &options,
+        )?;
+        let window = web_sys::window().ok_or_else(|| JsValue::from_str("no window"))?;
+        window
+            .navigator()
+            .send_beacon_with_opt_blob(url, Some(&blob))?;
+        Ok(())
+    }
+}
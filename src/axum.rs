@@ -1,17 +1,111 @@
 use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::fmt;
+use std::future::Future;
+use std::ops::Deref;
 use std::pin::Pin;
-use std::task::Poll;
+use std::sync::atomic::{AtomicIsize, Ordering};
+use std::sync::Arc;
+use std::task::{ready, Poll};
+use std::time::Duration;
 
+#[cfg(feature = "compression")]
+use axum::body::{Body, Bytes};
+use axum::extract::{FromRef, FromRequestParts, Path, Request, State};
+use axum::http::request::Parts;
+#[cfg(feature = "compression")]
+use axum::http::HeaderValue;
+use axum::http::{header, StatusCode};
+use axum::middleware::Next;
 use axum::response::sse::Event;
-use futures::stream::{Stream, StreamExt, TryStream};
+#[cfg(feature = "testing")]
+use axum::response::sse::Sse;
+use axum::response::{IntoResponse, Response};
+use axum::{routing::get, Json, Router};
+use futures::future;
+use futures::stream::{self, Stream, StreamExt, TryStream, TryStreamExt};
 use pin_project_lite::pin_project;
 use serde::Serialize;
 use serde_json::Value;
 use tokio::sync::mpsc;
 pub use tokio::sync::mpsc::error::{SendError, TrySendError};
+use tokio::time::{Instant, Interval, MissedTickBehavior, Sleep};
 use tokio_stream::wrappers::ReceiverStream;
 
-use crate::ServerSignalUpdate;
+use crate::chunk::{self, CHUNK_EVENT};
+#[cfg(feature = "compression")]
+use crate::compression;
+use crate::control::{
+    heartbeat_data, SignalFailure, BATCH_EVENT, FAILURE_EVENT, HEARTBEAT_EVENT, RECONNECT_EVENT,
+};
+use crate::encoder::{TraceContext, UpdateEncoder};
+use crate::float;
+use crate::registry::{to_json, SignalProjection, Subscriber, SubscriptionStats, UpdateBatch};
+#[cfg(feature = "encryption")]
+use crate::SessionKey;
+#[cfg(feature = "compression")]
+use crate::SseCompression;
+#[cfg(feature = "testing")]
+use crate::SseRecorder;
+use crate::{
+    AllowedOrigins, EventIds, NonFinite, OnError, PatchRewrite, ServerSignalUpdate, SignalCodec,
+    SignalDescriptor, SignalName, SseChannel, SseClientId, SseConnection, SseFormat, SseRegistry,
+    SseScope,
+};
+#[cfg(feature = "inspector")]
+use crate::{ConnectionInfo, ProducerInfo, SignalStats};
+
+/// The item type of [`ServerSentEvents`] streams.
+pub type ServerSentEventsItem = Result<Event, axum::BoxError>;
+
+/// The error of a [`ServerSentEvents`] stream, boxed into its [`ServerSentEventsItem`]s.
+///
+/// ```ignore
+/// if let Some(SseError::Serialize(err)) = err.downcast_ref::<SseError>() {
+///     log::error!("Can't serialize the value: {err}");
+/// }
+/// ```
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SseError {
+    /// The stream of values failed.
+    Stream(axum::BoxError),
+    /// A value couldn't be serialized.
+    Serialize(serde_json::Error),
+    /// An update was larger than the limit set with [`ServerSentEvents::max_message_size`].
+    TooLarge {
+        /// The size of the encoded update, in bytes.
+        size: usize,
+        /// The limit, in bytes.
+        limit: usize,
+    },
+}
+
+impl fmt::Display for SseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SseError::Stream(err) => write!(f, "stream failed: {err}"),
+            SseError::Serialize(err) => write!(f, "serialization failed: {err}"),
+            SseError::TooLarge { size, limit } => {
+                write!(
+                    f,
+                    "update of {size} bytes exceeds the limit of {limit} bytes"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for SseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SseError::Stream(err) => Some(err.as_ref()),
+            SseError::Serialize(err) => Some(err),
+            SseError::TooLarge { .. } => None,
+        }
+    }
+}
 
 pin_project! {
     /// A signal owned by the server which writes to the SSE when mutated.
@@ -21,6 +115,22 @@ pin_project! {
         #[pin]
         stream: S,
         json_value: Value,
+        seq: u64,
+        schema_version: Option<u32>,
+        encoder: UpdateEncoder,
+        queued: Option<Arc<AtomicIsize>>,
+        tracking: Option<(SseConnection, Arc<SubscriptionStats>)>,
+        subscriber: Option<Subscriber>,
+        chunk_size: Option<usize>,
+        max_message_size: Option<usize>,
+        chunks: VecDeque<String>,
+        event_ids: Option<EventIds>,
+        chunk_id: Option<String>,
+        non_finite: NonFinite,
+        rewrite: Option<PatchRewrite>,
+        projection: Option<SignalProjection>,
+        on_error: OnError,
+        terminated: bool,
     }
 }
 
@@ -37,9 +147,217 @@ impl<S> ServerSentEvents<S> {
             name: name.into(),
             stream,
             json_value: serde_json::to_value(T::default())?,
+            seq: 0,
+            schema_version: None,
+            encoder: UpdateEncoder::default(),
+            queued: None,
+            tracking: None,
+            subscriber: None,
+            chunk_size: None,
+            max_message_size: None,
+            chunks: VecDeque::new(),
+            event_ids: None,
+            chunk_id: None,
+            non_finite: NonFinite::default(),
+            rewrite: None,
+            projection: None,
+            on_error: OnError::default(),
+            terminated: false,
+        })
+    }
+
+    /// Create a new [`ServerSentEvents`] a stream, converting its values with a [`SignalCodec`].
+    ///
+    /// Clients must receive the signal with the same codec, see
+    /// [`create_sse_signal_with_codec`](crate::create_sse_signal_with_codec).
+    pub fn with_codec<T, C>(
+        name: impl Into<Cow<'static, str>>,
+        stream: S,
+    ) -> Result<
+        ServerSentEvents<impl TryStream<Ok = Value, Error = axum::BoxError>>,
+        serde_json::Error,
+    >
+    where
+        T: Default,
+        C: SignalCodec<T>,
+        S: TryStream<Ok = T, Error = axum::BoxError>,
+    {
+        let stream = stream.and_then(|value| future::ready(C::encode(&value).map_err(Into::into)));
+        Ok(ServerSentEvents {
+            json_value: C::encode(&T::default())?,
+            ..ServerSentEvents::new::<Value>(name, stream)?
         })
     }
 
+    /// Create a new [`ServerSentEvents`] a stream, named after the type `T`.
+    ///
+    /// See [`SignalName`] for how the name is derived.
+    pub fn by_type<T>(stream: S) -> Result<Self, serde_json::Error>
+    where
+        T: SignalName + Default + Serialize,
+        S: TryStream<Ok = T, Error = axum::BoxError>,
+    {
+        Self::new(T::signal_name(), stream)
+    }
+
+    /// Signs every update with `key`, see [`ServerSignalUpdate::sign`].
+    ///
+    /// Use a shared key, or a per-session key handed to the client over an authenticated
+    /// server function.
+    #[cfg(feature = "signing")]
+    pub fn signed(mut self, key: impl Into<Arc<[u8]>>) -> Self {
+        self.encoder.signing_key = Some(key.into());
+        self
+    }
+
+    /// Encrypts every update with `key`, see [`SessionKey`].
+    ///
+    /// Encrypted updates are signed first if [`ServerSentEvents::signed`] is used too.
+    #[cfg(feature = "encryption")]
+    pub fn encrypted(mut self, key: SessionKey) -> Self {
+        self.encoder.encrypt_with(key);
+        self
+    }
+
+    /// Sends the first update, which contains the whole document, as base64 encoded CBOR.
+    ///
+    /// Initial syncs of large documents dominate the bandwidth of a stream, and CBOR is much
+    /// more compact than json for them. The later updates are still json patches. Clients need
+    /// the `cbor` feature too.
+    ///
+    /// Snapshots aren't split into chunks, and signed or encrypted streams send the first update
+    /// as usual.
+    #[cfg(feature = "cbor")]
+    pub fn cbor_snapshots(mut self) -> Self {
+        self.encoder.cbor_snapshots = true;
+        self
+    }
+
+    /// Sends the updates in `format`, usually negotiated with the client, see [`SseFormat`].
+    ///
+    /// Updates in other formats than json aren't split into chunks, and signed or encrypted
+    /// streams always send json.
+    pub fn format(mut self, format: SseFormat) -> Self {
+        self.encoder.format = format;
+        self
+    }
+
+    /// Interns the name of the signal as `id`, which must be unique among the streams of the
+    /// response.
+    ///
+    /// The first update of the stream carries both the name and the id, and the following json
+    /// updates only the id, which saves bytes on every message, and lets clients look the
+    /// signal up by a number rather than by parsing and hashing its name. This matters for apps
+    /// with hundreds of signals, or with long names. Clients older than this version don't
+    /// understand interned updates.
+    ///
+    /// ```ignore
+    /// let counter = ServerSentEvents::new("counter", counts())?.intern(0);
+    /// let orders = ServerSentEvents::new("orders", orders())?.intern(1);
+    /// Sse::new(stream::select(counter, orders))
+    /// ```
+    pub fn intern(mut self, id: u32) -> Self {
+        self.encoder.intern = Some(id);
+        self
+    }
+
+    /// Attaches the W3C trace context returned by `context`, as a `traceparent` header value,
+    /// to every update, see [`ServerSignalUpdate::with_trace_context`].
+    ///
+    /// `context` is called as each update is encoded, so it usually reads the context of the
+    /// span current in the task producing the values. The streams created with
+    /// [`SseRegistry::events`] send the context of the transaction which published each value
+    /// instead, see [`Transaction::trace_context`](crate::Transaction::trace_context).
+    ///
+    /// ```ignore
+    /// let counter = ServerSentEvents::new("counter", counts())?
+    ///     .trace_context(|| current_traceparent());
+    /// ```
+    pub fn trace_context(
+        mut self,
+        context: impl Fn() -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.encoder.trace_context = Some(TraceContext::new(context));
+        self
+    }
+
+    /// Declares the schema version of the documents sent by this stream.
+    ///
+    /// Clients expecting another version migrate the documents, see
+    /// [`SseSchema`](crate::SseSchema). So that any client can apply it, the first update of a
+    /// versioned stream replaces the whole document rather than patching `T::default()`.
+    pub fn schema_version(mut self, version: u32) -> Self {
+        self.schema_version = Some(version);
+        if self.seq == 0 {
+            self.json_value = Value::Null;
+        }
+        self
+    }
+
+    /// Splits updates larger than `size` bytes into several `chunk` events.
+    ///
+    /// The first update of a stream contains the whole document, which may be too large for
+    /// proxies or browsers to handle comfortably in a single event. Clients reassemble the
+    /// chunks, and can report progress with
+    /// [`on_sse_chunk_progress`](crate::on_sse_chunk_progress).
+    pub fn chunked(mut self, size: usize) -> Self {
+        self.chunk_size = Some(size);
+        self
+    }
+
+    /// Rejects updates larger than `size` bytes once encoded, before they are chunked.
+    ///
+    /// A rejected update isn't sent, and is handled like any other error, see [`OnError`]. The
+    /// next update is diffed from the last value which was sent, so clients stay in step. This
+    /// keeps clients from having to parse arbitrarily large messages, which they can also
+    /// refuse with `SseOptions::max_message_size`.
+    pub fn max_message_size(mut self, size: usize) -> Self {
+        self.max_message_size = Some(size);
+        self
+    }
+
+    /// Sets how non-finite floats in the values are encoded, see [`NonFinite`].
+    ///
+    /// With [`NonFinite::Error`], a value containing one ends the stream with an error.
+    pub fn non_finite(mut self, non_finite: NonFinite) -> Self {
+        self.non_finite = non_finite;
+        self
+    }
+
+    /// Rewrites the patches of this stream before they are sent, see [`PatchRewrite`].
+    pub fn rewrite(mut self, rewrite: PatchRewrite) -> Self {
+        self.rewrite = Some(rewrite);
+        self
+    }
+
+    /// Sets the `id` field of the update events with `ids`, see [`EventIds`].
+    pub fn event_ids(mut self, ids: EventIds) -> Self {
+        self.event_ids = Some(ids);
+        self
+    }
+
+    /// Sets what the stream does when a value fails or can't be serialized, see [`OnError`].
+    pub fn on_error(mut self, on_error: OnError) -> Self {
+        self.on_error = on_error;
+        self
+    }
+
+    /// Lists this stream under `connection` in its [`SseRegistry`](crate::SseRegistry).
+    ///
+    /// The stream ends when the connection is disconnected, and disconnects it when it ends
+    /// because of an error, see [`OnError`]. The values are projected for the connection if
+    /// the registry has a projection for the signal, see
+    /// [`SseRegistry::project`](crate::SseRegistry::project).
+    pub fn track(mut self, connection: &SseConnection) -> Self {
+        let stats = connection.subscribe(&self.name, self.queued.clone());
+        self.tracking = Some((connection.clone(), stats));
+        self.projection = connection.projection(&self.name);
+        if self.subscriber.is_none() {
+            self.subscriber = connection.subscriber(&self.name);
+        }
+        self
+    }
+
     /// Create a server-sent-events (SSE) channel pair.
     ///
     /// The `buffer` argument controls how many unsent messages can be stored without waiting.
@@ -60,7 +378,10 @@ impl<S> ServerSentEvents<S> {
     {
         let (sender, receiver) = mpsc::channel::<T>(buffer);
         let stream = ReceiverStream::new(receiver).map(Ok);
-        Ok((Sender(sender), ServerSentEvents::new(name, stream)?))
+        let queued = Arc::new(AtomicIsize::new(0));
+        let mut events = ServerSentEvents::new(name, stream)?;
+        events.queued = Some(queued.clone());
+        Ok((Sender(sender, queued), events))
     }
 }
 
@@ -69,35 +390,712 @@ where
     S: TryStream<Error = axum::BoxError>,
     S::Ok: Serialize,
 {
-    type Item = Result<Event, axum::BoxError>;
+    type Item = ServerSentEventsItem;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        if *this.terminated {
+            return Poll::Ready(None);
+        }
+        if let Some((connection, _)) = this.tracking {
+            if connection.is_closed() {
+                return Poll::Ready(None);
+            }
+        }
+        if let Some(chunk) = this.chunks.pop_front() {
+            let mut event = Event::default().event(CHUNK_EVENT).data(chunk);
+            // The last chunk completes the update
+            if this.chunks.is_empty() {
+                if let Some(id) = this.chunk_id.take() {
+                    event = event.id(id);
+                }
+            }
+            return Poll::Ready(Some(Ok(event)));
+        }
+        loop {
+            let err = match this.stream.as_mut().try_poll_next(cx) {
+                Poll::Ready(Some(Ok(value))) => {
+                    let mut encode = || -> Result<Event, SseError> {
+                        if let Some(queued) = this.queued {
+                            queued.fetch_sub(1, Ordering::Relaxed);
+                        }
+                        let mut new_json = float::to_value_with(&value, *this.non_finite)
+                            .map_err(SseError::Serialize)?;
+                        // Tracked streams record the value itself, not what the connection sees
+                        let raw = match (&this.projection, &this.tracking) {
+                            (Some(projection), Some((connection, _))) => {
+                                let raw = new_json.clone();
+                                projection.apply(connection, &mut new_json);
+                                Some(raw)
+                            }
+                            _ => None,
+                        };
+                        let mut update = ServerSignalUpdate::new_from_json::<S::Item>(
+                            this.name.clone(),
+                            this.json_value,
+                            &new_json,
+                        );
+                        if let Some(rewrite) = this.rewrite {
+                            rewrite.apply(&mut update.patch);
+                        }
+                        *this.seq += 1;
+                        let update = update.with_seq(*this.seq);
+                        let update = match *this.schema_version {
+                            Some(version) => update.with_version(version),
+                            None => update,
+                        };
+                        let doc = this.rewrite.is_none().then_some(&new_json);
+                        let encoded = this
+                            .encoder
+                            .encode(update, doc)
+                            .map_err(SseError::Serialize)?;
+                        if let Some(limit) = *this.max_message_size {
+                            if encoded.data.len() > limit {
+                                *this.seq -= 1;
+                                let size = encoded.data.len();
+                                return Err(SseError::TooLarge { size, limit });
+                            }
+                        }
+                        if let Some(subscriber) = this.subscriber {
+                            subscriber.record_sent(encoded.data.len());
+                        }
+                        let mut id = this
+                            .event_ids
+                            .as_ref()
+                            .map(|ids| ids.next(this.name, *this.seq));
+                        let event = match (encoded.event, *this.chunk_size) {
+                            (None, Some(size)) if encoded.data.len() > size => {
+                                this.chunks.extend(chunk::split(
+                                    this.name,
+                                    *this.seq,
+                                    &encoded.data,
+                                    size,
+                                ));
+                                *this.chunk_id = id.take();
+                                let chunk = this.chunks.pop_front().unwrap_or_default();
+                                Event::default().event(CHUNK_EVENT).data(chunk)
+                            }
+                            (None, _) => Event::default().data(encoded.data),
+                            (Some(event), _) => Event::default().event(event).data(encoded.data),
+                        };
+                        let event = match id {
+                            Some(id) => event.id(id),
+                            None => event,
+                        };
+                        *this.json_value = new_json;
+                        if let Some((connection, stats)) = this.tracking {
+                            let value = raw.as_ref().unwrap_or(this.json_value);
+                            // The sequence number first, for `SseRegistry::flush`
+                            stats.record_update(*this.seq);
+                            connection.record_value(this.name, value, stats);
+                        }
+                        Ok(event)
+                    };
+                    match encode() {
+                        Ok(event) => return Poll::Ready(Some(Ok(event))),
+                        Err(err) => err,
+                    }
+                }
+                Poll::Ready(Some(Err(err))) => match err.downcast::<SignalFailure>() {
+                    // The producer of a registry signal stopped, which the client reports
+                    Ok(failure) => {
+                        return Poll::Ready(Some(Ok(Event::default()
+                            .event(FAILURE_EVENT)
+                            .data(to_json(&failure)))))
+                    }
+                    Err(err) => SseError::Stream(err),
+                },
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+            match *this.on_error {
+                OnError::Comment => {
+                    let comment = err.to_string().replace(['\r', '\n'], " ");
+                    return Poll::Ready(Some(Ok(Event::default().comment(comment))));
+                }
+                OnError::Skip => continue,
+                _ => {
+                    if let Some((connection, _)) = this.tracking {
+                        connection.disconnect();
+                    }
+                    if *this.on_error == OnError::Terminate {
+                        *this.terminated = true;
+                        return Poll::Ready(None);
+                    }
+                    return Poll::Ready(Some(Err(err.into())));
+                }
+            }
+        }
+    }
+}
+
+pin_project! {
+    /// A stream of SSE events which ends after a maximum age, asking the client to reconnect.
+    ///
+    /// Load balancers and proxies often kill connections which have been open, or idle, for too
+    /// long, which browsers report as an error. Ending the stream before that with a `reconnect`
+    /// event makes the client open a new connection right away, without logging an error or
+    /// counting it as a failed attempt, and the signals resync on the new connection.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use axum::response::sse::Sse;
+    /// use futures::stream::Stream;
+    /// use leptos_sse::{MaxAge, ServerSentEventsItem};
+    ///
+    /// fn recycled(
+    ///     events: impl Stream<Item = ServerSentEventsItem> + Send + 'static,
+    /// ) -> Sse<impl Stream<Item = ServerSentEventsItem>> {
+    ///     Sse::new(MaxAge::new(events, Duration::from_secs(300)))
+    /// }
+    /// ```
+    #[derive(Debug)]
+    pub struct MaxAge<S> {
+        #[pin]
+        stream: S,
+        #[pin]
+        deadline: Sleep,
+        expired: bool,
+    }
+}
+
+impl<S> MaxAge<S> {
+    /// Wraps `stream`, ending it after `max_age`.
+    ///
+    /// This must be called within a Tokio runtime, such as in a request handler.
+    pub fn new(stream: S, max_age: Duration) -> Self {
+        MaxAge {
+            stream,
+            deadline: tokio::time::sleep(max_age),
+            expired: false,
+        }
+    }
+}
+
+impl<S> Stream for MaxAge<S>
+where
+    S: Stream<Item = ServerSentEventsItem>,
+{
+    type Item = ServerSentEventsItem;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        if *this.expired {
+            return Poll::Ready(None);
+        }
+        if this.deadline.poll(cx).is_ready() {
+            *this.expired = true;
+            return Poll::Ready(Some(Ok(Event::default().event(RECONNECT_EVENT).data("{}"))));
+        }
+        this.stream.poll_next(cx)
+    }
+}
+
+pin_project! {
+    /// A stream of SSE events which sends a `heartbeat` event carrying the server time whenever
+    /// no event was sent for an interval.
+    ///
+    /// Like a keep-alive, the heartbeats keep proxies from closing an idle connection, and let
+    /// clients detect a stalled one with `SseOptions::heartbeat_timeout`. The client also
+    /// estimates the offset of its clock from the server time, see `use_sse_clock_offset`, so
+    /// that durations computed from server timestamps are accurate even when its clock is not.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use axum::response::sse::Sse;
+    /// use futures::stream::Stream;
+    /// use leptos_sse::{Heartbeat, ServerSentEventsItem};
+    ///
+    /// fn with_heartbeats(
+    ///     events: impl Stream<Item = ServerSentEventsItem> + Send + 'static,
+    /// ) -> Sse<impl Stream<Item = ServerSentEventsItem>> {
+    ///     Sse::new(Heartbeat::new(events, Duration::from_secs(10)))
+    /// }
+    /// ```
+    #[derive(Debug)]
+    pub struct Heartbeat<S> {
+        #[pin]
+        stream: S,
+        interval: Interval,
+    }
+}
+
+impl<S> Heartbeat<S> {
+    /// Wraps `stream`, sending a heartbeat after every `interval` without events.
+    ///
+    /// This must be called within a Tokio runtime, such as in a request handler.
+    pub fn new(stream: S, interval: Duration) -> Self {
+        let mut interval = tokio::time::interval_at(Instant::now() + interval, interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        Heartbeat { stream, interval }
+    }
+}
+
+impl<S> Stream for Heartbeat<S>
+where
+    S: Stream<Item = ServerSentEventsItem>,
+{
+    type Item = ServerSentEventsItem;
 
     fn poll_next(
         self: Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> Poll<Option<Self::Item>> {
         let this = self.project();
-        match this.stream.try_poll_next(cx) {
-            Poll::Ready(Some(Ok(value))) => {
-                let new_json = serde_json::to_value(value)?;
-                let update = ServerSignalUpdate::new_from_json::<S::Item>(
-                    this.name.clone(),
-                    this.json_value,
-                    &new_json,
-                );
-                *this.json_value = new_json;
-                let event = Event::default().json_data(update)?;
-                Poll::Ready(Some(Ok(event)))
+        match this.stream.poll_next(cx) {
+            Poll::Ready(Some(item)) => {
+                this.interval.reset();
+                Poll::Ready(Some(item))
             }
-            Poll::Ready(Some(Err(err))) => Poll::Ready(Some(Err(err))),
             Poll::Ready(None) => Poll::Ready(None),
-            Poll::Pending => Poll::Pending,
+            Poll::Pending => {
+                ready!(this.interval.poll_tick(cx));
+                Poll::Ready(Some(Ok(Event::default()
+                    .event(HEARTBEAT_EVENT)
+                    .data(heartbeat_data()))))
+            }
+        }
+    }
+}
+
+impl<T> SseChannel<T> {
+    /// Create a new [`ServerSentEvents`] for this channel from a stream.
+    pub fn stream<S>(&self, stream: S) -> Result<ServerSentEvents<S>, serde_json::Error>
+    where
+        T: Default + Serialize,
+        S: TryStream<Ok = T, Error = axum::BoxError>,
+    {
+        ServerSentEvents::new(self.name(), stream)
+    }
+
+    /// Create a server-sent-events (SSE) channel pair for this channel.
+    ///
+    /// See [`ServerSentEvents::channel`].
+    #[allow(clippy::type_complexity)]
+    pub fn updater(
+        &self,
+        buffer: usize,
+    ) -> Result<
+        (
+            Sender<T>,
+            ServerSentEvents<impl TryStream<Ok = T, Error = axum::BoxError>>,
+        ),
+        serde_json::Error,
+    >
+    where
+        T: Default + Serialize,
+    {
+        ServerSentEvents::<()>::channel(self.name(), buffer)
+    }
+}
+
+impl AllowedOrigins {
+    /// Axum middleware rejecting requests from origins which aren't allowed with
+    /// `403 Forbidden`.
+    ///
+    /// ```
+    /// use axum::{middleware, routing::get, Router};
+    /// use leptos_sse::AllowedOrigins;
+    ///
+    /// # async fn handle_sse() {}
+    /// let allowed = AllowedOrigins::new(["https://example.com"]);
+    /// let app: Router = Router::new().route(
+    ///     "/sse",
+    ///     get(handle_sse).layer(middleware::from_fn_with_state(
+    ///         allowed,
+    ///         AllowedOrigins::middleware,
+    ///     )),
+    /// );
+    /// ```
+    pub async fn middleware(State(allowed): State<Self>, request: Request, next: Next) -> Response {
+        let headers = request.headers();
+        let origin = headers
+            .get(header::ORIGIN)
+            .and_then(|value| value.to_str().ok());
+        let referer = headers
+            .get(header::REFERER)
+            .and_then(|value| value.to_str().ok());
+        if allowed.is_allowed(origin, referer) {
+            next.run(request).await
+        } else {
+            StatusCode::FORBIDDEN.into_response()
         }
     }
 }
 
+#[cfg(feature = "compression")]
+impl SseCompression {
+    /// Axum middleware compressing SSE responses for clients which accept gzip, see
+    /// [`SseCompression`].
+    ///
+    /// Other responses are left as they are.
+    ///
+    /// ```
+    /// use axum::{middleware, routing::get, Router};
+    /// use leptos_sse::SseCompression;
+    ///
+    /// # async fn handle_sse() {}
+    /// let app: Router = Router::new().route(
+    ///     "/sse",
+    ///     get(handle_sse).layer(middleware::from_fn_with_state(
+    ///         SseCompression::new(),
+    ///         SseCompression::middleware,
+    ///     )),
+    /// );
+    /// ```
+    pub async fn middleware(
+        State(compression): State<Self>,
+        request: Request,
+        next: Next,
+    ) -> Response {
+        let accepted = compression.is_accepted(
+            request
+                .headers()
+                .get(header::ACCEPT_ENCODING)
+                .and_then(|value| value.to_str().ok()),
+        );
+        let mut response = next.run(request).await;
+        let headers = response.headers_mut();
+        let content_type = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok());
+        if !compression::is_event_stream(content_type) {
+            return response;
+        }
+        headers.append(header::VARY, HeaderValue::from_static("accept-encoding"));
+        if !accepted || headers.contains_key(header::CONTENT_ENCODING) {
+            return response;
+        }
+        headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+        headers.remove(header::CONTENT_LENGTH);
+        let gzip = compression.encoder();
+        response.map(|body| {
+            let frames = body.into_data_stream();
+            Body::from_stream(stream::unfold(
+                (frames, Some(gzip)),
+                |(mut frames, gzip)| async move {
+                    let mut gzip = gzip?;
+                    match frames.next().await {
+                        Some(Ok(frame)) => {
+                            let frame = Bytes::from(gzip.frame(&frame));
+                            Some((Ok(frame), (frames, Some(gzip))))
+                        }
+                        Some(Err(err)) => Some((Err(err), (frames, None))),
+                        None => Some((Ok(Bytes::from(gzip.finish())), (frames, None))),
+                    }
+                },
+            ))
+        })
+    }
+}
+
+impl SseRegistry {
+    /// Create a new [`ServerSentEvents`] sending the values published for the signal `name`,
+    /// see [`SseRegistry::publish`].
+    ///
+    /// ```
+    /// use axum::response::sse::Sse;
+    /// use leptos_sse::SseRegistry;
+    ///
+    /// # #[derive(Default, serde::Serialize)]
+    /// # struct Count { value: i32 }
+    /// let registry = SseRegistry::new();
+    /// let events = registry.events::<Count>("counter").unwrap();
+    /// let sse = Sse::new(events);
+    ///
+    /// // Anywhere else, such as in a server function
+    /// registry.publish("counter", &Count { value: 1 }).unwrap();
+    /// ```
+    pub fn events<T>(
+        &self,
+        name: impl Into<Cow<'static, str>>,
+    ) -> Result<
+        ServerSentEvents<impl TryStream<Ok = Value, Error = axum::BoxError>>,
+        serde_json::Error,
+    >
+    where
+        T: Default + Serialize,
+    {
+        let name = name.into();
+        let failures = self.failures(&name).map(|failure| Err(failure.into()));
+        let stream = stream::select(self.values(&name).map(Ok), failures);
+        let mut events = ServerSentEvents {
+            json_value: serde_json::to_value(T::default())?,
+            subscriber: Some(self.subscriber(&name)),
+            rewrite: self.rewrite_of(&name),
+            ..ServerSentEvents::new::<Value>(name.clone(), stream)?
+        };
+        events.encoder.trace_context = Some(self.trace_context(&name));
+        Ok(events)
+    }
+
+    /// Create a stream sending the values published for the signals `client` subscribed to at
+    /// runtime, see [`SseRegistry::update_subscription`].
+    ///
+    /// The client subscribes to the signals it creates with
+    /// [`create_sse_dynamic_signal`](crate::create_sse_dynamic_signal), whose names may be
+    /// computed from user input. Every signal is streamed from its latest value when the client
+    /// subscribes to it, as with [`SseRegistry::events`], and the stream of a signal is dropped
+    /// when the client unsubscribes. Merge it with the streams of the other signals:
+    ///
+    /// ```ignore
+    /// let client = SseClientId::from_query(query.as_deref());
+    /// let events = stream::select(
+    ///     registry.events::<Count>("counter")?,
+    ///     registry.dynamic_events(&client),
+    /// );
+    /// ```
+    pub fn dynamic_events(
+        &self,
+        client: &SseClientId,
+    ) -> impl Stream<Item = ServerSentEventsItem> + Send + 'static {
+        let registry = self.clone();
+        self.dynamic(client, move |name| {
+            registry
+                .events::<Value>(name.to_string())
+                .expect("json values serialize")
+        })
+    }
+
+    /// Create a stream sending the values published for the signals `names`, sending the values
+    /// published by a transaction in a single event, see [`SseRegistry::transaction`].
+    pub fn batched_events<I>(&self, names: I) -> impl Stream<Item = ServerSentEventsItem>
+    where
+        I: IntoIterator,
+        I::Item: Into<Cow<'static, str>>,
+    {
+        let names = names.into_iter().map(Into::into).collect();
+        self.batches(names).map(|batch| Ok(batch.into()))
+    }
+}
+
+impl From<ServerSignalUpdate> for Event {
+    /// Converts an update into an SSE event, for streams composed without
+    /// [`ServerSentEvents`].
+    ///
+    /// Clients apply the update on top of their current value, so it must patch the value the
+    /// stream last sent.
+    fn from(update: ServerSignalUpdate) -> Self {
+        Event::default().data(to_json(&update))
+    }
+}
+
+impl From<UpdateBatch> for Event {
+    fn from(batch: UpdateBatch) -> Self {
+        match <[_; 1]>::try_from(batch.0) {
+            Ok([update]) => update.into(),
+            Err(updates) => Event::default().event(BATCH_EVENT).data(to_json(&updates)),
+        }
+    }
+}
+
+/// Extracts the [`SseRegistry`] from the state of the router, so that any handler can update
+/// signals.
+///
+/// The state must provide the registry through [`FromRef`], which holds when the registry is
+/// the state itself, or a field of a state deriving `FromRef`.
+///
+/// ```
+/// use axum::{routing::post, Router};
+/// use leptos_sse::{SseRegistry, SseSignals};
+///
+/// # #[derive(Default, serde::Serialize, serde::Deserialize)]
+/// # struct Count { value: i32 }
+/// async fn increment(signals: SseSignals) {
+///     signals
+///         .update("counter", |count: &mut Count| count.value += 1)
+///         .unwrap();
+/// }
+///
+/// let app: Router = Router::new()
+///     .route("/increment", post(increment))
+///     .with_state(SseRegistry::new());
+/// ```
+#[derive(Clone, Debug)]
+pub struct SseSignals(pub SseRegistry);
+
+impl Deref for SseSignals {
+    type Target = SseRegistry;
+
+    fn deref(&self) -> &SseRegistry {
+        &self.0
+    }
+}
+
+impl<S> FromRequestParts<S> for SseSignals
+where
+    SseRegistry: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(_parts: &mut Parts, state: &S) -> Result<Self, Infallible> {
+        Ok(SseSignals(SseRegistry::from_ref(state)))
+    }
+}
+
+impl<S> FromRequestParts<S> for SseScope
+where
+    S: Send + Sync,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, StatusCode> {
+        parts
+            .extensions
+            .get::<SseScope>()
+            .cloned()
+            .ok_or(StatusCode::UNAUTHORIZED)
+    }
+}
+
+impl<S> FromRequestParts<S> for SseFormat
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Infallible> {
+        Ok(SseFormat::negotiate(parts.uri.query()))
+    }
+}
+
+impl<S> FromRequestParts<S> for SseClientId
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Infallible> {
+        Ok(SseClientId::from_query(parts.uri.query()))
+    }
+}
+
+#[cfg(feature = "testing")]
+impl SseRecorder {
+    /// Records the updates of `stream` until it ends or fails.
+    pub async fn record<S>(stream: S) -> Self
+    where
+        S: Stream<Item = ServerSentEventsItem> + Send + 'static,
+    {
+        Self::record_n(stream, usize::MAX).await
+    }
+
+    /// Records the first `count` updates of `stream`, or fewer if it ends or fails before.
+    pub async fn record_n<S>(stream: S, count: usize) -> Self
+    where
+        S: Stream<Item = ServerSentEventsItem> + Send + 'static,
+    {
+        let body = Sse::new(stream)
+            .into_response()
+            .into_body()
+            .into_data_stream()
+            .map(|bytes| {
+                bytes
+                    .map(|bytes| bytes.to_vec())
+                    .map_err(|err| err.to_string())
+            });
+        SseRecorder::record_body(body, count).await
+    }
+}
+
+impl SseRegistry {
+    /// Returns a router listing the signals of the registry as json at `/discovery`, see
+    /// [`SseRegistry::signal_descriptors`], and serving the JSON Schema of a signal at
+    /// `/schema/{name}`.
+    ///
+    /// Clients fetch the list with
+    /// [`discover_sse_signals`](crate::discover_sse_signals):
+    ///
+    /// ```ignore
+    /// let app = Router::new()
+    ///     .route("/sse", get(handle_sse))
+    ///     .nest("/sse", registry.discovery());
+    /// ```
+    pub fn discovery<S>(&self) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new()
+            .route("/discovery", get(discover_signals))
+            .route("/schema/{name}", get(signal_schema))
+            .with_state(self.clone())
+    }
+}
+
+async fn discover_signals(State(registry): State<SseRegistry>) -> Json<Vec<SignalDescriptor>> {
+    Json(registry.signal_descriptors())
+}
+
+async fn signal_schema(
+    State(registry): State<SseRegistry>,
+    Path(name): Path<String>,
+) -> Result<Json<Value>, StatusCode> {
+    registry
+        .signal_schema(&name)
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+#[cfg(feature = "inspector")]
+impl SseRegistry {
+    /// Returns a router serving the open connections as json at `/connections`, the latest value
+    /// of a signal at `/state/{name}`, the statistics of the signals at `/signals`, and the
+    /// producers and their failures at `/producers`.
+    ///
+    /// Anyone who can reach these routes can see who is connected, so protect them with an
+    /// authentication layer:
+    ///
+    /// ```ignore
+    /// let app = Router::new()
+    ///     .route("/sse", get(handle_sse))
+    ///     .nest("/sse", registry.inspector().layer(require_admin));
+    /// ```
+    pub fn inspector<S>(&self) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new()
+            .route("/connections", get(inspect_connections))
+            .route("/state/{name}", get(inspect_state))
+            .route("/signals", get(inspect_signals))
+            .route("/producers", get(inspect_producers))
+            .with_state(self.clone())
+    }
+}
+
+#[cfg(feature = "inspector")]
+async fn inspect_connections(State(registry): State<SseRegistry>) -> Json<Vec<ConnectionInfo>> {
+    Json(registry.connections())
+}
+
+#[cfg(feature = "inspector")]
+async fn inspect_signals(State(registry): State<SseRegistry>) -> Json<Vec<SignalStats>> {
+    Json(registry.signal_stats())
+}
+
+#[cfg(feature = "inspector")]
+async fn inspect_producers(State(registry): State<SseRegistry>) -> Json<Vec<ProducerInfo>> {
+    Json(registry.producers())
+}
+
+#[cfg(feature = "inspector")]
+async fn inspect_state(
+    State(registry): State<SseRegistry>,
+    Path(name): Path<String>,
+) -> Result<Json<Value>, StatusCode> {
+    registry.value(&name).map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
 /// Sender half of a server-sent events stream.
 #[derive(Clone, Debug)]
-pub struct Sender<T>(mpsc::Sender<T>);
+pub struct Sender<T>(mpsc::Sender<T>, Arc<AtomicIsize>);
 
 impl<T> Sender<T> {
     /// Send an SSE message.
@@ -105,7 +1103,10 @@ impl<T> Sender<T> {
     where
         T: Serialize,
     {
-        self.0.send(value).await
+        self.0.send(value).await?;
+        // Counted after sending, so cancelling the send can't leave the count off
+        self.1.fetch_add(1, Ordering::Relaxed);
+        Ok(())
     }
 
     /// Attempts to immediately send an SSE message.
@@ -113,6 +1114,8 @@ impl<T> Sender<T> {
     where
         T: Serialize,
     {
-        self.0.try_send(value)
+        self.0.try_send(value)?;
+        self.1.fetch_add(1, Ordering::Relaxed);
+        Ok(())
     }
 }
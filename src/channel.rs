@@ -0,0 +1,87 @@
+#[cfg(feature = "client")]
+use std::borrow::Cow;
+use std::fmt;
+use std::marker::PhantomData;
+
+#[cfg(feature = "client")]
+use leptos::prelude::*;
+#[cfg(feature = "client")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "client")]
+use crate::create_sse_signal;
+
+/// A typed server signal definition, shared between the client and the server.
+///
+/// Defining the name and the type of a signal once keeps the two sides from disagreeing on
+/// either of them. Use [`SseChannel::signal`] on the client, and `SseChannel::stream` or
+/// `SseChannel::updater` on the server.
+///
+/// # Example
+///
+/// ```
+/// use leptos::prelude::*;
+/// use leptos_sse::SseChannel;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Clone, Default, Serialize, Deserialize)]
+/// pub struct Count {
+///     pub value: i32,
+/// }
+///
+/// pub const COUNTER: SseChannel<Count> = SseChannel::new("counter");
+///
+/// #[component]
+/// pub fn App() -> impl IntoView {
+///     let count = COUNTER.signal();
+///
+///     view! {
+///         <h1>"Count: " {move || count.get().value.to_string()}</h1>
+///     }
+/// }
+/// ```
+pub struct SseChannel<T> {
+    name: &'static str,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> SseChannel<T> {
+    /// Defines a channel for the signal `name`.
+    pub const fn new(name: &'static str) -> Self {
+        SseChannel {
+            name,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the name of the signal.
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Creates a signal for this channel, see [`create_sse_signal`].
+    #[cfg(feature = "client")]
+    pub fn signal(&self) -> ReadSignal<T>
+    where
+        T: Default + Serialize + for<'de> Deserialize<'de> + Send + Sync + 'static,
+    {
+        create_sse_signal(Cow::Borrowed(self.name))
+    }
+}
+
+impl<T> Clone for SseChannel<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for SseChannel<T> {}
+
+impl<T> fmt::Debug for SseChannel<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SseChannel")
+            .field("name", &self.name)
+            .field("type", &std::any::type_name::<T>())
+            .finish()
+    }
+}
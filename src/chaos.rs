@@ -0,0 +1,141 @@
+use std::time::Duration;
+
+/// Faults injected into the updates received by the client, see [`set_sse_chaos`].
+///
+/// This simulates a flaky network during development, to check that an app copes with late,
+/// reordered, duplicated and lost updates before it meets them in production. Every fault is
+/// drawn independently for every message, after chunks have been reassembled.
+///
+/// ```
+/// use std::time::Duration;
+/// use leptos_sse::{set_sse_chaos, SseChaos};
+///
+/// set_sse_chaos(Some(
+///     SseChaos::new()
+///         .delay(Duration::from_millis(50), Duration::from_millis(500))
+///         .reorder(0.1)
+///         .duplicate(0.05)
+///         .drop(0.01),
+/// ));
+/// ```
+///
+/// Dropped updates leave the documents out of step with the server, which is what lost
+/// messages would do too.
+///
+/// [`set_sse_chaos`]: crate::set_sse_chaos
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SseChaos {
+    pub(crate) min_delay: Duration,
+    pub(crate) max_delay: Duration,
+    pub(crate) reorder: f64,
+    pub(crate) duplicate: f64,
+    pub(crate) drop: f64,
+}
+
+impl SseChaos {
+    /// Creates a configuration which injects no faults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Delays every message by a random duration between `min` and `max`.
+    ///
+    /// Messages may overtake each other when the delays vary more than the time between them.
+    pub fn delay(mut self, min: Duration, max: Duration) -> Self {
+        self.min_delay = min;
+        self.max_delay = max.max(min);
+        self
+    }
+
+    /// Holds back a message until after the next one, with the probability `probability`.
+    pub fn reorder(mut self, probability: f64) -> Self {
+        self.reorder = probability;
+        self
+    }
+
+    /// Delivers a message twice, with the probability `probability`.
+    pub fn duplicate(mut self, probability: f64) -> Self {
+        self.duplicate = probability;
+        self
+    }
+
+    /// Drops a message, with the probability `probability`.
+    pub fn drop(mut self, probability: f64) -> Self {
+        self.drop = probability;
+        self
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod client {
+    use std::cell::{Cell, RefCell};
+    use std::time::Duration;
+
+    use leptos::prelude::*;
+
+    use super::SseChaos;
+    use crate::decode_data;
+
+    thread_local! {
+        static CHAOS: Cell<Option<SseChaos>> = Cell::new(None);
+        /// The message held back to be delivered after the next one.
+        static HELD: RefCell<Option<String>> = RefCell::new(None);
+    }
+
+    pub(crate) fn set(chaos: Option<SseChaos>) {
+        CHAOS.with(|cell| cell.set(chaos));
+        if let Some(held) = HELD.with(|held| held.borrow_mut().take()) {
+            decode_data(held);
+        }
+    }
+
+    fn chance(probability: f64) -> bool {
+        probability > 0.0 && js_sys::Math::random() < probability
+    }
+
+    /// Passes a message through the configured faults, returning it back if there are none.
+    pub(crate) fn inject(data: String) -> Option<String> {
+        let Some(chaos) = CHAOS.with(Cell::get) else {
+            return Some(data);
+        };
+        if chance(chaos.drop) {
+            sse_log!("Chaos: dropping SSE message");
+            return None;
+        }
+        if chance(chaos.duplicate) {
+            sse_log!("Chaos: duplicating SSE message");
+            deliver(&chaos, data.clone());
+        }
+        deliver(&chaos, data);
+        None
+    }
+
+    fn deliver(chaos: &SseChaos, data: String) {
+        let held = HELD.with(|held| held.borrow_mut().take());
+        match held {
+            None if chance(chaos.reorder) => {
+                sse_log!("Chaos: holding back SSE message");
+                HELD.with(|held| *held.borrow_mut() = Some(data));
+            }
+            held => {
+                schedule(chaos, data);
+                if let Some(held) = held {
+                    schedule(chaos, held);
+                }
+            }
+        }
+    }
+
+    fn schedule(chaos: &SseChaos, data: String) {
+        let jitter = (chaos.max_delay - chaos.min_delay).as_secs_f64() * js_sys::Math::random();
+        let delay = chaos.min_delay + Duration::from_secs_f64(jitter);
+        if delay.is_zero() {
+            decode_data(data);
+        } else {
+            set_timeout(move || decode_data(data), delay);
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) use client::*;
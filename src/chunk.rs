@@ -0,0 +1,125 @@
+#[cfg(all(target_arch = "wasm32", feature = "client"))]
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(all(target_arch = "wasm32", feature = "client"))]
+use crate::{connection, refuse_oversized};
+
+/// The named event carrying a chunk of an oversized message.
+pub(crate) const CHUNK_EVENT: &str = "chunk";
+
+/// A part of an SSE message which was too large to send in one event.
+///
+/// The parts of a message share the signal `name` and the `id`, which is the sequence number of
+/// the update, and are sent in order. Parts of messages for different signals may interleave.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Chunk {
+    pub(crate) name: String,
+    pub(crate) id: u64,
+    pub(crate) index: usize,
+    pub(crate) count: usize,
+    pub(crate) data: String,
+}
+
+/// Splits `data` into chunks of at most `size` bytes, serialized as json.
+#[cfg(all(feature = "ssr", any(feature = "actix", feature = "axum")))]
+pub(crate) fn split(name: &str, id: u64, data: &str, size: usize) -> Vec<String> {
+    let size = size.max(4);
+    let mut parts = Vec::new();
+    let mut rest = data;
+    while !rest.is_empty() {
+        let mut end = size.min(rest.len());
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        parts.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+    let count = parts.len();
+    parts
+        .into_iter()
+        .enumerate()
+        .map(|(index, data)| {
+            let chunk = Chunk {
+                name: name.to_string(),
+                id,
+                index,
+                count,
+                data: data.to_string(),
+            };
+            serde_json::to_string(&chunk).expect("chunks serialize to json")
+        })
+        .collect()
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "client"))]
+thread_local! {
+    static ASSEMBLIES: RefCell<HashMap<(String, u64), Vec<String>>> = RefCell::new(HashMap::new());
+    static PROGRESS: RefCell<Option<Rc<dyn Fn(&str, usize, usize)>>> = RefCell::new(None);
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "client"))]
+pub(crate) fn set_progress_callback(callback: impl Fn(&str, usize, usize) + 'static) {
+    PROGRESS.with(|progress| *progress.borrow_mut() = Some(Rc::new(callback)));
+}
+
+/// Handles a `chunk` event, returning the whole message once its last chunk has arrived.
+#[cfg(all(target_arch = "wasm32", feature = "client"))]
+pub(crate) fn receive(data: &str) -> Option<String> {
+    let chunk: Chunk = match serde_json::from_str(data) {
+        Ok(chunk) => chunk,
+        Err(err) => {
+            leptos::logging::error!("invalid SSE chunk: {}", err);
+            return None;
+        }
+    };
+
+    if chunk.index == 0 {
+        // Every chunk but the last one has the same size, so the first tells the whole size
+        let size = chunk.data.len().saturating_mul(chunk.count);
+        if connection::max_message_size().is_some_and(|max| size > max) {
+            refuse_oversized(Some(&chunk.name), size);
+            return None;
+        }
+    }
+
+    let key = (chunk.name, chunk.id);
+    let (received, message) = ASSEMBLIES.with(|assemblies| {
+        let mut assemblies = assemblies.borrow_mut();
+        if chunk.index == 0 {
+            assemblies.insert(key.clone(), Vec::with_capacity(chunk.count));
+        }
+        let Some(parts) = assemblies.get_mut(&key) else {
+            return (0, None);
+        };
+        if parts.len() != chunk.index {
+            leptos::logging::warn!(
+                "Dropping message {} for {} with a missing chunk",
+                key.1,
+                key.0
+            );
+            assemblies.remove(&key);
+            return (0, None);
+        }
+        parts.push(chunk.data);
+        let received = parts.len();
+        let message = (received == chunk.count)
+            .then(|| assemblies.remove(&key).map(|parts| parts.concat()))
+            .flatten();
+        (received, message)
+    });
+
+    if received > 0 {
+        if let Some(callback) = PROGRESS.with(|progress| progress.borrow().clone()) {
+            callback(&key.0, received, chunk.count);
+        }
+    }
+    message
+}
+
+/// Drops partially received messages, which a new stream won't complete.
+#[cfg(all(target_arch = "wasm32", feature = "client"))]
+pub(crate) fn reset() {
+    ASSEMBLIES.with(|assemblies| assemblies.borrow_mut().clear());
+}
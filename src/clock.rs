@@ -0,0 +1,44 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+use leptos::prelude::*;
+
+/// How many heartbeats the offset is estimated from.
+const SAMPLES: usize = 8;
+
+thread_local! {
+    static OFFSET: ArcRwSignal<Option<f64>> = ArcRwSignal::new(None);
+    /// The offsets measured from the last heartbeats.
+    static MEASURED: RefCell<VecDeque<f64>> = RefCell::new(VecDeque::with_capacity(SAMPLES));
+}
+
+pub(crate) fn signal() -> ArcReadSignal<Option<f64>> {
+    OFFSET.with(|offset| offset.read_only())
+}
+
+/// Returns the estimated offset of the server clock, in milliseconds.
+pub(crate) fn offset() -> Option<f64> {
+    OFFSET.with(|offset| offset.get_untracked())
+}
+
+/// Measures the offset of the server clock from a heartbeat sent at `server_time`.
+///
+/// A heartbeat arrives some time after it was sent, which makes the server clock look behind,
+/// so the offset is the largest one measured from the last heartbeats: the one which was
+/// delayed the least.
+pub(crate) fn sample(server_time: f64) {
+    let measured = server_time - js_sys::Date::now();
+    let estimate = MEASURED.with(|samples| {
+        let mut samples = samples.borrow_mut();
+        if samples.len() == SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back(measured);
+        samples.iter().copied().fold(f64::NEG_INFINITY, f64::max)
+    });
+    OFFSET.with(|offset| {
+        if offset.get_untracked() != Some(estimate) {
+            offset.set(Some(estimate));
+        }
+    });
+}
@@ -0,0 +1,66 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Converts the values of a signal to and from the json documents which are patched over the
+/// SSE.
+///
+/// The default, [`JsonCodec`], goes through [`serde_json::Value`], which doesn't round-trip
+/// every type: for example `u128`, numbers with `arbitrary_precision`, or maps with non-string
+/// keys. Implement this trait to control the conversion for such a type, and use the same codec
+/// on the client ([`create_sse_signal_with_codec`]) and on the server
+/// (`ServerSentEvents::with_codec`).
+///
+/// [`create_sse_signal_with_codec`]: crate::create_sse_signal_with_codec
+pub trait SignalCodec<T> {
+    /// Converts a value to the document sent to clients.
+    fn encode(value: &T) -> Result<Value, serde_json::Error>;
+
+    /// Converts a received document back to a value.
+    fn decode(doc: Value) -> Result<T, serde_json::Error>;
+}
+
+/// The default [`SignalCodec`], using the `Serialize` and `Deserialize` implementations of the
+/// type.
+///
+/// Decoding accepts the encodings of [`NonFinite`](crate::NonFinite) where a float is expected.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonCodec;
+
+impl<T> SignalCodec<T> for JsonCodec
+where
+    T: Serialize + DeserializeOwned,
+{
+    fn encode(value: &T) -> Result<Value, serde_json::Error> {
+        serde_json::to_value(value)
+    }
+
+    fn decode(doc: Value) -> Result<T, serde_json::Error> {
+        crate::float::from_value_lenient(doc)
+    }
+}
+
+/// A [`SignalCodec`] which sends the value as a json string, without going through
+/// [`serde_json::Value`].
+///
+/// The value is serialized and deserialized by `serde_json` directly, so it round-trips exactly
+/// like `serde_json::to_string` and `serde_json::from_str`. Every update replaces the whole
+/// string, so this is best suited to small values.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonStringCodec;
+
+impl<T> SignalCodec<T> for JsonStringCodec
+where
+    T: Serialize + DeserializeOwned,
+{
+    fn encode(value: &T) -> Result<Value, serde_json::Error> {
+        serde_json::to_string(value).map(Value::String)
+    }
+
+    fn decode(doc: Value) -> Result<T, serde_json::Error> {
+        match doc {
+            Value::String(json) => serde_json::from_str(&json),
+            doc => serde_json::from_value(doc),
+        }
+    }
+}
@@ -0,0 +1,42 @@
+// The Leptos APIs which differ between the releases this crate supports, selected with the
+// `leptos-0_7` and `leptos-0_8` features, with `leptos-0_7` taking precedence when both are
+// enabled. Signals and effects are created through these rather than with the Leptos prelude.
+
+use leptos::prelude::*;
+
+/// The storage of signals whose values aren't `Send + Sync`.
+pub(crate) type Local = LocalStorage;
+
+/// Creates a signal, with its value stored in the arena.
+pub(crate) fn new_signal<T>(value: T) -> (ReadSignal<T>, WriteSignal<T>)
+where
+    T: Send + Sync + 'static,
+{
+    signal(value)
+}
+
+/// Creates a signal for a value which isn't `Send + Sync`, see [`new_signal`].
+pub(crate) fn new_signal_local<T>(value: T) -> (ReadSignal<T, Local>, WriteSignal<T, Local>)
+where
+    T: 'static,
+{
+    signal_local(value)
+}
+
+/// Creates a reference counted signal, which lives outside of the reactive owner.
+pub(crate) fn new_arc_signal<T>(value: T) -> (ArcReadSignal<T>, ArcWriteSignal<T>) {
+    arc_signal(value)
+}
+
+/// Runs `effect` once, then whenever the signals it reads change.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub(crate) fn effect(effect: impl Fn() + 'static) {
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "leptos-0_7")] {
+            // The form taking the previous value, which every 0.7 release accepts
+            Effect::new(move |_: Option<()>| effect());
+        } else {
+            Effect::new(effect);
+        }
+    }
+}
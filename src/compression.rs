@@ -0,0 +1,100 @@
+use std::io::Write;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+/// Compresses the SSE route with gzip for clients which accept it.
+///
+/// Generic compression layers buffer the response until enough data is compressed, so events
+/// reach the client late, or not at all until the stream ends. This flushes the compressed data
+/// after every event instead, so each one can be decoded as soon as it arrives, while the
+/// repetitive json of updates still compresses well across events.
+///
+/// With axum, use [`SseCompression::middleware`]. With actix, use
+/// `SseCompression::compress_response` in the handler.
+///
+/// ```
+/// use leptos_sse::SseCompression;
+///
+/// let compression = SseCompression::new();
+/// assert!(compression.is_accepted(Some("gzip, deflate, br")));
+/// assert!(!compression.is_accepted(Some("gzip;q=0, br")));
+/// assert!(!compression.is_accepted(None));
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct SseCompression {
+    level: u32,
+}
+
+impl SseCompression {
+    /// Creates a compression with the default level.
+    pub fn new() -> Self {
+        SseCompression {
+            level: Compression::default().level(),
+        }
+    }
+
+    /// Sets the gzip compression level, from 0 to 9. Defaults to 6.
+    pub fn level(mut self, level: u32) -> Self {
+        self.level = level.min(9);
+        self
+    }
+
+    /// Returns `true` if a client sending this `Accept-Encoding` header value accepts gzip.
+    pub fn is_accepted(&self, accept_encoding: Option<&str>) -> bool {
+        let mut wildcard = false;
+        for coding in accept_encoding.unwrap_or_default().split(',') {
+            let mut params = coding.split(';');
+            let name = params.next().unwrap_or_default().trim();
+            let accepted = params
+                .find_map(|param| param.trim().strip_prefix("q="))
+                .is_none_or(|q| q.trim().parse::<f32>().is_ok_and(|q| q > 0.0));
+            if name.eq_ignore_ascii_case("gzip") || name.eq_ignore_ascii_case("x-gzip") {
+                return accepted;
+            }
+            if name == "*" {
+                wildcard = accepted;
+            }
+        }
+        wildcard
+    }
+
+    /// Returns the encoder of a response.
+    pub(crate) fn encoder(&self) -> GzipFrames {
+        GzipFrames {
+            encoder: GzEncoder::new(Vec::new(), Compression::new(self.level)),
+        }
+    }
+}
+
+impl Default for SseCompression {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Compresses the frames of a response body into one gzip stream.
+pub(crate) struct GzipFrames {
+    encoder: GzEncoder<Vec<u8>>,
+}
+
+impl GzipFrames {
+    /// Returns the compressed `frame`, flushed so the client can decode it right away.
+    pub(crate) fn frame(&mut self, frame: &[u8]) -> Vec<u8> {
+        self.encoder
+            .write_all(frame)
+            .and_then(|()| self.encoder.flush())
+            .expect("writing to a Vec can't fail");
+        std::mem::take(self.encoder.get_mut())
+    }
+
+    /// Returns the end of the gzip stream.
+    pub(crate) fn finish(self) -> Vec<u8> {
+        self.encoder.finish().expect("writing to a Vec can't fail")
+    }
+}
+
+/// Returns `true` if the `Content-Type` header value is the one of SSE.
+pub(crate) fn is_event_stream(content_type: Option<&str>) -> bool {
+    content_type.is_some_and(|content_type| content_type.starts_with("text/event-stream"))
+}
@@ -0,0 +1,572 @@
+use std::cell::{Cell, RefCell};
+use std::time::Duration;
+
+use leptos::prelude::*;
+use wasm_bindgen::{prelude::Closure, JsCast, JsValue};
+use web_sys::{EventSource, EventSourceInit, EventTarget, MessageEvent, Url};
+
+use crate::ack;
+use crate::chunk::{self, CHUNK_EVENT};
+use crate::clock;
+use crate::control::{
+    SignalFailure, BATCH_EVENT, FAILURE_EVENT, HEARTBEAT_EVENT, RECONNECT_EVENT, SNAPSHOT_EVENT,
+};
+use crate::diagnostics;
+use crate::dynamic;
+#[cfg(feature = "msgpack")]
+use crate::format::{self, msgpack};
+use crate::message;
+use crate::prefetch;
+use crate::state::{self, SseConnectionState, SseReconnectCause};
+use crate::worker;
+use crate::{
+    handle_data, handle_message, rebase_all_documents, ReconnectPolicy, SseOptions,
+    UnknownSignalPolicy, EVENT_SOURCE,
+};
+
+thread_local! {
+    static OPTIONS: RefCell<Option<SseOptions>> = RefCell::new(None);
+    static HANDLERS: RefCell<Vec<Closure<dyn FnMut(web_sys::Event)>>> = RefCell::new(Vec::new());
+    static ATTEMPT: Cell<u32> = Cell::new(0);
+    /// When the connection first failed since it was last open, in milliseconds.
+    static FAILING_SINCE: Cell<Option<f64>> = Cell::new(None);
+    /// Whether a connection has been opened before, so that the next one is a reconnection.
+    static OPENED: Cell<bool> = Cell::new(false);
+    static HEARTBEAT: RefCell<Option<TimeoutHandle>> = RefCell::new(None);
+    /// The pending reconnection, which closing the connection cancels.
+    static RECONNECT: RefCell<Option<TimeoutHandle>> = RefCell::new(None);
+    static LIVE_SIGNALS: Cell<usize> = Cell::new(0);
+}
+
+/// The named events a connection listens to, which a worker relays besides `open`, `error` and
+/// `message`.
+const EVENTS: &[&str] = &[
+    HEARTBEAT_EVENT,
+    CHUNK_EVENT,
+    BATCH_EVENT,
+    SNAPSHOT_EVENT,
+    FAILURE_EVENT,
+    RECONNECT_EVENT,
+    #[cfg(feature = "msgpack")]
+    msgpack::MSGPACK_EVENT,
+];
+
+pub(crate) fn set_options(options: SseOptions) {
+    OPTIONS.with(|cell| *cell.borrow_mut() = Some(options));
+}
+
+fn with_options<R>(f: impl FnOnce(&SseOptions) -> R) -> Option<R> {
+    OPTIONS.with(|options| options.borrow().as_ref().map(f))
+}
+
+/// Returns `true` unless logging has been disabled.
+#[cfg_attr(not(feature = "logging"), allow(dead_code))]
+pub(crate) fn logging() -> bool {
+    with_options(|options| options.logging).unwrap_or(true)
+}
+
+#[cfg(feature = "signing")]
+pub(crate) fn signing_key() -> Option<std::sync::Arc<[u8]>> {
+    with_options(|options| options.signing_key.clone()).flatten()
+}
+
+#[cfg(feature = "encryption")]
+pub(crate) fn session_key() -> Option<crate::SessionKey> {
+    with_options(|options| options.session_key.clone()).flatten()
+}
+
+pub(crate) fn max_message_size() -> Option<usize> {
+    with_options(|options| options.max_message_size).flatten()
+}
+
+pub(crate) fn strict() -> bool {
+    with_options(|options| options.strict).unwrap_or_default()
+}
+
+pub(crate) fn retain_documents() -> bool {
+    with_options(|options| options.retain_documents).unwrap_or(true)
+}
+
+pub(crate) fn unknown_signal_policy() -> UnknownSignalPolicy {
+    with_options(|options| options.unknown_signals).unwrap_or_default()
+}
+
+/// Opens the `EventSource` with the current options, replacing any previous one.
+///
+/// In worker mode, the `EventSource` is opened in the worker, which relays its events.
+pub(crate) fn connect() -> Result<(), JsValue> {
+    let Some(options) = with_options(Clone::clone) else {
+        return Err(JsValue::from_str("SSE options have not been set"));
+    };
+
+    let url = resolve_url(&options)?;
+    #[cfg(feature = "msgpack")]
+    let url = format::advertise(&url);
+    let url = match options.ack_url.is_some() || options.subscription_url.is_some() {
+        true => ack::beacon::identify(&url),
+        false => url,
+    };
+    let handlers = if options.worker {
+        observe(&worker::open(&url, options.with_credentials, EVENTS)?)?
+    } else {
+        let init = EventSourceInit::new();
+        init.set_with_credentials(options.with_credentials);
+        let es = EventSource::new_with_event_source_init_dict(&url, &init)?;
+        let handlers = observe(&es)?;
+        // Store the EventSource, closing the one it replaces
+        EVENT_SOURCE.with(|source| {
+            if let Some(previous) = source.borrow_mut().replace(es) {
+                previous.close();
+            }
+        });
+        handlers
+    };
+    sse_log!("SSE message handler installed");
+
+    // Keep the handlers alive for as long as their EventSource
+    HANDLERS.with(|cell| *cell.borrow_mut() = handlers);
+    reset_heartbeat();
+
+    Ok(())
+}
+
+/// Adds the listeners of a connection opened by [`connect`] to `target`, which is either its
+/// `EventSource` or the target the worker relays its events to.
+fn observe(target: &EventTarget) -> Result<Vec<Closure<dyn FnMut(web_sys::Event)>>, JsValue> {
+    let onopen = Closure::wrap(Box::new(move |_event: web_sys::Event| {
+        sse_log!("SSE connection opened successfully");
+        // Every stream numbers its updates from 1 again, including after the browser reconnects
+        rebase_all_documents();
+        chunk::reset();
+        message::reset();
+        reset_attempts();
+        state::set(SseConnectionState::Open);
+        state::recovered();
+        if OPENED.with(|opened| opened.replace(true)) {
+            prefetch::resync_all();
+        }
+        // The server may have restarted, and forgotten the subscriptions
+        dynamic::resubscribe();
+        reset_heartbeat();
+    }) as Box<dyn FnMut(_)>);
+    target.add_event_listener_with_callback("open", onopen.as_ref().unchecked_ref())?;
+
+    let onerror = Closure::wrap(Box::new(move |_event: web_sys::Event| {
+        leptos::logging::error!("SSE connection error occurred");
+        handle_error();
+    }) as Box<dyn FnMut(_)>);
+    target.add_event_listener_with_callback("error", onerror.as_ref().unchecked_ref())?;
+
+    let mut handlers = vec![onopen, onerror];
+    handlers.extend(listen(target)?);
+
+    let onreconnect = Closure::wrap(Box::new(move |_event: web_sys::Event| {
+        sse_log!("SSE server asked to reconnect");
+        state::retrying(1, Some(Duration::ZERO), SseReconnectCause::ServerRequest);
+        close();
+        reconnect_after(Duration::ZERO);
+    }) as Box<dyn FnMut(_)>);
+    target
+        .add_event_listener_with_callback(RECONNECT_EVENT, onreconnect.as_ref().unchecked_ref())?;
+    handlers.push(onreconnect);
+    Ok(handlers)
+}
+
+/// Adds the listeners of the messages carrying updates to `target`, returning them to be kept
+/// alive for as long as it is open.
+fn listen(target: &EventTarget) -> Result<Vec<Closure<dyn FnMut(web_sys::Event)>>, JsValue> {
+    let onmessage = Closure::wrap(Box::new(move |event: web_sys::Event| {
+        reset_heartbeat();
+        handle_message(event.unchecked_into::<MessageEvent>());
+    }) as Box<dyn FnMut(_)>);
+    target.add_event_listener_with_callback("message", onmessage.as_ref().unchecked_ref())?;
+
+    let onheartbeat = Closure::wrap(Box::new(move |event: web_sys::Event| {
+        reset_heartbeat();
+        let data = event.unchecked_into::<MessageEvent>().data().as_string();
+        if let Some(server_time) = data.and_then(|data| data.parse::<f64>().ok()) {
+            clock::sample(server_time);
+        }
+    }) as Box<dyn FnMut(_)>);
+    target
+        .add_event_listener_with_callback(HEARTBEAT_EVENT, onheartbeat.as_ref().unchecked_ref())?;
+
+    let onchunk = Closure::wrap(Box::new(move |event: web_sys::Event| {
+        reset_heartbeat();
+        let data = event.unchecked_into::<MessageEvent>().data().as_string();
+        if let Some(message) = data.as_deref().and_then(chunk::receive) {
+            handle_data(message);
+        }
+    }) as Box<dyn FnMut(_)>);
+    target.add_event_listener_with_callback(CHUNK_EVENT, onchunk.as_ref().unchecked_ref())?;
+
+    let onbatch = Closure::wrap(Box::new(move |event: web_sys::Event| {
+        reset_heartbeat();
+        let data = event
+            .unchecked_into::<MessageEvent>()
+            .data()
+            .as_string()
+            .unwrap_or_default();
+        // Applied within the same task, so that effects only see the batch as a whole
+        match serde_json::from_str::<Vec<serde_json::Value>>(&data) {
+            Ok(updates) => updates
+                .into_iter()
+                .for_each(|update| handle_data(update.to_string())),
+            Err(err) => diagnostics::record_malformed(&err, &data),
+        }
+    }) as Box<dyn FnMut(_)>);
+    target.add_event_listener_with_callback(BATCH_EVENT, onbatch.as_ref().unchecked_ref())?;
+
+    let onsnapshot = Closure::wrap(Box::new(move |event: web_sys::Event| {
+        reset_heartbeat();
+        let data = event
+            .unchecked_into::<MessageEvent>()
+            .data()
+            .as_string()
+            .unwrap_or_default();
+        #[cfg(feature = "cbor")]
+        match crate::snapshot::decode(&data) {
+            Ok(update) => handle_data(update),
+            Err(err) => diagnostics::record_malformed(&err, &data),
+        }
+        #[cfg(not(feature = "cbor"))]
+        diagnostics::record_malformed(&"snapshots need the `cbor` feature", &data);
+    }) as Box<dyn FnMut(_)>);
+    target.add_event_listener_with_callback(SNAPSHOT_EVENT, onsnapshot.as_ref().unchecked_ref())?;
+
+    let onfailure = Closure::wrap(Box::new(move |event: web_sys::Event| {
+        reset_heartbeat();
+        let data = event
+            .unchecked_into::<MessageEvent>()
+            .data()
+            .as_string()
+            .unwrap_or_default();
+        match serde_json::from_str::<SignalFailure>(&data) {
+            Ok(failure) => diagnostics::record_failure(failure),
+            Err(err) => diagnostics::record_malformed(&err, &data),
+        }
+    }) as Box<dyn FnMut(_)>);
+    target.add_event_listener_with_callback(FAILURE_EVENT, onfailure.as_ref().unchecked_ref())?;
+
+    #[cfg(feature = "msgpack")]
+    let onmsgpack = Closure::wrap(Box::new(move |event: web_sys::Event| {
+        reset_heartbeat();
+        let data = event
+            .unchecked_into::<MessageEvent>()
+            .data()
+            .as_string()
+            .unwrap_or_default();
+        match msgpack::decode(&data) {
+            Ok(update) => handle_data(update),
+            Err(err) => diagnostics::record_malformed(&err, &data),
+        }
+    }) as Box<dyn FnMut(_)>);
+    #[cfg(feature = "msgpack")]
+    target.add_event_listener_with_callback(
+        msgpack::MSGPACK_EVENT,
+        onmsgpack.as_ref().unchecked_ref(),
+    )?;
+
+    #[allow(unused_mut)]
+    let mut handlers = vec![
+        onmessage,
+        onheartbeat,
+        onchunk,
+        onbatch,
+        onsnapshot,
+        onfailure,
+    ];
+    #[cfg(feature = "msgpack")]
+    handlers.push(onmsgpack);
+    Ok(handlers)
+}
+
+/// Applies the updates received by an `EventSource` managed by the app, instead of opening one.
+pub(crate) fn attach(es: &EventSource) -> Result<(), JsValue> {
+    let onopen = Closure::wrap(Box::new(move |_event: web_sys::Event| {
+        // Every stream numbers its updates from 1 again, including after the browser reconnects
+        rebase_all_documents();
+        chunk::reset();
+        message::reset();
+        state::set(SseConnectionState::Open);
+    }) as Box<dyn FnMut(_)>);
+    es.add_event_listener_with_callback("open", onopen.as_ref().unchecked_ref())?;
+
+    let mut handlers = vec![onopen];
+    handlers.extend(listen(es)?);
+    HANDLERS.with(|cell| *cell.borrow_mut() = handlers);
+    state::set(match es.ready_state() {
+        EventSource::OPEN => {
+            leptos::logging::warn!(
+                "SSE attached after its EventSource opened, updates may be lost"
+            );
+            SseConnectionState::Open
+        }
+        EventSource::CLOSED => SseConnectionState::Closed,
+        _ => SseConnectionState::Connecting,
+    });
+    Ok(())
+}
+
+fn is_lazy() -> bool {
+    with_options(|options| options.lazy).unwrap_or(false)
+}
+
+/// Returns `true` if the connection isn't needed right now because it is lazy and there are no
+/// signals.
+fn is_idle() -> bool {
+    is_lazy() && LIVE_SIGNALS.with(Cell::get) == 0
+}
+
+fn is_open() -> bool {
+    ready_state().is_some_and(|state| state != EventSource::CLOSED)
+}
+
+/// Returns the `readyState` of the `EventSource`, whether it is owned by the page or by the
+/// worker.
+fn ready_state() -> Option<u16> {
+    EVENT_SOURCE
+        .with(|source| source.borrow().as_ref().map(EventSource::ready_state))
+        .or_else(worker::ready_state)
+}
+
+/// Records that a signal has been created, opening a lazy connection for the first one.
+pub(crate) fn signal_created() {
+    let live = LIVE_SIGNALS.with(|live| {
+        live.set(live.get() + 1);
+        live.get()
+    });
+    if live == 1 && is_lazy() && !is_open() {
+        sse_log!("Opening lazy SSE connection");
+        rebase_all_documents();
+        reset_attempts();
+        state::set(SseConnectionState::Connecting);
+        if let Err(err) = connect() {
+            leptos::logging::error!("failed to open SSE connection: {:?}", err);
+            handle_error();
+        }
+    }
+}
+
+/// Records that a signal has been disposed, closing a lazy connection after the last one.
+pub(crate) fn signal_disposed() {
+    LIVE_SIGNALS.with(|live| live.set(live.get().saturating_sub(1)));
+    if is_idle() {
+        sse_log!("Closing idle lazy SSE connection");
+        close();
+        state::set(SseConnectionState::Closed);
+    }
+}
+
+/// Resolves the url of the endpoint against the base path and the page's base url.
+pub(crate) fn resolve_url(options: &SseOptions) -> Result<String, JsValue> {
+    resolve(&options.url_with_base_path())
+}
+
+/// Returns the url to acknowledge applied updates at, if any.
+pub(crate) fn ack_url() -> Option<String> {
+    with_options(|options| {
+        let url = options.ack_url.as_deref()?;
+        resolve(&options.apply_base_path(url)).ok()
+    })
+    .flatten()
+}
+
+/// Returns whether requests to the server send credentials, like the SSE connection.
+pub(crate) fn with_credentials() -> bool {
+    with_options(|options| options.with_credentials).unwrap_or(false)
+}
+
+/// Returns the url to post the subscriptions of dynamic signals to, if any.
+pub(crate) fn subscription_url() -> Option<String> {
+    with_options(|options| {
+        let url = options.subscription_url.as_deref()?;
+        resolve(&options.apply_base_path(url)).ok()
+    })
+    .flatten()
+}
+
+/// Returns the url to prefetch the snapshot of a signal from, and whether to send credentials.
+pub(crate) fn snapshot_url(name: &str) -> Option<(String, bool)> {
+    with_options(|options| {
+        let base = options.prefetch.as_deref()?;
+        let name = String::from(js_sys::encode_uri_component(name));
+        let url = format!("{}/{}", base.trim_end_matches('/'), name);
+        let url = resolve(&options.apply_base_path(&url)).ok()?;
+        Some((url, options.with_credentials))
+    })
+    .flatten()
+}
+
+/// Resolves `url` against the page's base url.
+fn resolve(url: &str) -> Result<String, JsValue> {
+    let base = web_sys::window()
+        .and_then(|window| window.document())
+        .and_then(|document| document.base_uri().ok().flatten());
+    match base {
+        Some(base) => Ok(Url::new_with_base(url, &base)?.href()),
+        None => Ok(url.to_string()),
+    }
+}
+
+/// Reconnects to a new url, resyncing all signals from the new stream.
+pub(crate) fn switch_url(url: String) -> Result<(), JsValue> {
+    sse_log!("Switching SSE connection to: {}", url);
+    let switchable = OPTIONS.with(|options| match options.borrow_mut().as_mut() {
+        Some(options) => {
+            options.url = url;
+            true
+        }
+        None => false,
+    });
+    if !switchable {
+        return Err(JsValue::from_str(
+            "SSE url can only be switched after `provide_sse`",
+        ));
+    }
+
+    close();
+    rebase_all_documents();
+    reset_attempts();
+    state::set(SseConnectionState::Connecting);
+    connect()
+}
+
+/// Closes the current `EventSource`, if any, and cancels a pending reconnection.
+pub(crate) fn close() {
+    EVENT_SOURCE.with(|source| {
+        if let Some(es) = source.borrow().as_ref() {
+            es.close();
+        }
+    });
+    worker::close();
+    HEARTBEAT.with(|heartbeat| {
+        if let Some(handle) = heartbeat.borrow_mut().take() {
+            handle.clear();
+        }
+    });
+    RECONNECT.with(|reconnect| {
+        if let Some(handle) = reconnect.borrow_mut().take() {
+            handle.clear();
+        }
+    });
+}
+
+fn reset_attempts() {
+    ATTEMPT.with(|attempt| attempt.set(0));
+    FAILING_SINCE.with(|since| since.set(None));
+}
+
+/// Records a failed attempt, returning its number and whether the reconnect limits allow
+/// another one.
+fn record_attempt(options: &SseOptions) -> (u32, bool) {
+    let attempt = ATTEMPT.with(|attempt| {
+        attempt.set(attempt.get() + 1);
+        attempt.get()
+    });
+    let now = js_sys::Date::now();
+    let since = FAILING_SINCE.with(|since| {
+        let first = since.get().unwrap_or(now);
+        since.set(Some(first));
+        first
+    });
+    let within_attempts = options
+        .max_reconnect_attempts
+        .is_none_or(|max| attempt <= max);
+    let within_time = options
+        .max_reconnect_time
+        .is_none_or(|max| now - since < max.as_millis() as f64);
+    (attempt, within_attempts && within_time)
+}
+
+/// Closes the connection for good.
+fn fail() {
+    close();
+    state::set(SseConnectionState::Failed);
+}
+
+fn handle_error() {
+    let Some((policy, attempt, retry)) = with_options(|options| {
+        let (attempt, retry) = record_attempt(options);
+        (options.reconnect, attempt, retry)
+    }) else {
+        return;
+    };
+    if !retry {
+        leptos::logging::error!("Giving up on the SSE connection after {} attempts", attempt);
+        fail();
+        return;
+    }
+    match policy {
+        ReconnectPolicy::Browser => {
+            let closed = ready_state().is_none_or(|state| state == EventSource::CLOSED);
+            // The browser doesn't retry after HTTP errors
+            if closed {
+                fail();
+            } else {
+                state::set(SseConnectionState::Reconnecting);
+                state::retrying(attempt, None, SseReconnectCause::Error);
+            }
+        }
+        ReconnectPolicy::Never => fail(),
+        ReconnectPolicy::Exponential { initial, max } => {
+            close();
+            state::set(SseConnectionState::Reconnecting);
+            let delay = initial
+                .saturating_mul(2u32.saturating_pow(attempt - 1))
+                .min(max);
+            sse_log!("Reconnecting SSE in {:?} (attempt {})", delay, attempt);
+            state::retrying(attempt, Some(delay), SseReconnectCause::Error);
+            reconnect_after(delay);
+        }
+    }
+}
+
+fn reconnect_after(delay: Duration) {
+    let handle = set_timeout_with_handle(
+        || {
+            RECONNECT.with(|reconnect| reconnect.borrow_mut().take());
+            if is_idle() {
+                return;
+            }
+            // The new stream starts over from the default documents
+            rebase_all_documents();
+            if let Err(err) = connect() {
+                leptos::logging::error!("failed to reconnect SSE: {:?}", err);
+                handle_error();
+            }
+        },
+        delay,
+    );
+    RECONNECT.with(|reconnect| {
+        let mut reconnect = reconnect.borrow_mut();
+        if let Some(previous) = reconnect.take() {
+            previous.clear();
+        }
+        *reconnect = handle.ok();
+    });
+}
+
+/// Restarts the heartbeat timeout, if one is configured.
+fn reset_heartbeat() {
+    let Some(Some(timeout)) = with_options(|options| options.heartbeat_timeout) else {
+        return;
+    };
+    let handle = set_timeout_with_handle(
+        || {
+            leptos::logging::warn!("No SSE message received in time, reconnecting");
+            close();
+            state::set(SseConnectionState::Reconnecting);
+            state::retrying(1, Some(Duration::ZERO), SseReconnectCause::HeartbeatTimeout);
+            reconnect_after(Duration::ZERO);
+        },
+        timeout,
+    );
+    HEARTBEAT.with(|heartbeat| {
+        let mut heartbeat = heartbeat.borrow_mut();
+        if let Some(previous) = heartbeat.take() {
+            previous.clear();
+        }
+        *heartbeat = handle.ok();
+    });
+}
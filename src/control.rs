@@ -0,0 +1,56 @@
+/// The named event asking the client to close the connection and open a new one right away.
+///
+/// The server sends it before closing a connection which reached its maximum age, so that load
+/// balancers and proxies with idle or age limits never kill it, which browsers report as an
+/// error.
+pub(crate) const RECONNECT_EVENT: &str = "reconnect";
+
+/// The named event carrying the updates of several signals, to be applied together.
+///
+/// The server sends a json array of updates for a transaction changing several signals, see
+/// `SseRegistry::transaction`.
+pub(crate) const BATCH_EVENT: &str = "batch";
+
+/// The named event carrying the whole document of a signal as base64 encoded CBOR.
+///
+/// With the `cbor` feature, the server can send the first update of a stream this way, see
+/// `ServerSentEvents::cbor_snapshots`.
+#[cfg(any(feature = "cbor", all(feature = "client", target_arch = "wasm32")))]
+pub(crate) const SNAPSHOT_EVENT: &str = "snapshot";
+
+/// The named event keeping the connection alive, carrying the time of the server.
+///
+/// Its data is the time it was sent, in milliseconds since the Unix epoch, from which the client
+/// estimates the offset of its own clock, see `Heartbeat`. A heartbeat without data, such as a
+/// `KeepAlive` event, only resets the heartbeat timeout.
+pub(crate) const HEARTBEAT_EVENT: &str = "heartbeat";
+
+/// Returns the data of a heartbeat event sent now.
+#[cfg(any(feature = "actix", feature = "axum"))]
+pub(crate) fn heartbeat_data() -> String {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    now.as_millis().to_string()
+}
+
+/// The named event telling that the producer of a signal stopped after failing repeatedly.
+///
+/// Its data is a json [`SignalFailure`], see `SseRegistry::spawn_interval`. It must not be
+/// named `error`, which browsers use for connection errors.
+pub(crate) const FAILURE_EVENT: &str = "failure";
+
+/// The data of a `failure` event.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub(crate) struct SignalFailure {
+    pub(crate) name: String,
+    pub(crate) error: String,
+}
+
+impl std::fmt::Display for SignalFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the producer of `{}` failed: {}", self.name, self.error)
+    }
+}
+
+impl std::error::Error for SignalFailure {}
@@ -0,0 +1,164 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// A vector clock, counting the writes of every replica of a signal.
+///
+/// Two states were written concurrently if neither clock is greater than or equal to the other,
+/// see [`VectorClock::compare`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct VectorClock(BTreeMap<String, u64>);
+
+impl VectorClock {
+    /// Returns the number of writes of `replica` seen by this clock.
+    pub fn get(&self, replica: &str) -> u64 {
+        self.0.get(replica).copied().unwrap_or_default()
+    }
+
+    /// Counts a write of `replica`.
+    pub fn increment(&mut self, replica: &str) {
+        *self.0.entry(replica.to_string()).or_default() += 1;
+    }
+
+    /// Sets every counter to the greater one of both clocks.
+    pub fn merge(&mut self, other: &VectorClock) {
+        for (replica, count) in &other.0 {
+            let current = self.0.entry(replica.clone()).or_default();
+            *current = (*current).max(*count);
+        }
+    }
+
+    /// Compares the writes seen by both clocks, or returns `None` if they are concurrent.
+    pub fn compare(&self, other: &VectorClock) -> Option<Ordering> {
+        let mut ordering = Ordering::Equal;
+        for replica in self.0.keys().chain(other.0.keys()) {
+            match (ordering, self.get(replica).cmp(&other.get(replica))) {
+                (_, Ordering::Equal) => {}
+                (Ordering::Equal, next) => ordering = next,
+                (current, next) if current != next => return None,
+                _ => {}
+            }
+        }
+        Some(ordering)
+    }
+
+    /// The total number of writes seen, which grows with every write causally after them.
+    fn total(&self) -> u64 {
+        self.0.values().sum()
+    }
+}
+
+/// The state of a signal in merge mode, which replicas exchange to converge.
+///
+/// Every field of the value is a last-writer-wins register. Writes are ordered by the number of
+/// writes their replica had seen, then by replica name, so any replica merging the same states
+/// ends up with the same value, whatever the order it received them in. Removed fields are kept
+/// as tombstones, so a removal isn't undone by merging an older state.
+///
+/// See [`SseRegistry::crdt`](crate::SseRegistry::crdt).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CrdtState {
+    replica: String,
+    clock: VectorClock,
+    fields: BTreeMap<String, Register>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Register {
+    /// The value of the field, or `None` once it was removed.
+    value: Option<Value>,
+    time: u64,
+    replica: String,
+}
+
+impl Register {
+    fn stamp(&self) -> (u64, &str) {
+        (self.time, &self.replica)
+    }
+}
+
+impl CrdtState {
+    pub(crate) fn new(replica: String) -> Self {
+        CrdtState {
+            replica,
+            clock: VectorClock::default(),
+            fields: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the name of the replica which holds this state.
+    pub fn replica(&self) -> &str {
+        &self.replica
+    }
+
+    /// Returns the writes this state has seen.
+    pub fn clock(&self) -> &VectorClock {
+        &self.clock
+    }
+
+    /// Returns the merged value, an object with the fields which weren't removed.
+    pub fn value(&self) -> Value {
+        let fields = self.fields.iter().filter_map(|(field, register)| {
+            let value = register.value.clone()?;
+            Some((field.clone(), value))
+        });
+        Value::Object(fields.collect::<Map<_, _>>())
+    }
+
+    /// Writes the fields of `value` which changed, and removes those it doesn't have.
+    ///
+    /// Returns `false` without writing anything if `value` isn't an object.
+    pub(crate) fn write(&mut self, value: &Value) -> bool {
+        let Some(object) = value.as_object() else {
+            return false;
+        };
+        let removed = self
+            .fields
+            .iter()
+            .filter(|(field, register)| register.value.is_some() && !object.contains_key(*field))
+            .map(|(field, _)| (field.clone(), None));
+        let written = object
+            .iter()
+            .filter(|(field, value)| {
+                let current = self.fields.get(*field);
+                current.and_then(|register| register.value.as_ref()) != Some(*value)
+            })
+            .map(|(field, value)| (field.clone(), Some(value.clone())));
+        let changes: Vec<_> = removed.chain(written).collect();
+        if changes.is_empty() {
+            return true;
+        }
+        self.clock.increment(&self.replica);
+        let time = self.clock.total();
+        for (field, value) in changes {
+            let replica = self.replica.clone();
+            let register = Register {
+                value,
+                time,
+                replica,
+            };
+            self.fields.insert(field, register);
+        }
+        true
+    }
+
+    /// Merges the state of another replica, and returns whether the value changed.
+    pub(crate) fn merge(&mut self, other: &CrdtState) -> bool {
+        self.clock.merge(&other.clock);
+        let mut changed = false;
+        for (field, theirs) in &other.fields {
+            let newer = match self.fields.get(field) {
+                Some(ours) => theirs.stamp() > ours.stamp(),
+                None => true,
+            };
+            if newer {
+                let previous = self.fields.insert(field.clone(), theirs.clone());
+                changed |= previous.and_then(|register| register.value) != theirs.value;
+            }
+        }
+        changed
+    }
+}
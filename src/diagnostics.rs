@@ -0,0 +1,103 @@
+use std::collections::BTreeMap;
+
+use leptos::prelude::*;
+
+#[cfg(target_arch = "wasm32")]
+use crate::control::SignalFailure;
+
+/// Diagnostics of the messages the client could not read, see
+/// [`use_sse_diagnostics`](crate::use_sse_diagnostics).
+///
+/// Messages which fail to decrypt or to parse as an update are dropped. A growing count usually
+/// means that the client and the server disagree on the protocol, for example after deploying
+/// a new server version, or because a proxy rewrites the stream.
+///
+/// Updates whose patch doesn't apply to the document of their signal are dropped too, without
+/// affecting the other signals, and so are the updates rejected in strict mode, see
+/// [`SseOptions::strict`](crate::SseOptions::strict). The diagnostics also list the signals whose producer the server
+/// stopped after it failed repeatedly, which no longer update.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SseDiagnostics {
+    /// The number of messages dropped because they could not be read.
+    pub malformed_messages: u64,
+    /// The error of the last malformed message, failed patch or rejected document.
+    pub last_error: Option<String>,
+    /// The raw data of the last malformed message. Only kept in debug builds.
+    pub last_payload: Option<String>,
+    /// The number of updates dropped because their patch didn't apply.
+    pub failed_patches: u64,
+    /// The number of updates dropped in strict mode, because the document they produced
+    /// didn't deserialize into the type of its signal.
+    pub rejected_documents: u64,
+    /// The signals whose producer failed on the server, with its last error, see
+    /// `SseRegistry::spawn_interval`.
+    pub failed_signals: BTreeMap<String, String>,
+}
+
+thread_local! {
+    static DIAGNOSTICS: ArcRwSignal<SseDiagnostics> = ArcRwSignal::new(SseDiagnostics::default());
+}
+
+/// Returns the signal holding the diagnostics of this page.
+pub(crate) fn signal() -> ArcReadSignal<SseDiagnostics> {
+    DIAGNOSTICS.with(|diagnostics| diagnostics.read_only())
+}
+
+/// Records a message which could not be read.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn record_malformed(err: &dyn std::fmt::Display, payload: &str) {
+    leptos::logging::warn!("Dropping malformed SSE message: {}", err);
+    DIAGNOSTICS.with(|diagnostics| {
+        diagnostics.update(|diagnostics| {
+            diagnostics.malformed_messages += 1;
+            diagnostics.last_error = Some(err.to_string());
+            diagnostics.last_payload = cfg!(debug_assertions).then(|| payload.to_string());
+        });
+    });
+}
+
+/// Records an update whose patch didn't apply to the document of the signal `name`.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn record_failed_patch(name: &str, err: &json_patch::PatchError) {
+    leptos::logging::error!(
+        "Dropping SSE update to {} which doesn't apply: {}",
+        name,
+        err
+    );
+    DIAGNOSTICS.with(|diagnostics| {
+        diagnostics.update(|diagnostics| {
+            diagnostics.failed_patches += 1;
+            diagnostics.last_error = Some(format!("{name}: {err}"));
+        });
+    });
+}
+
+/// Records an update whose document didn't deserialize into the type of the signal `name`.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn record_rejected_document(name: &str, err: &serde_json::Error) {
+    leptos::logging::error!(
+        "Dropping SSE update to {} which doesn't match its type: {}",
+        name,
+        err
+    );
+    DIAGNOSTICS.with(|diagnostics| {
+        diagnostics.update(|diagnostics| {
+            diagnostics.rejected_documents += 1;
+            diagnostics.last_error = Some(format!("{name}: {err}"));
+        });
+    });
+}
+
+/// Records a signal whose producer failed on the server.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn record_failure(failure: SignalFailure) {
+    leptos::logging::warn!("{}", failure);
+    DIAGNOSTICS.with(|diagnostics| {
+        diagnostics.update(|diagnostics| {
+            diagnostics
+                .failed_signals
+                .insert(failure.name, failure.error);
+        });
+    });
+}
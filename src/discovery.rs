@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A signal published by the server, as listed by its discovery route.
+///
+/// The server lists the signals of an `SseRegistry` with `SseRegistry::discovery`, and clients
+/// fetch them with [`discover_sse_signals`](crate::discover_sse_signals), to render whatever
+/// the server publishes without knowing the signals in advance.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct SignalDescriptor {
+    /// The name of the signal.
+    pub name: String,
+    /// The JSON Schema of the values of the signal, if the server described it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<Value>,
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "client"))]
+pub(crate) mod client {
+    use wasm_bindgen::JsValue;
+
+    use super::SignalDescriptor;
+    use crate::{connection, prefetch};
+
+    /// Fetches the signals listed at `url`.
+    pub(crate) async fn fetch(url: &str) -> Result<Vec<SignalDescriptor>, JsValue> {
+        let list = prefetch::fetch(url, connection::with_credentials())
+            .await?
+            .ok_or_else(|| JsValue::from_str("the discovery request failed"))?;
+        serde_json::from_value(list).map_err(|err| JsValue::from_str(&err.to_string()))
+    }
+}
@@ -0,0 +1,64 @@
+/// A document held by the client for the signals of a name, see
+/// [`sse_documents`](crate::sse_documents).
+///
+/// Every signal created for a name shares one document, which the updates of the name are
+/// applied to. Unless [`SseOptions::retain_documents`](crate::SseOptions::retain_documents) is
+/// disabled, the document outlives its signals, so that signals created again for the name
+/// start from it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SseDocumentInfo {
+    /// The name of the signal.
+    pub name: String,
+    /// The number of signals using the document which haven't been disposed.
+    pub signals: usize,
+    /// The size of the document serialized as json, in bytes.
+    pub size: usize,
+}
+
+#[cfg(target_arch = "wasm32")]
+mod client {
+    use super::SseDocumentInfo;
+    use crate::{current_document, forget_document, SIGNAL_META};
+
+    /// Lists the documents of the registered signals, sorted by name.
+    pub(crate) fn list() -> Vec<SseDocumentInfo> {
+        let signals: Vec<_> = SIGNAL_META.with(|meta| {
+            meta.borrow()
+                .iter()
+                .map(|(name, meta)| (name.to_string(), meta.signals))
+                .collect()
+        });
+        let mut documents: Vec<_> = signals
+            .into_iter()
+            .map(|(name, signals)| {
+                let size = current_document(&name)
+                    .and_then(|doc| serde_json::to_vec(&doc).ok())
+                    .map_or(0, |doc| doc.len());
+                SseDocumentInfo {
+                    name,
+                    signals,
+                    size,
+                }
+            })
+            .collect();
+        documents.sort_by(|a, b| a.name.cmp(&b.name));
+        documents
+    }
+
+    /// Drops the document of `name` if no signal uses it anymore.
+    pub(crate) fn remove(name: &str) -> bool {
+        let unused = SIGNAL_META.with(|meta| {
+            meta.borrow()
+                .get(name)
+                .is_some_and(|meta| meta.signals == 0)
+        });
+        if unused {
+            forget_document(name);
+        }
+        unused
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) use client::*;
@@ -0,0 +1,48 @@
+use std::cell::Cell;
+
+use js_sys::{Object, Reflect, JSON};
+use json_patch::Patch;
+use wasm_bindgen::JsValue;
+use web_sys::{CustomEvent, CustomEventInit};
+
+use crate::current_document;
+
+/// The type of the event dispatched on `window` for every applied update.
+const EVENT_TYPE: &str = "leptos-sse:update";
+
+thread_local! {
+    static ENABLED: Cell<bool> = Cell::new(false);
+}
+
+pub(crate) fn set_enabled(enabled: bool) {
+    ENABLED.with(|cell| cell.set(enabled));
+}
+
+/// Dispatches a [`EVENT_TYPE`] event on `window`, if enabled.
+pub(crate) fn dispatch(name: &str, patch: &Patch) {
+    if !ENABLED.with(Cell::get) {
+        return;
+    }
+    if let Err(err) = try_dispatch(name, patch) {
+        leptos::logging::warn!("failed to dispatch {} event: {:?}", EVENT_TYPE, err);
+    }
+}
+
+fn try_dispatch(name: &str, patch: &Patch) -> Result<(), JsValue> {
+    let Some(window) = web_sys::window() else {
+        return Ok(());
+    };
+    let patch = serde_json::to_string(patch).map_err(|err| JsValue::from_str(&err.to_string()))?;
+    let value = current_document(name).unwrap_or_default();
+
+    let detail = Object::new();
+    Reflect::set(&detail, &"name".into(), &name.into())?;
+    Reflect::set(&detail, &"patch".into(), &JSON::parse(&patch)?)?;
+    Reflect::set(&detail, &"value".into(), &JSON::parse(&value.to_string())?)?;
+
+    let init = CustomEventInit::new();
+    init.set_detail(&detail);
+    let event = CustomEvent::new_with_event_init_dict(EVENT_TYPE, &init)?;
+    window.dispatch_event(&event)?;
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use crate::ack::beacon;
+use crate::forget_document;
+
+thread_local! {
+    /// The number of live dynamic signals, by name.
+    static SIGNALS: RefCell<BTreeMap<Cow<'static, str>, usize>> = RefCell::new(BTreeMap::new());
+}
+
+/// Counts a new dynamic signal, subscribing to `name` if it is the first one.
+pub(crate) fn acquire(name: Cow<'static, str>) {
+    let first = SIGNALS.with(|signals| {
+        let mut signals = signals.borrow_mut();
+        let count = signals.entry(name).or_default();
+        *count += 1;
+        *count == 1
+    });
+    if first {
+        send();
+    }
+}
+
+/// Counts a disposed dynamic signal, unsubscribing from `name` and forgetting its document if
+/// it was the last one.
+pub(crate) fn release(name: &str) {
+    let last = SIGNALS.with(|signals| {
+        let mut signals = signals.borrow_mut();
+        let Some(count) = signals.get_mut(name) else {
+            return false;
+        };
+        *count -= 1;
+        if *count > 0 {
+            return false;
+        }
+        signals.remove(name);
+        true
+    });
+    if last {
+        forget_document(name);
+        send();
+    }
+}
+
+/// Posts the subscriptions again, for a server which may have lost them.
+pub(crate) fn resubscribe() {
+    if SIGNALS.with(|signals| !signals.borrow().is_empty()) {
+        send();
+    }
+}
+
+fn send() {
+    let names = SIGNALS.with(|signals| {
+        let signals = signals.borrow();
+        signals.keys().map(|name| name.to_string()).collect()
+    });
+    beacon::subscribe(names);
+}
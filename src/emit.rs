@@ -0,0 +1,70 @@
+use leptos::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::SseRegistry;
+
+/// Returns the [`SseRegistry`] provided as context, for example to a server function.
+///
+/// Provide the registry when handling server functions, such as with
+/// `handle_server_fns_with_context` or `leptos_routes_with_context`:
+///
+/// ```ignore
+/// let registry = SseRegistry::new();
+/// let app = Router::new().leptos_routes_with_context(
+///     &leptos_options,
+///     routes,
+///     {
+///         let registry = registry.clone();
+///         move || provide_context(registry.clone())
+///     },
+///     App,
+/// );
+/// ```
+pub fn use_sse_registry() -> Option<SseRegistry> {
+    use_context::<SseRegistry>()
+}
+
+/// Publishes a new value of the signal `name` from a server function, see
+/// [`SseRegistry::publish`].
+///
+/// The registry must be provided as context, see [`use_sse_registry`].
+///
+/// ```ignore
+/// #[server]
+/// async fn reset_count() -> Result<(), ServerFnError> {
+///     publish_sse_signal("counter", &Count { value: 0 })
+/// }
+/// ```
+pub fn publish_sse_signal<T>(name: &str, value: &T) -> Result<(), ServerFnError>
+where
+    T: Serialize + ?Sized,
+{
+    expect_registry()?
+        .publish(name, value)
+        .map_err(ServerFnError::new)
+}
+
+/// Updates the value of the signal `name` in place from a server function, see
+/// [`SseRegistry::update`].
+///
+/// The registry must be provided as context, see [`use_sse_registry`].
+///
+/// ```ignore
+/// #[server]
+/// async fn increment() -> Result<(), ServerFnError> {
+///     update_sse_signal("counter", |count: &mut Count| count.value += 1)
+/// }
+/// ```
+pub fn update_sse_signal<T>(name: &str, update: impl FnOnce(&mut T)) -> Result<(), ServerFnError>
+where
+    T: Default + Serialize + DeserializeOwned,
+{
+    expect_registry()?
+        .update(name, update)
+        .map_err(ServerFnError::new)
+}
+
+fn expect_registry() -> Result<SseRegistry, ServerFnError> {
+    use_sse_registry().ok_or_else(|| ServerFnError::new("no SseRegistry provided as context"))
+}
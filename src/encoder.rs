@@ -0,0 +1,155 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::Arc;
+
+use serde_json::Value;
+
+#[cfg(feature = "cbor")]
+use crate::control::SNAPSHOT_EVENT;
+#[cfg(feature = "msgpack")]
+use crate::format::msgpack;
+#[cfg(feature = "protobuf")]
+use crate::protobuf;
+#[cfg(feature = "encryption")]
+use crate::SessionKey;
+use crate::{ServerSignalUpdate, SseFormat};
+
+/// Turns updates into SSE data, signing and encrypting them if configured.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct UpdateEncoder {
+    #[cfg(feature = "signing")]
+    pub(crate) signing_key: Option<Arc<[u8]>>,
+    /// The session key, and the random nonce prefix of this stream.
+    #[cfg(feature = "encryption")]
+    encryption: Option<(SessionKey, [u8; 16])>,
+    #[cfg(feature = "cbor")]
+    pub(crate) cbor_snapshots: bool,
+    pub(crate) format: SseFormat,
+    /// The id the name of the signal is interned as.
+    pub(crate) intern: Option<u32>,
+    pub(crate) trace_context: Option<TraceContext>,
+}
+
+impl UpdateEncoder {
+    /// Encrypts the updates of this stream with `key`.
+    #[cfg(feature = "encryption")]
+    pub(crate) fn encrypt_with(&mut self, key: SessionKey) {
+        let mut prefix = [0; 16];
+        getrandom::getrandom(&mut prefix).expect("failed to generate a nonce");
+        self.encryption = Some((key, prefix));
+    }
+
+    /// Encodes `update`, which patches the previous document to `doc`.
+    ///
+    /// Updates which are neither signed nor encrypted may be sent as a binary snapshot, unless
+    /// the document isn't known because the patch was rewritten, or in the negotiated
+    /// [`SseFormat`] instead of json.
+    #[allow(unused_variables)]
+    pub(crate) fn encode(
+        &self,
+        mut update: ServerSignalUpdate,
+        doc: Option<&Value>,
+    ) -> Result<Encoded, serde_json::Error> {
+        update.id = self.intern;
+        if let Some(traceparent) = self.trace_context.as_ref().and_then(TraceContext::get) {
+            update = update.with_trace_context(traceparent);
+        }
+        #[cfg(feature = "cbor")]
+        if let Some(doc) = doc.filter(|_| self.cbor_snapshots && update.seq() == Some(1)) {
+            if self.is_plain() {
+                return Ok(Encoded {
+                    event: Some(SNAPSHOT_EVENT),
+                    data: crate::snapshot::encode(&update, doc)?,
+                });
+            }
+        }
+        match self.format {
+            SseFormat::Json => {}
+            #[cfg(feature = "msgpack")]
+            SseFormat::MessagePack if self.is_plain() => {
+                return Ok(Encoded {
+                    event: Some(msgpack::MSGPACK_EVENT),
+                    data: msgpack::encode(&update)?,
+                });
+            }
+            #[cfg(feature = "msgpack")]
+            SseFormat::MessagePack => {}
+            #[cfg(feature = "protobuf")]
+            SseFormat::Protobuf if self.is_plain() => {
+                return Ok(Encoded {
+                    event: Some(protobuf::PROTOBUF_EVENT),
+                    data: protobuf::encode(&update)?,
+                });
+            }
+            #[cfg(feature = "protobuf")]
+            SseFormat::Protobuf => {}
+        }
+
+        #[cfg(feature = "signing")]
+        let mut update = match &self.signing_key {
+            Some(key) => update.sign(key),
+            None => update,
+        };
+        // The first update of a stream binds the name to the id, clients resolve it after
+        if self.intern.is_some() && update.seq() != Some(1) {
+            update.name = Cow::Borrowed("");
+        }
+        let data = serde_json::to_string(&update)?;
+
+        #[cfg(feature = "encryption")]
+        if let Some((key, prefix)) = &self.encryption {
+            // Sequence numbers are unique within a stream, so nonces are never reused
+            let mut nonce = [0; 24];
+            nonce[..16].copy_from_slice(prefix);
+            nonce[16..].copy_from_slice(&update.seq().unwrap_or_default().to_be_bytes());
+            return Ok(Encoded {
+                event: None,
+                data: key.seal(&nonce, &data),
+            });
+        }
+
+        Ok(Encoded { event: None, data })
+    }
+
+    #[cfg(any(feature = "cbor", feature = "msgpack", feature = "protobuf"))]
+    fn is_plain(&self) -> bool {
+        #[cfg(feature = "signing")]
+        if self.signing_key.is_some() {
+            return false;
+        }
+        #[cfg(feature = "encryption")]
+        if self.encryption.is_some() {
+            return false;
+        }
+        true
+    }
+}
+
+/// The SSE data of an update, and the named event to send it as, if any.
+pub(crate) struct Encoded {
+    pub(crate) event: Option<&'static str>,
+    pub(crate) data: String,
+}
+
+/// Returns the trace context to attach to the next update of a stream, see
+/// `ServerSentEvents::trace_context`.
+#[derive(Clone)]
+pub(crate) struct TraceContext(Arc<TraceContextFn>);
+
+type TraceContextFn = dyn Fn() -> Option<String> + Send + Sync;
+
+impl TraceContext {
+    pub(crate) fn new(context: impl Fn() -> Option<String> + Send + Sync + 'static) -> Self {
+        TraceContext(Arc::new(context))
+    }
+
+    pub(crate) fn get(&self) -> Option<String> {
+        (self.0)()
+    }
+}
+
+impl fmt::Debug for TraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("TraceContext").finish_non_exhaustive()
+    }
+}
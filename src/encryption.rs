@@ -0,0 +1,98 @@
+use std::fmt;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+
+/// A symmetric key for encrypting the updates of one session with XChaCha20-Poly1305.
+///
+/// Generate a key on the server for every session, hand it to the client over an authenticated
+/// server function, and use it for both `ServerSentEvents::encrypted` and
+/// [`SseOptions::session_key`](crate::SseOptions::session_key). Intermediaries such as logging
+/// proxies and CDNs then only ever see ciphertext.
+///
+/// The key serializes as its raw bytes, so it can be returned from a server function directly.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionKey([u8; 32]);
+
+impl SessionKey {
+    /// Generates a new random key.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn generate() -> Self {
+        let mut key = [0; 32];
+        getrandom::getrandom(&mut key).expect("failed to generate a session key");
+        SessionKey(key)
+    }
+
+    /// Creates a key from its raw bytes.
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        SessionKey(bytes)
+    }
+
+    /// Returns the raw bytes of the key.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Encrypts the SSE data of an update with a nonce which is never reused for this key.
+    #[cfg(all(
+        feature = "ssr",
+        any(feature = "actix", feature = "axum", feature = "spin")
+    ))]
+    pub(crate) fn seal(&self, nonce: &[u8; 24], data: &str) -> String {
+        let cipher = XChaCha20Poly1305::new(&self.0.into());
+        let ciphertext = cipher
+            .encrypt(XNonce::from_slice(nonce), data.as_bytes())
+            .expect("encrypting an update can't fail");
+        let sealed = Sealed {
+            nonce: STANDARD.encode(nonce),
+            ciphertext: STANDARD.encode(ciphertext),
+        };
+        serde_json::to_string(&sealed).expect("sealed updates serialize to json")
+    }
+
+    /// Decrypts the SSE data of an update encrypted with this key.
+    pub fn open(&self, data: &str) -> Result<String, DecryptError> {
+        let sealed: Sealed = serde_json::from_str(data).map_err(|_| DecryptError)?;
+        let nonce = STANDARD.decode(sealed.nonce).map_err(|_| DecryptError)?;
+        let ciphertext = STANDARD
+            .decode(sealed.ciphertext)
+            .map_err(|_| DecryptError)?;
+        if nonce.len() != 24 {
+            return Err(DecryptError);
+        }
+        let cipher = XChaCha20Poly1305::new(&self.0.into());
+        let plaintext = cipher
+            .decrypt(XNonce::from_slice(&nonce), ciphertext.as_slice())
+            .map_err(|_| DecryptError)?;
+        String::from_utf8(plaintext).map_err(|_| DecryptError)
+    }
+}
+
+impl fmt::Debug for SessionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SessionKey(..)")
+    }
+}
+
+/// SSE data which couldn't be decrypted, because it is malformed, or was encrypted with
+/// another key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecryptError;
+
+impl fmt::Display for DecryptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("failed to decrypt update")
+    }
+}
+
+impl std::error::Error for DecryptError {}
+
+/// The SSE data of an encrypted update.
+#[derive(Serialize, Deserialize)]
+struct Sealed {
+    nonce: String,
+    ciphertext: String,
+}
@@ -0,0 +1,17 @@
+/// What a [`ServerSentEvents`](crate::ServerSentEvents) stream does when its values fail, or
+/// can't be serialized, see `ServerSentEvents::on_error`.
+///
+/// Errors are `SseError`s, boxed into the error type of the stream items.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OnError {
+    /// Yields the error, which usually makes the web framework close the connection.
+    #[default]
+    Propagate,
+    /// Sends the error as an SSE comment, which clients ignore, and carries on.
+    Comment,
+    /// Ends the stream without an error.
+    Terminate,
+    /// Skips the value and carries on.
+    Skip,
+}
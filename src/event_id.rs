@@ -0,0 +1,102 @@
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Arc, Mutex};
+
+use crate::registry::now;
+
+/// Generates the `id` field of the events of a stream.
+///
+/// Browsers send the id of the last event they received in the `Last-Event-ID` header when they
+/// reconnect, so ids from the same sequence as an event store let the server resume a stream
+/// from where the client left it. The generator receives the name of the signal and the
+/// sequence number of the update. The sequence numbers themselves always count the updates of
+/// the stream from 1, since clients rely on them to detect missed updates.
+///
+/// ```
+/// use leptos_sse::EventIds;
+///
+/// // Ids from an existing generator, such as snowflakes
+/// # fn snowflake() -> u64 { 1 }
+/// let ids = EventIds::new(|_name, _seq| snowflake().to_string());
+/// let ids = EventIds::per_signal();
+/// let ids = EventIds::ulid();
+/// ```
+///
+/// Use it with `ServerSentEvents::event_ids`. Ids must not contain line breaks. An update split
+/// into chunks carries its id on the last chunk, so a client never resumes after a partial
+/// update.
+#[derive(Clone)]
+pub struct EventIds(Arc<Generate>);
+
+type Generate = dyn Fn(&str, u64) -> String + Send + Sync;
+
+impl EventIds {
+    /// Creates ids calling `ids` with the name of the signal and the sequence number of every
+    /// update.
+    pub fn new(ids: impl Fn(&str, u64) -> String + Send + Sync + 'static) -> Self {
+        EventIds(Arc::new(ids))
+    }
+
+    /// Creates ids made of the name of the signal and the sequence number of the update, such as
+    /// `counter:3`, which increase monotonically for every signal.
+    pub fn per_signal() -> Self {
+        EventIds::new(|name, seq| format!("{name}:{seq}"))
+    }
+
+    /// Creates [ULIDs](https://github.com/ulid/spec), which sort by the time they were
+    /// generated, and increase monotonically within a millisecond.
+    ///
+    /// Their random part isn't suitable for secrets.
+    pub fn ulid() -> Self {
+        let last = Mutex::new((0, 0));
+        EventIds::new(move |_, _| {
+            let mut last = last.lock().unwrap_or_else(|err| err.into_inner());
+            let (mut time, mut random) = *last;
+            let current = now();
+            if current > time {
+                time = current;
+                random = random_bits() & ULID_RANDOM;
+            } else if random == ULID_RANDOM {
+                // Out of ids for this millisecond, borrow from the next one
+                time += 1;
+                random = 0;
+            } else {
+                random += 1;
+            }
+            *last = (time, random);
+            encode_ulid((u128::from(time) << 80) | random)
+        })
+    }
+
+    pub(crate) fn next(&self, name: &str, seq: u64) -> String {
+        (self.0)(name, seq)
+    }
+}
+
+impl fmt::Debug for EventIds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("EventIds").finish_non_exhaustive()
+    }
+}
+
+/// The bits of the random part of a ULID.
+const ULID_RANDOM: u128 = (1 << 80) - 1;
+
+/// Returns random bits from the randomly seeded keys of the standard hasher.
+fn random_bits() -> u128 {
+    let hash = |n| {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(n);
+        u128::from(hasher.finish())
+    };
+    (hash(0) << 64) | hash(1)
+}
+
+/// Encodes a ULID in Crockford's base32.
+fn encode_ulid(ulid: u128) -> String {
+    const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+    (0..26)
+        .map(|i| ALPHABET[((ulid >> (125 - 5 * i)) & 31) as usize] as char)
+        .collect()
+}
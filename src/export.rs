@@ -0,0 +1,111 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The documents of all server signals on the client, see [`export_sse_state`].
+///
+/// This serializes to one JSON object, which can be kept across full page reloads, for example
+/// in `sessionStorage`, or attached to a bug report, and restored with [`import_sse_state`].
+///
+/// ```
+/// use leptos_sse::{export_sse_state, import_sse_state, SseStateSnapshot};
+///
+/// let json = serde_json::to_string(&export_sse_state()).unwrap();
+/// // ...
+/// let snapshot: SseStateSnapshot = serde_json::from_str(&json).unwrap();
+/// import_sse_state(snapshot);
+/// ```
+///
+/// [`export_sse_state`]: crate::export_sse_state
+/// [`import_sse_state`]: crate::import_sse_state
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SseStateSnapshot {
+    pub(crate) signals: BTreeMap<String, SignalSnapshot>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct SignalSnapshot {
+    pub(crate) doc: Value,
+    /// The sequence number of the last update applied to the document, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) seq: Option<u64>,
+}
+
+impl SseStateSnapshot {
+    /// Returns the names of the signals in this snapshot.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.signals.keys().map(String::as_str)
+    }
+
+    /// Returns the document of the signal `name`, if it is in this snapshot.
+    pub fn document(&self, name: &str) -> Option<&Value> {
+        self.signals.get(name).map(|signal| &signal.doc)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod client {
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+
+    use super::{SignalSnapshot, SseStateSnapshot};
+    use crate::{current_document, set_document, SIGNAL_META};
+
+    thread_local! {
+        /// The imported documents of signals which haven't been created yet.
+        static IMPORTED: RefCell<BTreeMap<String, SignalSnapshot>> = RefCell::new(BTreeMap::new());
+    }
+
+    pub(crate) fn export() -> SseStateSnapshot {
+        let seqs: Vec<(String, Option<u64>)> = SIGNAL_META.with(|meta| {
+            let meta = meta.borrow();
+            meta.iter()
+                .map(|(name, meta)| (name.to_string(), meta.seq))
+                .collect()
+        });
+        let signals = seqs.into_iter().filter_map(|(name, seq)| {
+            let doc = current_document(&name)?;
+            Some((name, SignalSnapshot { doc, seq }))
+        });
+        SseStateSnapshot {
+            signals: signals.collect(),
+        }
+    }
+
+    pub(crate) fn import(snapshot: SseStateSnapshot) {
+        for (name, signal) in snapshot.signals {
+            let registered = SIGNAL_META.with(|meta| meta.borrow().contains_key(name.as_str()));
+            if registered {
+                apply(&name, signal);
+            } else {
+                IMPORTED.with(|imported| imported.borrow_mut().insert(name, signal));
+            }
+        }
+    }
+
+    /// Applies the imported document of a signal which has just been created, if any.
+    pub(crate) fn restore(name: &str) {
+        if let Some(signal) = IMPORTED.with(|imported| imported.borrow_mut().remove(name)) {
+            apply(name, signal);
+        }
+    }
+
+    /// Sets the document of a signal, unless it has received an update from the current
+    /// stream, which is newer than the snapshot.
+    fn apply(name: &str, signal: SignalSnapshot) {
+        let fresh = SIGNAL_META.with(|meta| match meta.borrow_mut().get_mut(name) {
+            Some(meta) if meta.seq.is_none() => {
+                meta.seq = signal.seq;
+                true
+            }
+            _ => false,
+        });
+        if fresh {
+            set_document(name, signal.doc);
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) use client::*;
@@ -0,0 +1,36 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::ServerSignalUpdate;
+
+type Filter = Rc<dyn Fn(ServerSignalUpdate) -> Option<ServerSignalUpdate>>;
+
+thread_local! {
+    static FILTERS: RefCell<HashMap<Cow<'static, str>, Filter>> = RefCell::new(HashMap::new());
+}
+
+pub(crate) fn set(name: Cow<'static, str>, filter: Filter) {
+    FILTERS.with(|filters| {
+        filters.borrow_mut().insert(name, filter);
+    });
+}
+
+pub(crate) fn clear(name: &str) {
+    FILTERS.with(|filters| {
+        filters.borrow_mut().remove(name);
+    });
+}
+
+/// Passes an update through the filter of its signal, if any.
+///
+/// Returns `None` if the filter dropped the update.
+pub(crate) fn apply(update: ServerSignalUpdate) -> Option<ServerSignalUpdate> {
+    // Cloned out, so that the filter can install or remove filters itself
+    let filter = FILTERS.with(|filters| filters.borrow().get(&*update.name).cloned());
+    match filter {
+        Some(filter) => filter(update),
+        None => Some(update),
+    }
+}
@@ -0,0 +1,409 @@
+use serde::de::value::{MapAccessDeserializer, MapDeserializer, SeqDeserializer};
+use serde::de::{self, DeserializeOwned, IntoDeserializer, Visitor};
+use serde_json::Value;
+
+/// How non-finite floats, `NaN` and the infinities, are encoded in the json documents.
+///
+/// Json has no representation for non-finite numbers, and `serde_json` silently turns them into
+/// `null`, which `f64` fields then fail to deserialize from. Clients decode both `null` and the
+/// string sentinels back into floats, so either encoding keeps a signal working.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum NonFinite {
+    /// Encode as `null`, which is decoded as `NaN`.
+    #[default]
+    Null,
+    /// Encode as the strings `"NaN"`, `"Infinity"` and `"-Infinity"`, which keep the value.
+    String,
+    /// Fail to encode the value.
+    Error,
+}
+
+#[cfg(feature = "diff")]
+pub(crate) use encode::to_value_with;
+
+/// Converts a json document to a value, decoding `null` and the string sentinels of
+/// [`NonFinite::String`] as floats where a float is expected.
+pub(crate) fn from_value_lenient<T>(doc: Value) -> Result<T, serde_json::Error>
+where
+    T: DeserializeOwned,
+{
+    T::deserialize(Decode(doc))
+}
+
+#[cfg(feature = "diff")]
+mod encode {
+    use serde::ser::{self, Serialize, Serializer};
+    use serde_json::Value;
+
+    use super::NonFinite;
+
+    /// Converts `value` to a json document, encoding non-finite floats as configured.
+    pub(crate) fn to_value_with<T>(
+        value: &T,
+        non_finite: NonFinite,
+    ) -> Result<Value, serde_json::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        Encode(value, non_finite).serialize(serde_json::value::Serializer)
+    }
+
+    /// A value serialized with non-finite floats encoded as configured.
+    struct Encode<'a, T: ?Sized>(&'a T, NonFinite);
+
+    impl<T> Serialize for Encode<'_, T>
+    where
+        T: Serialize + ?Sized,
+    {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.0.serialize(Encoder(serializer, self.1))
+        }
+    }
+
+    /// Forwards to the wrapped serializer, intercepting floats.
+    struct Encoder<S>(S, NonFinite);
+
+    impl<S: Serializer> Encoder<S> {
+        fn float(self, value: f64) -> Result<S::Ok, S::Error> {
+            if value.is_finite() {
+                return self.0.serialize_f64(value);
+            }
+            match self.1 {
+                NonFinite::Null => self.0.serialize_unit(),
+                NonFinite::String if value.is_nan() => self.0.serialize_str("NaN"),
+                NonFinite::String if value > 0.0 => self.0.serialize_str("Infinity"),
+                NonFinite::String => self.0.serialize_str("-Infinity"),
+                NonFinite::Error => Err(ser::Error::custom(format!(
+                    "cannot encode non-finite float {value}"
+                ))),
+            }
+        }
+    }
+
+    macro_rules! forward {
+        ($($method:ident($($arg:ident: $ty:ty),*);)*) => {
+            $(
+                fn $method(self, $($arg: $ty),*) -> Result<S::Ok, S::Error> {
+                    self.0.$method($($arg),*)
+                }
+            )*
+        };
+    }
+
+    impl<S: Serializer> Serializer for Encoder<S> {
+        type Ok = S::Ok;
+        type Error = S::Error;
+        type SerializeSeq = Compound<S::SerializeSeq>;
+        type SerializeTuple = Compound<S::SerializeTuple>;
+        type SerializeTupleStruct = Compound<S::SerializeTupleStruct>;
+        type SerializeTupleVariant = Compound<S::SerializeTupleVariant>;
+        type SerializeMap = Compound<S::SerializeMap>;
+        type SerializeStruct = Compound<S::SerializeStruct>;
+        type SerializeStructVariant = Compound<S::SerializeStructVariant>;
+
+        forward! {
+            serialize_bool(v: bool);
+            serialize_i8(v: i8);
+            serialize_i16(v: i16);
+            serialize_i32(v: i32);
+            serialize_i64(v: i64);
+            serialize_i128(v: i128);
+            serialize_u8(v: u8);
+            serialize_u16(v: u16);
+            serialize_u32(v: u32);
+            serialize_u64(v: u64);
+            serialize_u128(v: u128);
+            serialize_char(v: char);
+            serialize_str(v: &str);
+            serialize_bytes(v: &[u8]);
+            serialize_none();
+            serialize_unit();
+            serialize_unit_struct(name: &'static str);
+            serialize_unit_variant(name: &'static str, index: u32, variant: &'static str);
+        }
+
+        fn serialize_f32(self, v: f32) -> Result<S::Ok, S::Error> {
+            if v.is_finite() {
+                return self.0.serialize_f32(v);
+            }
+            self.float(v.into())
+        }
+
+        fn serialize_f64(self, v: f64) -> Result<S::Ok, S::Error> {
+            self.float(v)
+        }
+
+        fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<S::Ok, S::Error> {
+            self.0.serialize_some(&Encode(value, self.1))
+        }
+
+        fn serialize_newtype_struct<T: Serialize + ?Sized>(
+            self,
+            name: &'static str,
+            value: &T,
+        ) -> Result<S::Ok, S::Error> {
+            self.0
+                .serialize_newtype_struct(name, &Encode(value, self.1))
+        }
+
+        fn serialize_newtype_variant<T: Serialize + ?Sized>(
+            self,
+            name: &'static str,
+            index: u32,
+            variant: &'static str,
+            value: &T,
+        ) -> Result<S::Ok, S::Error> {
+            self.0
+                .serialize_newtype_variant(name, index, variant, &Encode(value, self.1))
+        }
+
+        fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, S::Error> {
+            Ok(Compound(self.0.serialize_seq(len)?, self.1))
+        }
+
+        fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, S::Error> {
+            Ok(Compound(self.0.serialize_tuple(len)?, self.1))
+        }
+
+        fn serialize_tuple_struct(
+            self,
+            name: &'static str,
+            len: usize,
+        ) -> Result<Self::SerializeTupleStruct, S::Error> {
+            Ok(Compound(self.0.serialize_tuple_struct(name, len)?, self.1))
+        }
+
+        fn serialize_tuple_variant(
+            self,
+            name: &'static str,
+            index: u32,
+            variant: &'static str,
+            len: usize,
+        ) -> Result<Self::SerializeTupleVariant, S::Error> {
+            let inner = self.0.serialize_tuple_variant(name, index, variant, len)?;
+            Ok(Compound(inner, self.1))
+        }
+
+        fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, S::Error> {
+            Ok(Compound(self.0.serialize_map(len)?, self.1))
+        }
+
+        fn serialize_struct(
+            self,
+            name: &'static str,
+            len: usize,
+        ) -> Result<Self::SerializeStruct, S::Error> {
+            Ok(Compound(self.0.serialize_struct(name, len)?, self.1))
+        }
+
+        fn serialize_struct_variant(
+            self,
+            name: &'static str,
+            index: u32,
+            variant: &'static str,
+            len: usize,
+        ) -> Result<Self::SerializeStructVariant, S::Error> {
+            let inner = self.0.serialize_struct_variant(name, index, variant, len)?;
+            Ok(Compound(inner, self.1))
+        }
+    }
+
+    /// Forwards to a compound serializer, wrapping its elements.
+    struct Compound<C>(C, NonFinite);
+
+    impl<C: ser::SerializeSeq> ser::SerializeSeq for Compound<C> {
+        type Ok = C::Ok;
+        type Error = C::Error;
+
+        fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+            self.0.serialize_element(&Encode(value, self.1))
+        }
+
+        fn end(self) -> Result<C::Ok, C::Error> {
+            self.0.end()
+        }
+    }
+
+    impl<C: ser::SerializeTuple> ser::SerializeTuple for Compound<C> {
+        type Ok = C::Ok;
+        type Error = C::Error;
+
+        fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+            self.0.serialize_element(&Encode(value, self.1))
+        }
+
+        fn end(self) -> Result<C::Ok, C::Error> {
+            self.0.end()
+        }
+    }
+
+    impl<C: ser::SerializeTupleStruct> ser::SerializeTupleStruct for Compound<C> {
+        type Ok = C::Ok;
+        type Error = C::Error;
+
+        fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+            self.0.serialize_field(&Encode(value, self.1))
+        }
+
+        fn end(self) -> Result<C::Ok, C::Error> {
+            self.0.end()
+        }
+    }
+
+    impl<C: ser::SerializeTupleVariant> ser::SerializeTupleVariant for Compound<C> {
+        type Ok = C::Ok;
+        type Error = C::Error;
+
+        fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+            self.0.serialize_field(&Encode(value, self.1))
+        }
+
+        fn end(self) -> Result<C::Ok, C::Error> {
+            self.0.end()
+        }
+    }
+
+    impl<C: ser::SerializeMap> ser::SerializeMap for Compound<C> {
+        type Ok = C::Ok;
+        type Error = C::Error;
+
+        fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), C::Error> {
+            self.0.serialize_key(&Encode(key, self.1))
+        }
+
+        fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+            self.0.serialize_value(&Encode(value, self.1))
+        }
+
+        fn end(self) -> Result<C::Ok, C::Error> {
+            self.0.end()
+        }
+    }
+
+    impl<C: ser::SerializeStruct> ser::SerializeStruct for Compound<C> {
+        type Ok = C::Ok;
+        type Error = C::Error;
+
+        fn serialize_field<T: Serialize + ?Sized>(
+            &mut self,
+            key: &'static str,
+            value: &T,
+        ) -> Result<(), C::Error> {
+            self.0.serialize_field(key, &Encode(value, self.1))
+        }
+
+        fn end(self) -> Result<C::Ok, C::Error> {
+            self.0.end()
+        }
+    }
+
+    impl<C: ser::SerializeStructVariant> ser::SerializeStructVariant for Compound<C> {
+        type Ok = C::Ok;
+        type Error = C::Error;
+
+        fn serialize_field<T: Serialize + ?Sized>(
+            &mut self,
+            key: &'static str,
+            value: &T,
+        ) -> Result<(), C::Error> {
+            self.0.serialize_field(key, &Encode(value, self.1))
+        }
+
+        fn end(self) -> Result<C::Ok, C::Error> {
+            self.0.end()
+        }
+    }
+}
+
+/// Deserializes from a json document like `serde_json::Value`, but also decodes `null` and the
+/// non-finite sentinels where a float is expected.
+struct Decode(Value);
+
+impl Decode {
+    fn float(&self) -> Option<f64> {
+        match &self.0 {
+            Value::Null => Some(f64::NAN),
+            Value::String(s) if s == "NaN" => Some(f64::NAN),
+            Value::String(s) if s == "Infinity" => Some(f64::INFINITY),
+            Value::String(s) if s == "-Infinity" => Some(f64::NEG_INFINITY),
+            _ => None,
+        }
+    }
+}
+
+impl IntoDeserializer<'_, serde_json::Error> for Decode {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Decode {
+    type Error = serde_json::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Value::Array(values) => {
+                let mut seq = SeqDeserializer::new(values.into_iter().map(Decode));
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Value::Object(map) => {
+                let mut map = MapDeserializer::new(map.into_iter().map(|(k, v)| (k, Decode(v))));
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_f64(visitor)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.float() {
+            Some(value) => visitor.visit_f64(value),
+            None => self.0.deserialize_f64(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Value::Null => visitor.visit_none(),
+            value => visitor.visit_some(Decode(value)),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Value::Object(map) => {
+                let map = MapDeserializer::new(map.into_iter().map(|(k, v)| (k, Decode(v))));
+                visitor.visit_enum(MapAccessDeserializer::new(map))
+            }
+            value => value.deserialize_enum(name, variants, visitor),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 char str string bytes byte_buf unit
+        unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
@@ -0,0 +1,124 @@
+/// The query parameter listing the formats a client supports, best first.
+pub(crate) const FORMATS_PARAM: &str = "sse_formats";
+
+/// The format of the updates sent on one SSE connection.
+///
+/// Clients built with the `msgpack` feature advertise MessagePack in the query string of the
+/// SSE url, and the server picks the best format both sides support with
+/// [`SseFormat::negotiate`], or by extracting `SseFormat` in the SSE handler:
+///
+/// ```ignore
+/// async fn handle_sse(format: SseFormat) -> Sse<impl Stream<Item = ServerSentEventsItem>> {
+///     Sse::new(ServerSentEvents::new("counter", counts()).unwrap().format(format))
+/// }
+/// ```
+///
+/// Other clients, such as `curl` or the browser's devtools, still get readable json.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SseFormat {
+    /// Updates are sent as json.
+    #[default]
+    Json,
+    /// Updates are sent as base64 encoded MessagePack, in `msgpack` events.
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+    /// Updates are sent as base64 encoded protobuf messages, in `protobuf` events.
+    ///
+    /// This is for clients in other languages, such as mobile apps, which generate their
+    /// decoders from `proto/leptos_sse.proto`. Leptos clients never ask for it.
+    #[cfg(feature = "protobuf")]
+    Protobuf,
+}
+
+impl SseFormat {
+    /// Returns the name of the format in the query string.
+    pub fn name(self) -> &'static str {
+        match self {
+            SseFormat::Json => "json",
+            #[cfg(feature = "msgpack")]
+            SseFormat::MessagePack => "msgpack",
+            #[cfg(feature = "protobuf")]
+            SseFormat::Protobuf => "protobuf",
+        }
+    }
+
+    /// Returns the format named `name`, if it is supported.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "json" => Some(SseFormat::Json),
+            #[cfg(feature = "msgpack")]
+            "msgpack" => Some(SseFormat::MessagePack),
+            #[cfg(feature = "protobuf")]
+            "protobuf" => Some(SseFormat::Protobuf),
+            _ => None,
+        }
+    }
+
+    /// Picks the first format listed by the client which is supported, from the query string of
+    /// an SSE request.
+    ///
+    /// Clients which don't list any formats get json.
+    ///
+    /// ```
+    /// use leptos_sse::SseFormat;
+    ///
+    /// assert_eq!(SseFormat::negotiate(Some("sse_formats=cbor,json")), SseFormat::Json);
+    /// assert_eq!(SseFormat::negotiate(None), SseFormat::Json);
+    /// ```
+    pub fn negotiate(query: Option<&str>) -> Self {
+        query
+            .unwrap_or_default()
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .filter(|(key, _)| *key == FORMATS_PARAM)
+            .flat_map(|(_, formats)| formats.split([',', ' ']))
+            .find_map(SseFormat::from_name)
+            .unwrap_or_default()
+    }
+}
+
+/// Appends the formats this client supports to the SSE url.
+#[cfg(all(target_arch = "wasm32", feature = "client", feature = "msgpack"))]
+pub(crate) fn advertise(url: &str) -> String {
+    let separator = if url.contains('?') { '&' } else { '?' };
+    format!("{url}{separator}{FORMATS_PARAM}=msgpack,json")
+}
+
+#[cfg(all(
+    feature = "msgpack",
+    any(
+        all(target_arch = "wasm32", feature = "client"),
+        all(
+            feature = "ssr",
+            any(feature = "actix", feature = "axum", feature = "spin")
+        )
+    )
+))]
+pub(crate) mod msgpack {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+
+    use crate::ServerSignalUpdate;
+
+    /// The named event carrying an update as base64 encoded MessagePack.
+    pub(crate) const MSGPACK_EVENT: &str = "msgpack";
+
+    #[cfg(all(
+        feature = "ssr",
+        any(feature = "actix", feature = "axum", feature = "spin")
+    ))]
+    pub(crate) fn encode(update: &ServerSignalUpdate) -> Result<String, serde_json::Error> {
+        let bytes = rmp_serde::to_vec_named(update).map_err(serde::ser::Error::custom)?;
+        Ok(STANDARD.encode(bytes))
+    }
+
+    /// Decodes a `msgpack` event into the json of its update.
+    #[cfg(any(all(target_arch = "wasm32", feature = "client"), feature = "testing"))]
+    pub(crate) fn decode(data: &str) -> Result<String, String> {
+        let bytes = STANDARD.decode(data).map_err(|err| err.to_string())?;
+        let update: ServerSignalUpdate =
+            rmp_serde::from_slice(&bytes).map_err(|err| err.to_string())?;
+        serde_json::to_string(&update).map_err(|err| err.to_string())
+    }
+}
@@ -0,0 +1,99 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use leptos::prelude::*;
+
+use crate::{use_sse_connection_state, use_sse_stale, use_sse_version, SseConnectionState};
+
+/// A signal controlled by the server, together with the state of its updates, see
+/// [`use_sse_signal`](crate::use_sse_signal).
+///
+/// The accessors track what they read, like [`ReadSignal::get`], so views and effects using
+/// them rerun when it changes. Like a [`ReadSignal`], it is `Copy`, and lives as long as the
+/// reactive owner it was created in.
+pub struct SseSignal<T> {
+    value: ReadSignal<T>,
+    version: ReadSignal<u64>,
+    last_updated: ReadSignal<Option<f64>>,
+    stale: ReadSignal<bool>,
+    connection: ReadSignal<SseConnectionState>,
+}
+
+impl<T> SseSignal<T>
+where
+    T: Send + Sync + 'static,
+{
+    pub(crate) fn new(name: Cow<'static, str>, value: ReadSignal<T>) -> Self {
+        cfg_if::cfg_if! {
+            if #[cfg(target_arch = "wasm32")] {
+                let last_updated = crate::version::last_updated(name.clone());
+            } else {
+                let last_updated = ArcRwSignal::new(None).read_only();
+            }
+        }
+        SseSignal {
+            value,
+            version: use_sse_version(name.clone()).into(),
+            last_updated: last_updated.into(),
+            stale: use_sse_stale(name).into(),
+            connection: use_sse_connection_state().into(),
+        }
+    }
+
+    /// Returns the current value.
+    pub fn value(&self) -> T
+    where
+        T: Clone,
+    {
+        self.value.get()
+    }
+
+    /// Applies `f` to the current value, without cloning it.
+    pub fn with<U>(&self, f: impl FnOnce(&T) -> U) -> U {
+        self.value.with(f)
+    }
+
+    /// Returns the number of updates applied to the signal, see
+    /// [`use_sse_version`](crate::use_sse_version).
+    pub fn version(&self) -> u64 {
+        self.version.get()
+    }
+
+    /// Returns when the last update was applied, in milliseconds since the Unix epoch on the
+    /// clock of the client, or `None` before the first one.
+    pub fn last_updated(&self) -> Option<f64> {
+        self.last_updated.get()
+    }
+
+    /// Returns `true` while the data is stale, see [`use_sse_stale`](crate::use_sse_stale).
+    pub fn is_stale(&self) -> bool {
+        self.stale.get()
+    }
+
+    /// Returns the state of the SSE connection, see
+    /// [`use_sse_connection_state`](crate::use_sse_connection_state).
+    pub fn connection(&self) -> SseConnectionState {
+        self.connection.get()
+    }
+
+    /// Returns the signal of the value.
+    pub fn signal(&self) -> ReadSignal<T> {
+        self.value
+    }
+}
+
+impl<T> Clone for SseSignal<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for SseSignal<T> {}
+
+impl<T> fmt::Debug for SseSignal<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SseSignal")
+            .field("value", &self.value)
+            .finish_non_exhaustive()
+    }
+}
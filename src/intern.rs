@@ -0,0 +1,49 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+use crate::ServerSignalUpdate;
+
+thread_local! {
+    /// The names bound to the ids of the interned signals.
+    static IDS: RefCell<HashMap<u32, &'static str>> = RefCell::new(HashMap::new());
+    /// The interned names, leaked once each so that updates can borrow them.
+    static NAMES: RefCell<HashSet<&'static str>> = RefCell::new(HashSet::new());
+}
+
+/// Fills in the name of an update to an interned signal, see `ServerSentEvents::intern`.
+///
+/// The first update of an interned stream carries both the name and the id, which binds them,
+/// and the following ones only the id. Returns `None` for an id which was never bound.
+pub(crate) fn resolve(mut update: ServerSignalUpdate) -> Option<ServerSignalUpdate> {
+    let Some(id) = update.id else {
+        return Some(update);
+    };
+    if update.name.is_empty() {
+        update.name = Cow::Borrowed(name(id)?);
+    } else {
+        let name = intern(&update.name);
+        IDS.with(|ids| ids.borrow_mut().insert(id, name));
+        update.name = Cow::Borrowed(name);
+    }
+    Some(update)
+}
+
+/// Returns the name bound to `id`, if any.
+pub(crate) fn name(id: u32) -> Option<&'static str> {
+    IDS.with(|ids| ids.borrow().get(&id).copied())
+}
+
+fn intern(name: &str) -> &'static str {
+    NAMES.with(|names| {
+        let mut names = names.borrow_mut();
+        match names.get(name) {
+            Some(name) => name,
+            None => {
+                let name: &'static str = Box::leak(name.into());
+                names.insert(name);
+                name
+            }
+        }
+    })
+}
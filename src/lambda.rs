@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+use axum::response::sse::Sse;
+use axum::Router;
+use futures::stream::Stream;
+
+use crate::axum::{Heartbeat, MaxAge, ServerSentEventsItem};
+
+/// The longest a Lambda function can run, and therefore stream a response.
+pub const LAMBDA_MAX_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+/// How long before the function times out the stream asks the client to reconnect.
+const TIMEOUT_MARGIN: Duration = Duration::from_secs(30);
+
+/// How often a `heartbeat` event is sent while no update is.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Serves `router` on AWS Lambda, streaming the responses.
+///
+/// Response streaming is only available through Lambda function urls, with the invoke mode
+/// set to `RESPONSE_STREAM`. Other responses are streamed too, which makes no difference to
+/// the client.
+///
+/// ```ignore
+/// #[tokio::main]
+/// async fn main() -> Result<(), lambda_http::Error> {
+///     let app = Router::new().route("/sse", get(handle_sse));
+///     leptos_sse::run_lambda(app).await
+/// }
+/// ```
+pub async fn run_lambda(router: Router) -> Result<(), lambda_http::Error> {
+    lambda_http::run_with_streaming_response(router).await
+}
+
+/// Wraps the SSE `events` of a handler running on Lambda, see [`run_lambda`].
+///
+/// A streaming function is stopped once it reaches its `timeout`, which browsers report as an
+/// error. The stream asks the client to reconnect shortly before, see [`MaxAge`], so the
+/// signals resync on a new invocation instead. `timeout` is the timeout configured for the
+/// function, at most [`LAMBDA_MAX_TIMEOUT`].
+///
+/// The stream also sends a `heartbeat` event every 10 seconds while there are no updates,
+/// which keeps the function url from closing an idle response, and lets clients detect a
+/// stalled invocation with `SseOptions::heartbeat_timeout`, see [`Heartbeat`].
+///
+/// ```ignore
+/// async fn handle_sse() -> Sse<impl Stream<Item = ServerSentEventsItem>> {
+///     let events = ServerSentEvents::new("counter", counts()).unwrap();
+///     leptos_sse::lambda_sse(events, Duration::from_secs(300))
+/// }
+/// ```
+///
+/// This must be called within a Tokio runtime, such as in a request handler.
+pub fn lambda_sse<S>(events: S, timeout: Duration) -> Sse<impl Stream<Item = ServerSentEventsItem>>
+where
+    S: Stream<Item = ServerSentEventsItem> + Send + 'static,
+{
+    let max_age = match timeout.min(LAMBDA_MAX_TIMEOUT) {
+        timeout if timeout > TIMEOUT_MARGIN * 2 => timeout - TIMEOUT_MARGIN,
+        timeout => timeout / 2,
+    };
+    Sse::new(Heartbeat::new(
+        MaxAge::new(events, max_age),
+        HEARTBEAT_INTERVAL,
+    ))
+}
@@ -3,14 +3,126 @@
 
 use std::borrow::Cow;
 
+#[cfg(feature = "client")]
+use futures::future;
+#[cfg(feature = "client")]
+use futures::stream::{Stream, StreamExt};
 use json_patch::Patch;
+#[cfg(feature = "client")]
 use leptos::prelude::*;
 use serde::{Deserialize, Serialize};
+#[cfg(any(feature = "client", feature = "diff"))]
 use serde_json::Value;
+#[cfg(feature = "client")]
 use wasm_bindgen::JsValue;
 
+#[cfg(feature = "client")]
+use crate::compat::{new_arc_signal, new_signal, new_signal_local, Local};
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown", not(feature = "client")))]
+compile_error!("the `client` feature is required to build for the browser");
+
+/// Logs a message, unless logging has been disabled in the [`SseOptions`].
+#[cfg(all(target_arch = "wasm32", feature = "client", feature = "logging"))]
+macro_rules! sse_log {
+    ($($t:tt)*) => {
+        if $crate::connection::logging() {
+            leptos::logging::log!($($t)*);
+        }
+    };
+}
+
+/// Without the `logging` feature, the messages are type-checked but left out of the binary.
+#[cfg(all(target_arch = "wasm32", feature = "client", not(feature = "logging")))]
+macro_rules! sse_log {
+    ($($t:tt)*) => {
+        if false {
+            let _ = format_args!($($t)*);
+        }
+    };
+}
+
+#[cfg(any(
+    all(target_arch = "wasm32", feature = "client"),
+    all(feature = "ssr", any(feature = "actix", feature = "axum"))
+))]
+mod chunk;
+
+#[cfg(any(
+    all(target_arch = "wasm32", feature = "client"),
+    all(
+        feature = "ssr",
+        any(feature = "actix", feature = "axum", feature = "spin")
+    )
+))]
+#[cfg_attr(not(any(feature = "actix", feature = "axum")), allow(dead_code))]
+mod control;
+
+#[cfg(all(
+    feature = "ssr",
+    any(feature = "actix", feature = "axum", feature = "spin")
+))]
+mod encoder;
+#[cfg(all(
+    feature = "protobuf",
+    feature = "ssr",
+    any(feature = "actix", feature = "axum", feature = "spin")
+))]
+mod protobuf;
+
+cfg_if::cfg_if! {
+    if #[cfg(all(feature = "ssr", any(feature = "actix", feature = "axum")))] {
+        #[cfg(feature = "compression")]
+        mod compression;
+        mod crdt;
+        #[cfg(feature = "client")]
+        mod emit;
+        mod error;
+        mod event_id;
+        mod origin;
+        mod registry;
+        mod rewrite;
+        mod update_log;
+        #[cfg(feature = "webhooks")]
+        mod webhook;
+        #[cfg(feature = "compression")]
+        pub use crate::compression::SseCompression;
+        pub use crate::crdt::*;
+        #[cfg(feature = "client")]
+        pub use crate::emit::*;
+        pub use crate::error::*;
+        pub use crate::event_id::EventIds;
+        pub use crate::origin::*;
+        pub use crate::registry::*;
+        pub use crate::rewrite::PatchRewrite;
+        pub use crate::update_log::SseUpdateLog;
+        #[cfg(feature = "webhooks")]
+        pub use crate::webhook::SseWebhook;
+    }
+}
+
+#[cfg(feature = "proptest")]
+mod roundtrip;
+#[cfg(feature = "proptest")]
+pub use crate::roundtrip::*;
+#[cfg(all(
+    feature = "testing",
+    feature = "ssr",
+    any(feature = "actix", feature = "axum")
+))]
+mod testing;
+#[cfg(all(
+    feature = "testing",
+    feature = "ssr",
+    any(feature = "actix", feature = "axum")
+))]
+pub use crate::testing::SseRecorder;
+
 cfg_if::cfg_if! {
-    if #[cfg(all(feature = "actix", feature = "ssr"))] {
+    if #[cfg(all(feature = "actix", feature = "axum", feature = "ssr"))] {
+        // The axum types are exported when both integrations are enabled
+        pub mod actix;
+    } else if #[cfg(all(feature = "actix", feature = "ssr"))] {
         mod actix;
         pub use crate::actix::*;
     }
@@ -23,17 +135,165 @@ cfg_if::cfg_if! {
     }
 }
 
+cfg_if::cfg_if! {
+    if #[cfg(all(feature = "lambda", feature = "ssr"))] {
+        mod lambda;
+        pub use crate::lambda::*;
+    }
+}
+
+#[cfg(feature = "tauri")]
+mod tauri;
+#[cfg(feature = "tauri")]
+pub use crate::tauri::*;
+
+cfg_if::cfg_if! {
+    if #[cfg(all(feature = "spin", feature = "ssr"))] {
+        mod spin;
+        pub use crate::spin::*;
+    }
+}
+
+mod ack;
+mod channel;
+#[cfg(all(feature = "client", feature = "chaos"))]
+mod chaos;
+mod codec;
+#[cfg(feature = "client")]
+mod compat;
+#[cfg(feature = "client")]
+mod diagnostics;
+mod discovery;
+#[cfg(feature = "client")]
+mod documents;
+#[cfg(feature = "encryption")]
+mod encryption;
+#[cfg(feature = "client")]
+mod export;
+mod float;
+mod format;
+#[cfg(feature = "client")]
+mod handle;
+#[cfg(feature = "client")]
+mod list;
+mod macros;
+#[cfg(feature = "client")]
+mod options;
+#[cfg(feature = "client")]
+mod recording;
+#[cfg(feature = "client")]
+mod resource;
+mod schema;
+mod scope;
+#[cfg(feature = "signing")]
+mod signing;
+#[cfg(all(
+    feature = "cbor",
+    any(
+        all(target_arch = "wasm32", feature = "client"),
+        all(
+            feature = "ssr",
+            any(feature = "actix", feature = "axum", feature = "spin")
+        )
+    )
+))]
+mod snapshot;
+#[cfg(feature = "client")]
+mod state;
+#[cfg(feature = "client")]
+mod ttl;
+#[cfg(feature = "client")]
+mod wrappers;
+pub use crate::ack::{SseAck, SseClientId, SseSubscription};
+pub use crate::channel::*;
+#[cfg(all(feature = "client", feature = "chaos"))]
+pub use crate::chaos::SseChaos;
+pub use crate::codec::*;
+#[cfg(feature = "client")]
+pub use crate::diagnostics::SseDiagnostics;
+pub use crate::discovery::SignalDescriptor;
+#[cfg(feature = "client")]
+pub use crate::documents::SseDocumentInfo;
+#[cfg(feature = "encryption")]
+pub use crate::encryption::*;
+#[cfg(feature = "client")]
+pub use crate::export::SseStateSnapshot;
+pub use crate::float::NonFinite;
+pub use crate::format::SseFormat;
+#[cfg(feature = "client")]
+pub use crate::handle::SseSignal;
+#[cfg(feature = "client")]
+pub use crate::list::*;
+#[cfg(feature = "client")]
+pub use crate::options::*;
+#[cfg(feature = "client")]
+pub use crate::recording::SseRecording;
+#[cfg(feature = "client")]
+pub use crate::resource::*;
+pub use crate::schema::SseSchema;
+pub use crate::scope::*;
+#[cfg(feature = "client")]
+pub use crate::state::{SseConnectionState, SseReconnectCause, SseReconnectEvent};
+#[cfg(feature = "client")]
+pub use crate::ttl::SseTtl;
+#[cfg(feature = "client")]
+pub use crate::wrappers::SseSignalExt;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use futures;
+    pub use serde_json;
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(all(target_arch = "wasm32", feature = "client"))] {
+        mod clock;
+        mod connection;
+        mod dom_events;
+        mod dynamic;
+        mod filter;
+        mod intern;
+        mod message;
+        mod observers;
+        mod pause;
+        mod persist;
+        mod prefetch;
+        mod rw;
+        mod selector;
+        mod series;
+        mod stream;
+        mod version;
+        mod worker;
+    }
+}
+
 /// A server signal update containing the signal type name and json patch.
 ///
 /// This is whats sent over the SSE, and is used to patch the signal.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ServerSignalUpdate {
+    /// Left out of the updates to an interned signal once its id is bound, see
+    /// `ServerSentEvents::intern`.
+    #[serde(default, skip_serializing_if = "str::is_empty")]
     name: Cow<'static, str>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<u32>,
     patch: Patch,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    seq: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sig: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    traceparent: Option<String>,
 }
 
 impl ServerSignalUpdate {
     /// Creates a new [`ServerSignalUpdate`] from an old and new instance of `T`.
+    ///
+    /// Non-finite floats are encoded as `null`, see [`ServerSignalUpdate::new_with_non_finite`].
+    #[cfg(feature = "diff")]
     pub fn new<T>(
         name: impl Into<Cow<'static, str>>,
         old: &T,
@@ -42,25 +302,196 @@ impl ServerSignalUpdate {
     where
         T: Serialize,
     {
-        let left = serde_json::to_value(old)?;
-        let right = serde_json::to_value(new)?;
+        Self::new_with_non_finite(name, old, new, NonFinite::Null)
+    }
+
+    /// Creates a new [`ServerSignalUpdate`] from an old and new instance of `T`, encoding
+    /// non-finite floats as configured.
+    ///
+    /// This fails with [`NonFinite::Error`] if either instance contains a non-finite float.
+    #[cfg(feature = "diff")]
+    pub fn new_with_non_finite<T>(
+        name: impl Into<Cow<'static, str>>,
+        old: &T,
+        new: &T,
+        non_finite: NonFinite,
+    ) -> Result<Self, serde_json::Error>
+    where
+        T: Serialize,
+    {
+        let left = float::to_value_with(old, non_finite)?;
+        let right = float::to_value_with(new, non_finite)?;
         let patch = json_patch::diff(&left, &right);
         Ok(ServerSignalUpdate {
             name: name.into(),
+            id: None,
             patch,
+            seq: None,
+            version: None,
+            sig: None,
+            traceparent: None,
         })
     }
 
     /// Creates a new [`ServerSignalUpdate`] from two json values.
+    #[cfg(feature = "diff")]
     pub fn new_from_json<T>(name: impl Into<Cow<'static, str>>, old: &Value, new: &Value) -> Self {
         let patch = json_patch::diff(old, new);
         ServerSignalUpdate {
             name: name.into(),
+            id: None,
             patch,
+            seq: None,
+            version: None,
+            sig: None,
+            traceparent: None,
+        }
+    }
+
+    /// Sets the sequence number of this update within its stream.
+    ///
+    /// Sequence numbers start at 1 for every new stream, and the update with sequence number 1
+    /// is always a diff from `T::default()`. Clients use this to detect documents which are no
+    /// longer at the base the server is diffing from, and ignore updates whose sequence number
+    /// isn't greater than the last one applied, so replayed or duplicated events are never
+    /// applied twice.
+    pub fn with_seq(mut self, seq: u64) -> Self {
+        self.seq = Some(seq);
+        self
+    }
+
+    /// Sets the schema version of the document this update patches, see [`SseSchema`].
+    pub fn with_version(mut self, version: u32) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// Replaces the json patch of this update, for example to rewrite its paths in a filter,
+    /// see `set_sse_filter`.
+    ///
+    /// The signature of a signed update no longer matches once its patch has been replaced.
+    pub fn with_patch(mut self, patch: Patch) -> Self {
+        self.patch = patch;
+        self
+    }
+
+    /// Attaches the W3C trace context of the change this update carries, as a `traceparent`
+    /// header value.
+    ///
+    /// With the `tracing` feature, the client applies the update within a span recording it,
+    /// so that a state change can be traced from the backend request which caused it to the
+    /// browser, for example with `tracing-web`.
+    pub fn with_trace_context(mut self, traceparent: impl Into<String>) -> Self {
+        self.traceparent = Some(traceparent.into());
+        self
+    }
+
+    /// Returns the name of the signal this update is for.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the json patch of this update.
+    pub fn patch(&self) -> &Patch {
+        &self.patch
+    }
+
+    /// Returns the sequence number of this update, if any.
+    pub fn seq(&self) -> Option<u64> {
+        self.seq
+    }
+
+    /// Returns the schema version of the document this update patches, if any.
+    pub fn version(&self) -> Option<u32> {
+        self.version
+    }
+
+    /// Returns the W3C trace context of this update, if any.
+    pub fn trace_context(&self) -> Option<&str> {
+        self.traceparent.as_deref()
+    }
+}
+
+/// Switches the SSE connection to a new url.
+///
+/// The current connection is closed and a new one is opened with the same [`SseOptions`].
+/// Registered signals keep their current values until the new server stream sends its first
+/// update for them, which is then applied to `T::default()` just like on a fresh connection.
+/// Updates queued for signals which haven't been created yet are dropped.
+///
+/// This is useful when the endpoint changes at runtime, for example after login moves the user
+/// to a tenant-specific endpoint. It fails if no SSE has been provided.
+#[cfg(feature = "client")]
+#[allow(unused_variables)]
+pub fn switch_sse_url(url: impl Into<String>) -> Result<(), JsValue> {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            connection::switch_url(url.into())
+        } else {
+            Ok(())
         }
     }
 }
 
+/// A type with a default signal name.
+///
+/// The name defaults to the name of the type without its module path, so `my_app::Count` is
+/// named `"Count"`. With the `derive` feature, the trait can be derived, and the name overridden
+/// with the `sse` attribute:
+///
+/// ```
+/// # #[cfg(feature = "derive")] {
+/// use leptos_sse::SignalName;
+///
+/// #[derive(Default, SignalName)]
+/// #[sse(name = "counter")]
+/// pub struct Count {
+///     pub value: i32,
+/// }
+///
+/// #[derive(Default, SignalName)]
+/// pub struct Chat;
+///
+/// assert_eq!(Count::signal_name(), "counter");
+/// assert_eq!(Chat::signal_name(), "Chat");
+/// # }
+/// ```
+///
+/// Otherwise, implement the trait, overriding [`SignalName::signal_name`] to use another name:
+///
+/// ```
+/// use std::borrow::Cow;
+/// use leptos_sse::SignalName;
+///
+/// #[derive(Default)]
+/// pub struct Count {
+///     pub value: i32,
+/// }
+///
+/// impl SignalName for Count {
+///     fn signal_name() -> Cow<'static, str> {
+///         Cow::Borrowed("counter")
+///     }
+/// }
+/// ```
+///
+/// Using the type name keeps the client and the server from disagreeing on the name of a
+/// signal. Since [`std::any::type_name`] is not guaranteed to be stable between compiler
+/// versions, the client and the server should be built with the same toolchain when relying on
+/// the default.
+pub trait SignalName {
+    /// Returns the name of the signal for this type.
+    fn signal_name() -> Cow<'static, str> {
+        let name = std::any::type_name::<Self>();
+        let end = name.find('<').unwrap_or(name.len());
+        let start = name[..end].rfind("::").map_or(0, |i| i + 2);
+        Cow::Borrowed(&name[start..])
+    }
+}
+
+#[cfg(feature = "derive")]
+pub use leptos_sse_macros::SignalName;
+
 /// Provides a SSE url for server signals, if there is not already one provided.
 /// This ensures that you can provide it at the highest possible level, without overwriting a SSE
 /// that has already been provided (for example, by a server-rendering integration.)
@@ -79,9 +510,71 @@ impl ServerSignalUpdate {
 ///     // ...
 /// }
 /// ```
+///
+/// Pass [`SseOptions`] instead of a url to configure the connection.
+///
+/// The connection is global to the page rather than tied to the context it was provided in. In
+/// islands mode, where islands don't share context, call this in every island using server
+/// signals: only the first call opens the connection, and the others return right away.
+#[cfg(feature = "client")]
+pub fn provide_sse(options: impl Into<SseOptions>) -> Result<(), JsValue> {
+    provide_sse_inner(options.into())
+}
+
+/// Provides a SSE url for server signals, with the connection owned by a dedicated Web Worker.
+///
+/// The worker owns the `EventSource`, and relays its events to the main thread, which handles
+/// them like the events of a connection it owns. This keeps the connection and the parsing of
+/// the event stream off the UI thread, while only the messages cross threads, never the
+/// documents. Every option of [`SseOptions`] applies in worker mode, except that
+/// `with_event_source` has no `EventSource` to provide.
+///
+/// Like [`provide_sse`], this does nothing if a SSE has already been provided. This is the same
+/// as `provide_sse(SseOptions::new(url).worker(true))`.
+#[cfg(feature = "client")]
+pub fn provide_sse_worker(url: &str) -> Result<(), JsValue> {
+    provide_sse(SseOptions::new(url).worker(true))
+}
+
+/// Provides server signals with an `EventSource` managed by the app, instead of opening one.
+///
+/// This lets apps which already manage a connection, for example with `use_event_source` of
+/// [leptos-use], apply the updates it receives without opening a second one. The other
+/// messages of the connection are left to the app, and so is reconnecting. Call this before
+/// the connection opens, such as right after creating it, so no update is missed:
+///
+/// ```ignore
+/// let UseEventSourceReturn { event_source, .. } =
+///     use_event_source::<String, FromToStringCodec>("/sse");
+/// Effect::new(move |_| {
+///     if let Some(event_source) = event_source.get() {
+///         leptos_sse::provide_sse_event_source(&event_source).unwrap();
+///     }
+/// });
+/// ```
+///
+/// The other way around, `with_event_source` gives access to the connection opened by
+/// [`provide_sse`], to listen to other events with it. Like [`provide_sse`], this does nothing
+/// if a SSE has already been provided.
+///
+/// [leptos-use]: https://leptos-use.rs
+#[cfg(feature = "client")]
 #[allow(unused_variables)]
-pub fn provide_sse(url: &str) -> Result<(), JsValue> {
-    provide_sse_inner(url)
+pub fn provide_sse_event_source(event_source: &web_sys::EventSource) -> Result<(), JsValue> {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            if sse_initialized() {
+                sse_log!("SSE already initialized");
+                return Ok(());
+            }
+            connection::set_options(SseOptions::new(event_source.url()));
+            connection::attach(event_source)?;
+            mark_initialized();
+            Ok(())
+        } else {
+            Ok(())
+        }
+    }
 }
 
 /// Creates a signal which is controlled by the server.
@@ -91,6 +584,13 @@ pub fn provide_sse(url: &str) -> Result<(), JsValue> {
 ///
 /// For types that are not Send + Sync, use [`create_sse_signal_local`] instead.
 ///
+/// The signal doesn't need to be created below [`provide_sse`]: signals created before it, for
+/// example in a global store initialized at startup, are registered once the connection exists.
+/// Outside of a reactive owner, prefer [`create_sse_arc_signal`], which needs no `Effect`.
+///
+/// Signals created for the same name share one document, so several components, or several
+/// islands in islands mode, can subscribe to the same server signal.
+///
 /// # Example
 ///
 /// ```
@@ -113,16 +613,109 @@ pub fn provide_sse(url: &str) -> Result<(), JsValue> {
 ///     }
 /// }
 /// ```
+#[cfg(feature = "client")]
 #[allow(unused_variables)]
 pub fn create_sse_signal<T>(name: impl Into<Cow<'static, str>>) -> ReadSignal<T>
 where
     T: Default + Serialize + for<'de> Deserialize<'de> + Send + Sync + 'static,
 {
     let name = name.into();
-    let (get, set) = signal(T::default());
+    let (get, set) = new_signal(T::default());
     
     #[cfg(target_arch = "wasm32")]
-    setup_sse_signal(name, set);
+    setup_sse_signal::<T, JsonCodec>(name, set);
+
+    get
+}
+
+/// Creates a signal which is controlled by the server, with the state of its updates.
+///
+/// This is the same as [`create_sse_signal`], returning an [`SseSignal`] which also tells how
+/// many updates were applied, when the last one was, whether the data is stale, and the state
+/// of the connection, instead of reaching for each with its own function:
+///
+/// ```
+/// use leptos::prelude::*;
+/// use leptos_sse::{use_sse_signal, SseConnectionState};
+///
+/// #[component]
+/// pub fn Price() -> impl IntoView {
+///     let price = use_sse_signal::<f64>("price");
+///     view! {
+///         <p class:stale=move || price.is_stale()>{move || price.value()}</p>
+///         <Show when=move || price.connection() == SseConnectionState::Reconnecting>
+///             <small>"reconnecting…"</small>
+///         </Show>
+///     }
+/// }
+/// ```
+#[cfg(feature = "client")]
+pub fn use_sse_signal<T>(name: impl Into<Cow<'static, str>>) -> SseSignal<T>
+where
+    T: Default + Serialize + for<'de> Deserialize<'de> + Send + Sync + 'static,
+{
+    let name = name.into();
+    let value = create_sse_signal(name.clone());
+    SseSignal::new(name, value)
+}
+
+/// Creates a signal which is controlled by the server, for a name computed at runtime.
+///
+/// This is the same as [`create_sse_signal`], except that the client subscribes to `name` while
+/// a dynamic signal of that name exists, by posting its subscriptions to the
+/// [`subscription_url`](SseOptions::subscription_url). The server streams the signals a client
+/// subscribed to in the response of `SseRegistry::dynamic_events`, so signals don't have to be
+/// known when the connection opens:
+///
+/// ```
+/// use leptos::prelude::*;
+/// use leptos_sse::create_sse_dynamic_signal;
+/// # #[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+/// # pub struct Document { title: String }
+///
+/// #[component]
+/// pub fn Editor(id: u64) -> impl IntoView {
+///     let doc = create_sse_dynamic_signal::<Document>(format!("doc:{id}"));
+///     view! { <h1>{move || doc.get().title}</h1> }
+/// }
+/// ```
+///
+/// Once the last dynamic signal of a name is disposed, the client unsubscribes from it and
+/// drops its document, so names used by dynamic signals shouldn't be used by other signals.
+#[cfg(feature = "client")]
+pub fn create_sse_dynamic_signal<T>(name: impl Into<Cow<'static, str>>) -> ReadSignal<T>
+where
+    T: Default + Serialize + for<'de> Deserialize<'de> + Send + Sync + 'static,
+{
+    let name = name.into();
+    let signal = create_sse_signal(name.clone());
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        dynamic::acquire(name.clone());
+        on_cleanup(move || dynamic::release(&name));
+    }
+
+    signal
+}
+
+/// Creates a signal which is controlled by the server, converted with a custom [`SignalCodec`].
+///
+/// This is the same as [`create_sse_signal`], for types which don't round-trip through
+/// [`serde_json::Value`] with their `Serialize` and `Deserialize` implementations. The server
+/// must send the signal with the same codec.
+#[cfg(feature = "client")]
+#[allow(unused_variables)]
+pub fn create_sse_signal_with_codec<T, C>(name: impl Into<Cow<'static, str>>) -> ReadSignal<T>
+where
+    T: Default + Send + Sync + 'static,
+    C: SignalCodec<T> + 'static,
+{
+    let name = name.into();
+    let (get, set) = new_signal(T::default());
+
+    #[cfg(target_arch = "wasm32")]
+    setup_sse_signal::<T, C>(name, set);
 
     get
 }
@@ -131,13 +724,14 @@ where
 ///
 /// This is the same as [`create_sse_signal`] but uses LocalStorage for signals that don't
 /// implement Send + Sync.
+#[cfg(feature = "client")]
 #[allow(unused_variables)]
-pub fn create_sse_signal_local<T>(name: impl Into<Cow<'static, str>>) -> ReadSignal<T, LocalStorage>
+pub fn create_sse_signal_local<T>(name: impl Into<Cow<'static, str>>) -> ReadSignal<T, Local>
 where
     T: Default + Serialize + for<'de> Deserialize<'de> + 'static,
 {
     let name = name.into();
-    let (get, set) = signal_local(T::default());
+    let (get, set) = new_signal_local(T::default());
     
     #[cfg(target_arch = "wasm32")]
     setup_sse_signal_local(name, set);
@@ -145,214 +739,1538 @@ where
     get
 }
 
-cfg_if::cfg_if! {
-    if #[cfg(target_arch = "wasm32")] {
-        use std::collections::HashMap;
-        use std::rc::Rc;
-        use std::cell::RefCell;
-        use std::sync::{Arc, Mutex};
-
-        use web_sys::EventSource;
-        use leptos::prelude::*;
-
-        // Thread-local storage for EventSource since it's not Send + Sync
-        thread_local! {
-            static EVENT_SOURCE: RefCell<Option<EventSource>> = RefCell::new(None);
-            static STATE_SIGNALS: RefCell<HashMap<Cow<'static, str>, RwSignal<Value>>> = RefCell::new(HashMap::new());
-            static STATE_SIGNALS_LOCAL: RefCell<HashMap<Cow<'static, str>, RwSignal<Value, LocalStorage>>> = RefCell::new(HashMap::new());
-            static DELAYED_UPDATES: RefCell<HashMap<Cow<'static, str>, Vec<Patch>>> = RefCell::new(HashMap::new());
-        }
-
-        /// Context marker to indicate SSE has been initialized
-        #[derive(Clone, Debug, PartialEq, Eq)]
-        struct SseInitialized;
-
-        fn setup_sse_signal<T>(name: Cow<'static, str>, set: WriteSignal<T>)
-        where
-            T: Default + Serialize + for<'de> Deserialize<'de> + Send + Sync + 'static,
-        {
-            use leptos::prelude::*;
+/// Creates a signal which is controlled by the server, and can also be written on the client.
+///
+/// Local writes show up right away, for example while a server function saving them is in
+/// flight. If an update arrives from the server after a local write, `resolve` receives the
+/// local value and the value of the server, and returns the value the signal is set to,
+/// instead of the local write being overwritten:
+///
+/// ```
+/// use leptos::prelude::*;
+/// use leptos_sse::create_sse_signal_rw;
+/// # #[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+/// # pub struct Draft { title: String, comments: Vec<String> }
+///
+/// #[component]
+/// pub fn Editor() -> impl IntoView {
+///     // Keep the title being edited, and take the comments of the server
+///     let draft = create_sse_signal_rw("draft", |local: Draft, server: Draft| Draft {
+///         title: local.title,
+///         ..server
+///     });
+///     view! {
+///         <input
+///             prop:value=move || draft.with(|draft| draft.title.clone())
+///             on:input=move |ev| draft.update(|draft| draft.title = event_target_value(&ev))
+///         />
+///     }
+/// }
+/// ```
+///
+/// Local writes aren't sent to the server, see [`create_sse_action`] for saving them, and stay
+/// pending until an update from the server leaves the value equal to its document. Without a
+/// pending local write, updates from the server replace the value. Detecting local writes
+/// serializes the value on every update.
+#[cfg(feature = "client")]
+#[allow(unused_variables)]
+pub fn create_sse_signal_rw<T>(
+    name: impl Into<Cow<'static, str>>,
+    resolve: impl Fn(T, T) -> T + 'static,
+) -> RwSignal<T>
+where
+    T: Default + Serialize + for<'de> Deserialize<'de> + Send + Sync + 'static,
+{
+    let signal = RwSignal::new(T::default());
 
-            let signal = RwSignal::new(serde_json::to_value(T::default()).unwrap());
-            
-            if use_context::<SseInitialized>().is_some() {
-                leptos::logging::log!("Setting up SSE signal: {}", name);
-                
-                STATE_SIGNALS.with(|signals| {
-                    signals.borrow_mut().insert(name.clone(), signal);
-                });
+    #[cfg(target_arch = "wasm32")]
+    rw::setup(name.into(), signal, resolve);
 
-                Effect::new(move |_| {
-                    let new_value = serde_json::from_value(signal.get()).unwrap();
-                    set.set(new_value);
-                });
-            } else {
-                leptos::logging::error!(
-                    r#"server signal was used without a SSE being provided.
+    signal
+}
 
-Ensure you call `leptos_sse::provide_sse("http://localhost:3000/sse")` at the highest level in your app."#
-                );
+/// Creates an [`Action`] which writes a value to a signal created with
+/// [`create_sse_signal_rw`], and saves it on the server with `save`.
+///
+/// Dispatching the action sets the signal right away, then runs `save`, usually a server
+/// function, which serializes the value. The action is pending until `save` completes, and its
+/// value is the result, so forms and buttons can show the usual loading and error states. If
+/// `save` fails, the local write is discarded and the signal goes back to the value of the
+/// server.
+///
+/// ```ignore
+/// #[server]
+/// async fn save_draft(draft: Draft) -> Result<(), ServerFnError> {
+///     // ...
+/// }
+///
+/// #[component]
+/// pub fn Editor() -> impl IntoView {
+///     let draft = create_sse_signal_rw("draft", |local: Draft, _server: Draft| local);
+///     let save = create_sse_action("draft", draft, save_draft);
+///     view! {
+///         <button
+///             disabled=save.pending()
+///             on:click=move |_| { save.dispatch(Draft::default()); }
+///         >
+///             "Reset"
+///         </button>
+///     }
+/// }
+/// ```
+#[cfg(feature = "client")]
+#[allow(unused_variables)]
+pub fn create_sse_action<T, E, F, Fut>(
+    name: impl Into<Cow<'static, str>>,
+    signal: RwSignal<T>,
+    save: F,
+) -> Action<T, Result<(), E>>
+where
+    T: Clone + for<'de> Deserialize<'de> + Send + Sync + 'static,
+    E: Send + Sync + 'static,
+    F: Fn(T) -> Fut + Send + Sync + 'static,
+    Fut: std::future::Future<Output = Result<(), E>> + Send + 'static,
+{
+    let name = name.into();
+    Action::new(move |value: &T| {
+        signal.set(value.clone());
+        let saved = save(value.clone());
+        let name = name.clone();
+        async move {
+            let result = saved.await;
+            #[cfg(target_arch = "wasm32")]
+            if result.is_err() {
+                rw::revert(&name, signal);
             }
+            result
         }
+    })
+}
 
-        fn setup_sse_signal_local<T>(name: Cow<'static, str>, set: WriteSignal<T, LocalStorage>)
-        where
-            T: Default + Serialize + for<'de> Deserialize<'de> + 'static,
-        {
-            use leptos::prelude::*;
-
-            let signal = RwSignal::new_local(serde_json::to_value(T::default()).unwrap());
-            
-            if use_context::<SseInitialized>().is_some() {
-                STATE_SIGNALS_LOCAL.with(|signals| {
-                    signals.borrow_mut().insert(name.clone(), signal);
-                });
-
-                Effect::new(move |_| {
-                    let new_value = serde_json::from_value(signal.get()).unwrap();
-                    set.set(new_value);
-                });
-            } else {
-                leptos::logging::error!(
-                    r#"server signal was used without a SSE being provided.
+/// Enables or disables dispatching a `leptos-sse:update` [`CustomEvent`] on `window` for every
+/// applied update.
+///
+/// This lets JavaScript on the same page (legacy widgets, analytics) react to server signal
+/// changes without opening a second connection. The event `detail` is an object with the
+/// `name` of the signal, the json `patch` which was applied, and the resulting `value`.
+///
+/// ```js
+/// window.addEventListener("leptos-sse:update", (event) => {
+///   console.log(event.detail.name, event.detail.value);
+/// });
+/// ```
+///
+/// Disabled by default.
+///
+/// [`CustomEvent`]: https://developer.mozilla.org/en-US/docs/Web/API/CustomEvent
+#[cfg(feature = "client")]
+#[allow(unused_variables)]
+pub fn dispatch_dom_events(enabled: bool) {
+    #[cfg(target_arch = "wasm32")]
+    dom_events::set_enabled(enabled);
+}
 
-Ensure you call `leptos_sse::provide_sse("http://localhost:3000/sse")` at the highest level in your app."#
-                );
-            }
-        }
+/// Declares the schema version of a signal the client expects, with migrations from older
+/// versions.
+///
+/// Updates sent with an older version replace the whole document with the migrated one. Updates
+/// which can't be migrated, because a migration is missing or because the server sends a newer
+/// version, are logged and dropped.
+#[cfg(feature = "client")]
+#[allow(unused_variables)]
+pub fn set_sse_schema(name: impl Into<Cow<'static, str>>, schema: SseSchema) {
+    #[cfg(target_arch = "wasm32")]
+    schema::set(name.into(), schema);
+}
 
-        #[inline]
-        fn provide_sse_inner(url: &str) -> Result<(), JsValue> {
-            use web_sys::MessageEvent;
-            use wasm_bindgen::{prelude::Closure, JsCast};
-            use leptos::prelude::*;
-            use js_sys::{Function, JsString};
+/// Installs a filter on the updates to the signal `name`, replacing any previous one.
+///
+/// The filter receives every update before it is applied, after it has been verified and
+/// migrated, and returns the update to apply, or `None` to drop it. It can also return a
+/// different update, such as one with rewritten paths, see [`ServerSignalUpdate::with_patch`].
+///
+/// ```
+/// use leptos::prelude::*;
+/// use leptos_sse::set_sse_filter;
+///
+/// // Ignore the server while the user is editing
+/// let editing = RwSignal::new(false);
+/// set_sse_filter("profile", move |update| {
+///     (!editing.get_untracked()).then_some(update)
+/// });
+/// ```
+///
+/// The following updates are diffed from the value the server sent, so dropping or rewriting
+/// an update can leave the document out of step with the server, and later patches failing to
+/// apply. Reconnecting resyncs it, see [`switch_sse_url`].
+#[cfg(feature = "client")]
+#[allow(unused_variables)]
+pub fn set_sse_filter(
+    name: impl Into<Cow<'static, str>>,
+    filter: impl Fn(ServerSignalUpdate) -> Option<ServerSignalUpdate> + 'static,
+) {
+    #[cfg(target_arch = "wasm32")]
+    filter::set(name.into(), std::rc::Rc::new(filter));
+}
 
-            // Only initialize once
-            if use_context::<SseInitialized>().is_some() {
-                leptos::logging::log!("SSE already initialized");
-                return Ok(());
-            }
+/// Removes the filter on the updates to the signal `name`, see [`set_sse_filter`].
+#[cfg(feature = "client")]
+#[allow(unused_variables)]
+pub fn clear_sse_filter(name: &str) {
+    #[cfg(target_arch = "wasm32")]
+    filter::clear(name);
+}
 
-            leptos::logging::log!("Initializing SSE connection to: {}", url);
-            
-            let es = EventSource::new(url)?;
-            
-            // Add event listeners for debugging
-            {
-                use wasm_bindgen::JsCast;
-                
-                // Log when connection opens
-                let onopen = Closure::wrap(Box::new(move || {
-                    leptos::logging::log!("SSE connection opened successfully");
-                }) as Box<dyn Fn()>);
-                es.set_onopen(Some(onopen.as_ref().unchecked_ref()));
-                onopen.forget();
-                
-                // Log errors
-                let onerror = Closure::wrap(Box::new(move |_event: web_sys::Event| {
-                    leptos::logging::error!("SSE connection error occurred");
-                }) as Box<dyn Fn(_)>);
-                es.set_onerror(Some(onerror.as_ref().unchecked_ref()));
-                onerror.forget();
+/// Freezes the signal `name` at its current value, until [`resume_sse_signal`] is called.
+///
+/// This is for views the user explicitly freezes to inspect them, such as a live chart. The
+/// updates received while paused aren't queued: they are applied to a copy of the document
+/// instead, so a long pause only holds one document, and effects, observers and streams of
+/// values see nothing until the signal resumes. Updates to the signal are still published
+/// to [`use_sse_updates`].
+///
+/// ```
+/// use leptos::prelude::*;
+/// use leptos_sse::{pause_sse_signal, resume_sse_signal};
+///
+/// #[component]
+/// pub fn Chart() -> impl IntoView {
+///     let frozen = RwSignal::new(false);
+///     let toggle = move |_| {
+///         frozen.update(|frozen| *frozen = !*frozen);
+///         if frozen.get_untracked() {
+///             pause_sse_signal("chart");
+///         } else {
+///             resume_sse_signal("chart");
+///         }
+///     };
+///     let label = move || if frozen.get() { "Resume" } else { "Pause" };
+///     view! { <button on:click=toggle>{label}</button> }
+/// }
+/// ```
+#[cfg(feature = "client")]
+#[allow(unused_variables)]
+pub fn pause_sse_signal(name: impl Into<Cow<'static, str>>) {
+    #[cfg(target_arch = "wasm32")]
+    pause::pause(name.into());
+}
+
+/// Resumes the signal `name` paused with [`pause_sse_signal`].
+///
+/// The signal is set to the value it would have had without the pause, in one update. Does
+/// nothing if the signal isn't paused.
+#[cfg(feature = "client")]
+#[allow(unused_variables)]
+pub fn resume_sse_signal(name: &str) {
+    #[cfg(target_arch = "wasm32")]
+    pause::resume(name);
+}
+
+/// Exports the current documents of all server signals, with the sequence numbers of the last
+/// updates applied to them, see [`SseStateSnapshot`].
+///
+/// On the server, the snapshot is empty.
+#[cfg(feature = "client")]
+pub fn export_sse_state() -> SseStateSnapshot {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            export::export()
+        } else {
+            SseStateSnapshot::default()
+        }
+    }
+}
+
+/// Restores the documents of a snapshot taken with [`export_sse_state`].
+///
+/// Signals which haven't been created yet get their document once they are. A signal which has
+/// already received an update from the current connection keeps its value, since it is newer
+/// than the snapshot. Like with a fresh connection, the first update of a new stream is applied
+/// to the default value rather than the imported document.
+#[cfg(feature = "client")]
+#[allow(unused_variables)]
+pub fn import_sse_state(snapshot: SseStateSnapshot) {
+    #[cfg(target_arch = "wasm32")]
+    export::import(snapshot);
+}
+
+/// Starts recording the updates received by the client, see [`SseRecording`].
+///
+/// The recording starts with the current documents of all signals, and any recording already
+/// in progress is discarded.
+#[cfg(feature = "client")]
+pub fn start_sse_recording() {
+    #[cfg(target_arch = "wasm32")]
+    recording::start();
+}
+
+/// Stops the recording started with [`start_sse_recording`], and returns it.
+///
+/// Returns an empty recording if none is in progress, and on the server.
+#[cfg(feature = "client")]
+pub fn stop_sse_recording() -> SseRecording {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            recording::stop()
+        } else {
+            SseRecording::default()
+        }
+    }
+}
+
+/// Replays a recording into the signals, as if its updates were arriving from a server.
+///
+/// The signals are set to the documents the recording started with right away, then every
+/// update is applied at the time it was received, divided by `speed`: `2.0` replays twice as
+/// fast. This doesn't need a connection, so [`provide_sse`] doesn't have to be called, for
+/// example in a component playground. Updates from a connection which is open at the same time
+/// are applied too, interleaved with the replayed ones.
+///
+/// # Panics
+///
+/// Panics if `speed` isn't positive.
+#[cfg(feature = "client")]
+#[allow(unused_variables)]
+pub fn replay_sse_recording(recording: SseRecording, speed: f64) {
+    assert!(speed > 0.0, "replay speed must be positive");
+    #[cfg(target_arch = "wasm32")]
+    recording::replay(recording, speed);
+}
+
+/// Injects faults into the updates received by the client, or stops injecting them with
+/// `None`, see [`SseChaos`].
+///
+/// This is only meant for development, and needs the `chaos` feature.
+#[cfg(all(feature = "client", feature = "chaos"))]
+#[allow(unused_variables)]
+pub fn set_sse_chaos(chaos: Option<SseChaos>) {
+    #[cfg(target_arch = "wasm32")]
+    chaos::set(chaos);
+}
+
+/// Declares how long the data of the signal `name` stays fresh without updates, see [`SseTtl`].
+///
+/// The timer starts right away, so a signal which doesn't receive its first update in time is
+/// stale too.
+#[cfg(feature = "client")]
+#[allow(unused_variables)]
+pub fn set_sse_ttl(name: impl Into<Cow<'static, str>>, ttl: SseTtl) {
+    #[cfg(target_arch = "wasm32")]
+    ttl::set(name.into(), ttl);
+}
+
+/// Lists the documents the client holds for the signals, sorted by name, see
+/// [`SseDocumentInfo`].
+///
+/// The documents outlive their signals unless [`SseOptions::retain_documents`] is disabled, so
+/// this helps tracking down the memory of apps creating signals for many names. On the server,
+/// no document is held.
+#[cfg(feature = "client")]
+pub fn sse_documents() -> Vec<SseDocumentInfo> {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            documents::list()
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+/// Drops the document of the signal `name`, returning `false` if there is none, or if a signal
+/// still uses it.
+///
+/// The updates to `name` are dropped until a signal is created for it again, which starts from
+/// its default value, or from the snapshot fetched with [`SseOptions::prefetch`].
+#[cfg(feature = "client")]
+#[allow(unused_variables)]
+pub fn remove_sse_document(name: &str) -> bool {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            documents::remove(name)
+        } else {
+            false
+        }
+    }
+}
+
+/// Returns a signal which is `true` while the data of the signal `name` is stale.
+///
+/// A signal only becomes stale once a time to live has been set with [`set_sse_ttl`]. It is
+/// fresh again as soon as the next update arrives. On the server, the data is never stale.
+#[cfg(feature = "client")]
+#[allow(unused_variables)]
+pub fn use_sse_stale(name: impl Into<Cow<'static, str>>) -> ArcReadSignal<bool> {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            ttl::stale(name.into())
+        } else {
+            ArcRwSignal::new(false).read_only()
+        }
+    }
+}
+
+/// Returns a signal counting the updates applied to the signal `name`.
+///
+/// The version starts at 0, and is incremented after every update, even one which leaves the
+/// value unchanged. This makes it a cheap way to tell whether an update arrived, or a key for
+/// caching data derived from the signal. Documents replaced as a whole, such as by a prefetched
+/// snapshot, don't count as updates. On the server, the version stays 0.
+///
+/// ```
+/// use leptos::prelude::*;
+/// use leptos_sse::use_sse_version;
+///
+/// #[component]
+/// pub fn Debug() -> impl IntoView {
+///     let version = use_sse_version("counter");
+///     view! { <small>"counter updates: " {move || version.get()}</small> }
+/// }
+/// ```
+#[cfg(feature = "client")]
+#[allow(unused_variables)]
+pub fn use_sse_version(name: impl Into<Cow<'static, str>>) -> ArcReadSignal<u64> {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            version::version(name.into())
+        } else {
+            ArcRwSignal::new(0).read_only()
+        }
+    }
+}
+
+/// Returns a signal with the document of the signal `name`, as untyped json.
+///
+/// This gives generic tools, such as admin pages, json viewers or debugging panels, reactive
+/// access to a server signal without knowing its type:
+///
+/// ```
+/// use leptos::prelude::*;
+/// use leptos_sse::use_sse_raw;
+///
+/// #[component]
+/// pub fn Inspector(name: &'static str) -> impl IntoView {
+///     let doc = use_sse_raw(name);
+///     view! { <pre>{move || serde_json::to_string_pretty(&doc.get()).unwrap()}</pre> }
+/// }
+/// ```
+///
+/// The updates are diffs from the default value of the type, so the document is only known
+/// once a signal of the type has been created for `name`, and is `Value::Null` until then. Like
+/// [`on_sse_update`], the signal follows the updates as they are applied.
+#[cfg(feature = "client")]
+#[allow(unused_variables)]
+pub fn use_sse_raw(name: impl Into<Cow<'static, str>>) -> ReadSignal<Value> {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            let name = name.into();
+            let (get, set) = new_signal(current_document(&name).unwrap_or_default());
+            let id = observers::add(
+                name.clone(),
+                std::rc::Rc::new(move |_: &str, _: &Patch, doc: &Value| set.set(doc.clone())),
+            );
+            on_cleanup(move || observers::remove(&name, id));
+            get
+        } else {
+            new_signal(Value::Null).0
+        }
+    }
+}
+
+/// Fetches the signals the server publishes, as listed at `url`.
+///
+/// The server lists them with the discovery route of its `SseRegistry`, see
+/// `SseRegistry::discovery`. This lets generic dashboards render whatever the server
+/// publishes, for example with dynamic signals of untyped json, which the server streams with
+/// `SseRegistry::dynamic_events`:
+///
+/// ```
+/// use leptos::prelude::*;
+/// use leptos_sse::{create_sse_dynamic_signal, discover_sse_signals};
+/// use serde_json::Value;
+///
+/// #[component]
+/// pub fn Dashboard() -> impl IntoView {
+///     let signals = LocalResource::new(|| discover_sse_signals("/sse/discovery"));
+///     view! {
+///         <Suspense>
+///             {move || signals.get().and_then(Result::ok).map(|signals| {
+///                 signals.into_iter().map(|signal| {
+///                     let doc = create_sse_dynamic_signal::<Value>(signal.name.clone());
+///                     view! { <p>{signal.name} ": " {move || doc.get().to_string()}</p> }
+///                 }).collect_view()
+///             })}
+///         </Suspense>
+///     }
+/// }
+/// ```
+///
+/// Requests send credentials if the SSE connection does. On the server, no signal is listed.
+#[cfg(feature = "client")]
+#[allow(unused_variables)]
+pub async fn discover_sse_signals(url: &str) -> Result<Vec<SignalDescriptor>, JsValue> {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            discovery::client::fetch(url).await
+        } else {
+            Ok(Vec::new())
+        }
+    }
+}
+
+/// Calls `callback` whenever a chunk of an oversized message arrives.
+///
+/// The callback receives the name of the signal, the number of chunks received so far, and the
+/// total number of chunks. This makes it possible to show progress while the initial document of
+/// a large signal is loading, see `ServerSentEvents::chunked`.
+#[cfg(feature = "client")]
+#[allow(unused_variables)]
+pub fn on_sse_chunk_progress(callback: impl Fn(&str, usize, usize) + 'static) {
+    #[cfg(target_arch = "wasm32")]
+    chunk::set_progress_callback(callback);
+}
+
+/// Calls `observer` after every update applied to the signal `name`.
+///
+/// The observer receives the name of the signal, the json patch which was applied, and the
+/// resulting document. This is useful for client-side logging, analytics, or computations
+/// derived from several signals, without reading the signals in an `Effect`. The observer is
+/// removed when the current reactive owner is cleaned up.
+#[cfg(feature = "client")]
+#[allow(unused_variables)]
+pub fn on_sse_update(
+    name: impl Into<Cow<'static, str>>,
+    observer: impl Fn(&str, &Patch, &Value) + 'static,
+) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let name = name.into();
+        let id = observers::add(name.clone(), std::rc::Rc::new(observer));
+        on_cleanup(move || observers::remove(&name, id));
+    }
+}
+
+/// Creates a signal with a value computed from a part of a server signal, which is only
+/// recomputed when an update touches that part.
+///
+/// `pointer` is a json pointer into the document of type `D`, such as `/rows/42`, and `map`
+/// receives the value there, or `T::default()` if there is none. Updates which don't touch the
+/// pointer are skipped without decoding anything, so rendering one row of a large server-driven
+/// table doesn't decode the whole table on every update:
+///
+/// ```
+/// use leptos::prelude::*;
+/// use leptos_sse::create_sse_selector;
+/// # #[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+/// # pub struct Table { rows: Vec<Row> }
+/// # #[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+/// # pub struct Row { name: String }
+///
+/// #[component]
+/// pub fn RowName(index: usize) -> impl IntoView {
+///     let name = create_sse_selector::<Table, _, _>(
+///         "table",
+///         &format!("/rows/{index}"),
+///         |row: Row| row.name,
+///     );
+///     view! { <td>{name}</td> }
+/// }
+/// ```
+///
+/// Selectors share the document of the signal `name` with the signals created for it.
+///
+/// # Panics
+///
+/// Panics if `pointer` isn't a valid json pointer.
+#[cfg(feature = "client")]
+#[allow(unused_variables)]
+pub fn create_sse_selector<D, T, U>(
+    name: impl Into<Cow<'static, str>>,
+    pointer: &str,
+    map: impl Fn(T) -> U + 'static,
+) -> ReadSignal<U>
+where
+    D: Default + Serialize,
+    T: Default + for<'de> Deserialize<'de> + 'static,
+    U: Send + Sync + 'static,
+{
+    let pointer = json_patch::jsonptr::PointerBuf::parse(pointer).expect("invalid json pointer");
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            let (get, set) = new_signal(map(T::default()));
+            let default = || serde_json::to_value(D::default()).unwrap();
+            selector::setup(name.into(), pointer, default, set, map);
+            get
+        } else {
+            new_signal(map(T::default())).0
+        }
+    }
+}
+
+/// Creates a signal with the last `capacity` values of an array server signal, kept in a ring
+/// buffer.
+///
+/// Values added at the tail of the array are decoded and appended to the buffer in place,
+/// dropping the oldest ones, so a live chart doesn't re-clone and re-decode a growing `Vec` on
+/// every update:
+///
+/// ```
+/// use leptos::prelude::*;
+/// use leptos_sse::create_sse_series;
+/// # #[derive(Clone, serde::Serialize, serde::Deserialize)]
+/// # pub struct Point { x: f64, y: f64 }
+///
+/// #[component]
+/// pub fn Chart() -> impl IntoView {
+///     let points = create_sse_series::<Point>("metrics", 120);
+///     view! { <p>{move || points.with(|points| points.len())} " points"</p> }
+/// }
+/// ```
+///
+/// The server should only append to the array, or trim it from the front. Any other update,
+/// such as replacing values, copies the last values of the document into the buffer again. The
+/// series shares the document of the signal `name` with the signals created for it.
+#[cfg(feature = "client")]
+#[allow(unused_variables)]
+pub fn create_sse_series<T>(
+    name: impl Into<Cow<'static, str>>,
+    capacity: usize,
+) -> ReadSignal<std::collections::VecDeque<T>>
+where
+    T: for<'de> Deserialize<'de> + Send + Sync + 'static,
+{
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            let (get, set) = new_signal(std::collections::VecDeque::with_capacity(capacity));
+            series::setup(name.into(), capacity, set);
+            get
+        } else {
+            new_signal(std::collections::VecDeque::new()).0
+        }
+    }
+}
+
+/// Returns a signal with the diagnostics of the messages which could not be read.
+///
+/// Such messages are otherwise only logged, so this makes protocol mismatches between the
+/// client and the server discoverable, for example in a debug overlay. The diagnostics are
+/// global to the page, and stay at their defaults when rendering on the server.
+#[cfg(feature = "client")]
+pub fn use_sse_diagnostics() -> ArcReadSignal<SseDiagnostics> {
+    diagnostics::signal()
+}
+
+/// Returns a signal with the state of the SSE connection.
+///
+/// This makes it possible to show that realtime updates are unavailable once the connection
+/// has [`Failed`](SseConnectionState::Failed). The state is global to the page.
+#[cfg(feature = "client")]
+pub fn use_sse_connection_state() -> ArcReadSignal<SseConnectionState> {
+    state::signal()
+}
+
+/// Returns a signal with the last reconnection of the SSE connection, if any.
+///
+/// The signal is set when an attempt to re-establish the connection is scheduled, with its
+/// number, delay and cause, and once the connection is open again. This makes it possible to
+/// tell users when the app will try again:
+///
+/// ```
+/// use leptos::prelude::*;
+/// use leptos_sse::{use_sse_reconnect_event, SseReconnectEvent};
+///
+/// #[component]
+/// pub fn ConnectionBanner() -> impl IntoView {
+///     let event = use_sse_reconnect_event();
+///     move || match event.get() {
+///         Some(SseReconnectEvent::Retrying { delay: Some(delay), .. }) => {
+///             format!("Lost connection, retrying in {}s", delay.as_secs())
+///         }
+///         Some(SseReconnectEvent::Retrying { .. }) => "Lost connection, retrying".to_string(),
+///         _ => String::new(),
+///     }
+/// }
+/// ```
+///
+/// The events are global to the page.
+#[cfg(feature = "client")]
+pub fn use_sse_reconnect_event() -> ArcReadSignal<Option<SseReconnectEvent>> {
+    state::reconnect_signal()
+}
+
+/// Returns a signal with the estimated offset of the server clock from the client clock, in
+/// milliseconds.
+///
+/// The offset is estimated from the server time carried by `heartbeat` events, see
+/// `Heartbeat`, and is `None` until one arrived, or when rendering on the server. Add it to
+/// the client time to get the server time, see [`sse_server_now`], so that labels based on
+/// server timestamps stay accurate even when the client clock is not.
+#[cfg(feature = "client")]
+pub fn use_sse_clock_offset() -> ArcReadSignal<Option<f64>> {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            clock::signal()
+        } else {
+            ArcRwSignal::new(None).read_only()
+        }
+    }
+}
+
+/// Returns the estimated server time, in milliseconds since the Unix epoch.
+///
+/// This is the client time corrected by [`use_sse_clock_offset`], or the client time until the
+/// offset is known. It isn't reactive, so a label should be refreshed with an interval:
+///
+/// ```
+/// use leptos::prelude::*;
+/// use leptos_sse::{create_sse_signal, sse_server_now};
+///
+/// #[component]
+/// pub fn UpdatedAgo() -> impl IntoView {
+///     // Set by the server in milliseconds since the Unix epoch
+///     let updated_at = create_sse_signal::<f64>("updated_at");
+///     let (now, set_now) = signal(sse_server_now());
+///     set_interval(move || set_now.set(sse_server_now()), std::time::Duration::from_secs(1));
+///     move || format!("updated {:.0}s ago", (now.get() - updated_at.get()) / 1000.0)
+/// }
+/// ```
+#[cfg(feature = "client")]
+pub fn sse_server_now() -> f64 {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            js_sys::Date::now() + clock::offset().unwrap_or_default()
+        } else {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0.0, |now| now.as_secs_f64() * 1000.0)
+        }
+    }
+}
+
+/// Returns a stream of every update received from the SSE connection.
+///
+/// This is useful to drive consumers which aren't signals, such as charts doing their own
+/// buffering, without going through an `Effect`. Updates are yielded as they are received,
+/// whether or not a signal with their name has been created.
+///
+/// The stream is empty when not running in the browser.
+#[cfg(feature = "client")]
+pub fn use_sse_updates() -> impl Stream<Item = ServerSignalUpdate> {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            stream::subscribe()
+        } else {
+            futures::stream::empty()
+        }
+    }
+}
+
+/// Returns a stream of the values of a server signal.
+///
+/// The stream keeps its own document, initialized as `T::default()`, and yields the new value
+/// after every update for `name`. It does not require a signal with the same name to exist.
+///
+/// # Example
+///
+/// ```
+/// use futures::StreamExt;
+/// use leptos::prelude::*;
+/// use leptos_sse::use_sse_stream;
+/// # #[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+/// # pub struct Count {
+/// #     pub value: i32,
+/// # }
+///
+/// #[component]
+/// pub fn CountLogger() -> impl IntoView {
+///     let mut counts = use_sse_stream::<Count>("counter");
+///     leptos::task::spawn_local(async move {
+///         while let Some(count) = counts.next().await {
+///             leptos::logging::log!("count is now {}", count.value);
+///         }
+///     });
+/// }
+/// ```
+#[cfg(feature = "client")]
+pub fn use_sse_stream<T>(name: impl Into<Cow<'static, str>>) -> impl Stream<Item = T>
+where
+    T: Default + Serialize + for<'de> Deserialize<'de> + 'static,
+{
+    let name = name.into();
+    let default = serde_json::to_value(T::default()).unwrap();
+    let mut doc = default.clone();
+    use_sse_updates()
+        .filter(move |update| future::ready(update.name == name))
+        .filter_map(move |update| {
+            if update.seq == Some(1) {
+                doc = default.clone();
             }
-            
-            // Store the EventSource
-            EVENT_SOURCE.with(|source| {
-                *source.borrow_mut() = Some(es);
-            });
-            
-            // Set up the message handler
-            EVENT_SOURCE.with(|source| {
-                if let Some(es) = source.borrow().as_ref() {
-                    let callback = Closure::wrap(Box::new(move |event: MessageEvent| {
-                        leptos::logging::log!("SSE message received");
-                        let ws_string = event.data().dyn_into::<JsString>().unwrap().as_string().unwrap();
-                        leptos::logging::log!("SSE data: {}", &ws_string);
-                        if let Ok(update_signal) = serde_json::from_str::<ServerSignalUpdate>(&ws_string) {
-                            let name = &update_signal.name;
-                            
-                            // Try sync signals first
-                            let handled = STATE_SIGNALS.with(|signals| {
-                                let handler_map = signals.borrow();
-                                if let Some(signal) = handler_map.get(name) {
-                                    // Apply any delayed patches first
-                                    DELAYED_UPDATES.with(|delayed| {
-                                        let mut delayed_map = delayed.borrow_mut();
-                                        if let Some(delayed_patches) = delayed_map.remove(name) {
-                                            signal.update(|doc| {
-                                                for patch in delayed_patches {
-                                                    json_patch::patch(doc, &patch).unwrap();
-                                                }
-                                            });
-                                        }
-                                    });
-                                    
-                                    // Apply the current patch
-                                    signal.update(|doc| {
-                                        json_patch::patch(doc, &update_signal.patch).unwrap();
-                                    });
-                                    true
-                                } else {
-                                    false
-                                }
-                            });
-                            
-                            // If not found in sync signals, try local signals
-                            if !handled {
-                                let handled_local = STATE_SIGNALS_LOCAL.with(|signals| {
-                                    let handler_map = signals.borrow();
-                                    if let Some(signal) = handler_map.get(name) {
-                                        // Apply any delayed patches first
-                                        DELAYED_UPDATES.with(|delayed| {
-                                            let mut delayed_map = delayed.borrow_mut();
-                                            if let Some(delayed_patches) = delayed_map.remove(name) {
-                                                signal.update(|doc| {
-                                                    for patch in delayed_patches {
-                                                        json_patch::patch(doc, &patch).unwrap();
-                                                    }
-                                                });
-                                            }
-                                        });
-                                        
-                                        // Apply the current patch
-                                        signal.update(|doc| {
-                                            json_patch::patch(doc, &update_signal.patch).unwrap();
-                                        });
-                                        true
-                                    } else {
-                                        false
-                                    }
-                                });
-                                
-                                if !handled_local {
-                                    leptos::logging::warn!("No local state for update to {}. Queuing patch.", name);
-                                    DELAYED_UPDATES.with(|delayed| {
-                                        let mut delayed_map = delayed.borrow_mut();
-                                        delayed_map.entry(name.clone()).or_default().push(update_signal.patch.clone());
-                                    });
-                                }
-                            }
+            let value = match json_patch::patch(&mut doc, &update.patch) {
+                Ok(()) => float::from_value_lenient(doc.clone()).ok(),
+                Err(err) => {
+                    leptos::logging::error!("failed to patch {}: {}", update.name, err);
+                    None
+                }
+            };
+            future::ready(value)
+        })
+}
+
+/// Creates a signal which is controlled by the server, named after its type.
+///
+/// This is the same as [`create_sse_signal`], using [`SignalName::signal_name`] as the name.
+#[cfg(feature = "client")]
+pub fn create_sse_signal_by_type<T>() -> ReadSignal<T>
+where
+    T: SignalName + Default + Serialize + for<'de> Deserialize<'de> + Send + Sync + 'static,
+{
+    create_sse_signal(T::signal_name())
+}
+
+/// Creates a reference-counted signal which is controlled by the server.
+///
+/// This is the same as [`create_sse_signal`], but returns an [`ArcReadSignal`] which isn't
+/// owned by the reactive ownership tree. It stays alive for as long as it is referenced, so it
+/// can be moved into spawned tasks or global stores.
+///
+/// Updates are pushed to the signal directly, without an `Effect`.
+#[cfg(feature = "client")]
+#[allow(unused_variables)]
+pub fn create_sse_arc_signal<T>(name: impl Into<Cow<'static, str>>) -> ArcReadSignal<T>
+where
+    T: Default + Serialize + for<'de> Deserialize<'de> + Send + Sync + 'static,
+{
+    let name = name.into();
+    let (get, set) = new_arc_signal(T::default());
+
+    #[cfg(target_arch = "wasm32")]
+    setup_sse_signal_arc(name, set);
+
+    get
+}
+
+/// Creates a signal which is controlled by the server, and cached in IndexedDB.
+///
+/// This is the same as [`create_sse_signal`], except that the last received document is
+/// persisted in IndexedDB and restored when the signal is created, so large documents don't
+/// have to be shown as `T::default()` until the first update arrives.
+///
+/// The `version` is stored alongside the cached document, and a cache written with a different
+/// version is ignored. Bump it whenever the shape of `T` changes.
+///
+/// A cached document is only used until the server stream starts: the first update of every
+/// stream is applied to `T::default()` rather than to the cached document. Any other update was
+/// diffed from a document the cache may not have, so the cache is replaced with the snapshot of
+/// the signal instead, see [`SseOptions::prefetch`].
+#[cfg(feature = "client")]
+#[allow(unused_variables)]
+pub fn create_sse_signal_persisted<T>(
+    name: impl Into<Cow<'static, str>>,
+    version: u32,
+) -> ReadSignal<T>
+where
+    T: Default + Serialize + for<'de> Deserialize<'de> + Send + Sync + 'static,
+{
+    let name = name.into();
+    let signal = create_sse_signal::<T>(name.clone());
+
+    #[cfg(target_arch = "wasm32")]
+    persist::enable(name, version);
+
+    signal
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(all(target_arch = "wasm32", feature = "client"))] {
+        use std::collections::hash_map::{Entry, HashMap};
+        use std::collections::HashSet;
+        use std::rc::Rc;
+        use std::cell::{Cell, RefCell};
+        use std::sync::{Arc, Mutex};
+
+        use web_sys::{EventSource, MessageEvent};
+        use leptos::prelude::*;
+
+        // Thread-local storage for EventSource since it's not Send + Sync
+        thread_local! {
+            static EVENT_SOURCE: RefCell<Option<EventSource>> = RefCell::new(None);
+            /// The documents of the signals, which every update is routed through by name.
+            static DOCUMENTS: RefCell<HashMap<Cow<'static, str>, Document>> =
+                RefCell::new(HashMap::new());
+            /// The operations of the updates to signals which weren't created yet, as one patch.
+            static DELAYED_UPDATES: RefCell<HashMap<Cow<'static, str>, Patch>> =
+                RefCell::new(HashMap::new());
+            static SIGNAL_META: RefCell<HashMap<Cow<'static, str>, SignalMeta>> =
+                RefCell::new(HashMap::new());
+            /// The names whose documents were dropped, which updates are dropped for until a
+            /// signal is created for them again.
+            static FORGOTTEN: RefCell<HashSet<Cow<'static, str>>> = RefCell::new(HashSet::new());
+            static SSE_INITIALIZED: Cell<bool> = Cell::new(false);
+            static PENDING_REGISTRATIONS: RefCell<Vec<Box<dyn FnOnce()>>> =
+                RefCell::new(Vec::new());
+        }
+
+        /// The document of the signals of a name.
+        ///
+        /// The first signal created for a name decides how its document is stored. Signals of
+        /// the other kind created later follow it through an observer.
+        enum Document {
+            /// A document signal, which sync and local signals track with an `Effect`.
+            Signal(ArcRwSignal<Value>),
+            /// The document of arena-free signals.
+            Arc(ArcDocument),
+        }
+
+        impl Document {
+            /// Returns a copy of the document.
+            fn get(&self) -> Value {
+                match self {
+                    Document::Signal(signal) => signal.get_untracked(),
+                    Document::Arc(entry) => entry.doc.clone(),
+                }
+            }
+
+            /// Replaces the document.
+            fn set(&mut self, doc: Value) {
+                match self {
+                    Document::Signal(signal) => signal.set(doc),
+                    Document::Arc(entry) => {
+                        entry.doc = doc;
+                        entry.notify();
+                    }
+                }
+            }
+
+            /// Modifies the document in place, returning whether `update` succeeded.
+            ///
+            /// The signals are notified either way, as a failed update may have modified the
+            /// document before failing.
+            fn update(&mut self, update: impl FnOnce(&mut Value) -> bool) -> bool {
+                match self {
+                    Document::Signal(signal) => signal.try_update(update).unwrap_or(false),
+                    Document::Arc(entry) => {
+                        let updated = update(&mut entry.doc);
+                        entry.notify();
+                        updated
+                    }
+                }
+            }
+        }
+
+        /// The document of arena-free signals.
+        ///
+        /// It is patched in place, and pushed to the typed signals through `sets`, so that no
+        /// `Effect` (and therefore no reactive owner) is needed.
+        struct ArcDocument {
+            doc: Value,
+            sets: Vec<Box<dyn Fn(&Value)>>,
+        }
+
+        impl ArcDocument {
+            fn notify(&self) {
+                for set in &self.sets {
+                    set(&self.doc);
+                }
+            }
+        }
+
+        /// Pushes the document of `name` to `sets` after every update, for arena-free signals
+        /// of a name whose document is a signal.
+        fn follow_document(name: Cow<'static, str>, sets: Vec<Box<dyn Fn(&Value)>>) {
+            let observer = move |_: &str, _: &Patch, doc: &Value| {
+                for set in &sets {
+                    set(doc);
+                }
+            };
+            // Arena-free signals are never disposed, so neither is the observer
+            observers::add(name, Rc::new(observer));
+        }
+
+        /// Returns the document signal of `name`, and whether it was created by this call.
+        ///
+        /// The documents live outside of the reactive ownership tree, so that every signal
+        /// created for the same name, for example in different islands, shares one document
+        /// which outlives any of them.
+        fn document_signal(
+            name: &Cow<'static, str>,
+            default: impl FnOnce() -> Value,
+        ) -> (ArcRwSignal<Value>, bool) {
+            DOCUMENTS.with(|documents| {
+                let mut documents = documents.borrow_mut();
+                match documents.get_mut(name) {
+                    Some(Document::Signal(signal)) => (signal.clone(), false),
+                    Some(document) => {
+                        let signal = ArcRwSignal::new(document.get());
+                        let previous = std::mem::replace(document, Document::Signal(signal.clone()));
+                        if let Document::Arc(entry) = previous {
+                            follow_document(name.clone(), entry.sets);
                         }
-                    }) as Box<dyn FnMut(_)>);
-                    
-                    let function: &Function = callback.as_ref().unchecked_ref();
-                    es.set_onmessage(Some(function));
-
-                    // Keep the closure alive for the lifetime of the program
-                    callback.forget();
-                    
-                    leptos::logging::log!("SSE message handler installed");
+                        (signal, false)
+                    }
+                    None => {
+                        let signal = ArcRwSignal::new(default());
+                        documents.insert(name.clone(), Document::Signal(signal.clone()));
+                        (signal, true)
+                    }
+                }
+            })
+        }
+
+        /// Bookkeeping shared by every kind of signal.
+        struct SignalMeta {
+            /// The document of `T::default()`, which the first update of a stream is diffed from.
+            default: Value,
+            /// The sequence number of the last applied update.
+            seq: Option<u64>,
+            /// Whether the next update starts a new stream, whatever its sequence number.
+            rebase_pending: bool,
+            /// The number of signals using the document which haven't been disposed.
+            signals: usize,
+            /// Checks that a document deserializes into the type of the signal, see
+            /// [`SseOptions::strict`].
+            validate: Option<Validator>,
+        }
+
+        /// Deserializes a document into the type of a signal, dropping the value.
+        type Validator = fn(&Value) -> Result<(), serde_json::Error>;
+
+        fn register_meta(name: &Cow<'static, str>, default: Value) {
+            SIGNAL_META.with(|meta| {
+                meta.borrow_mut().insert(
+                    name.clone(),
+                    SignalMeta { default, seq: None, rebase_pending: false, signals: 0, validate: None },
+                );
+            });
+        }
+
+        /// Sets how the documents of the signal `name` are checked in strict mode, unless a
+        /// signal created earlier for the same name already did.
+        fn register_validator(name: &str, validate: Validator) {
+            SIGNAL_META.with(|meta| {
+                if let Some(meta) = meta.borrow_mut().get_mut(name) {
+                    meta.validate.get_or_insert(validate);
+                }
+            });
+        }
+
+        fn validate_codec<T, C>(doc: &Value) -> Result<(), serde_json::Error>
+        where
+            C: SignalCodec<T>,
+        {
+            C::decode(doc.clone()).map(drop)
+        }
+
+        fn validate_lenient<T>(doc: &Value) -> Result<(), serde_json::Error>
+        where
+            T: for<'de> Deserialize<'de>,
+        {
+            float::from_value_lenient::<T>(doc.clone()).map(drop)
+        }
+
+        /// Resets `doc` to the default document when `seq` starts a new stream.
+        ///
+        /// The first update of every stream is a diff from `T::default()`, so applying it to a
+        /// document restored from a cache or left over from a previous connection would
+        /// produce garbage. Returns `false` if `seq` doesn't follow the last update applied to
+        /// the signal, as `doc` then isn't the document the update was diffed from, see
+        /// [`resync`].
+        fn rebase_document(name: &str, seq: Option<u64>, doc: &mut Value) -> bool {
+            SIGNAL_META.with(|meta| {
+                let mut meta = meta.borrow_mut();
+                let Some(meta) = meta.get_mut(name) else {
+                    return true;
+                };
+                if seq == Some(1) || meta.rebase_pending {
+                    *doc = meta.default.clone();
+                    meta.rebase_pending = false;
+                } else if let (Some(seq), Some(last)) = (seq, meta.seq) {
+                    if last + 1 != seq {
+                        leptos::logging::warn!("Update {} for {} does not follow {}", seq, name, last);
+                        return false;
+                    }
+                }
+                if seq.is_some() {
+                    meta.seq = seq;
+                }
+                true
+            })
+        }
+
+        /// Records an update which was dropped rather than applied, so that the next one isn't
+        /// taken for a gap in the stream.
+        fn skip_update(name: &str, seq: Option<u64>) {
+            SIGNAL_META.with(|meta| {
+                if let (Some(meta), Some(_)) = (meta.borrow_mut().get_mut(name), seq) {
+                    meta.seq = seq;
+                }
+            });
+        }
+
+        /// Returns `true` if `seq` doesn't come after the last update applied to the signal.
+        ///
+        /// Such updates were replayed, or delivered twice, and must not be applied again.
+        fn is_replayed(name: &str, seq: Option<u64>) -> bool {
+            SIGNAL_META.with(|meta| match (meta.borrow().get(name), seq) {
+                (Some(meta), Some(seq)) => {
+                    !meta.rebase_pending && meta.seq.is_some_and(|last| seq <= last)
+                }
+                _ => false,
+            })
+        }
+
+        /// Marks every document as belonging to a previous stream.
+        ///
+        /// The next update of each signal is applied to its default document, and updates
+        /// queued for signals which don't exist yet are dropped.
+        fn rebase_all_documents() {
+            SIGNAL_META.with(|meta| {
+                for meta in meta.borrow_mut().values_mut() {
+                    meta.rebase_pending = true;
+                    meta.seq = None;
+                }
+            });
+            DELAYED_UPDATES.with(|delayed| delayed.borrow_mut().clear());
+        }
+
+        /// Runs after an update has been applied to a registered signal.
+        fn on_applied(name: &str, patch: &Patch) {
+            persist::schedule_store(name);
+            ack::beacon::schedule();
+            dom_events::dispatch(name, patch);
+            observers::notify(name, patch);
+            ttl::touch(name);
+            version::bump(name);
+        }
+
+        /// Returns the current document of a registered signal.
+        fn current_document(name: &str) -> Option<Value> {
+            DOCUMENTS.with(|documents| documents.borrow().get(name).map(Document::get))
+        }
+
+        /// Drops the document of the signal `name` once its last dynamic signal is disposed, so
+        /// that subscribing to it again starts over, and the documents of short-lived names
+        /// don't pile up.
+        fn forget_document(name: &str) {
+            DOCUMENTS.with(|documents| documents.borrow_mut().remove(name));
+            SIGNAL_META.with(|meta| meta.borrow_mut().remove(name));
+            DELAYED_UPDATES.with(|delayed| delayed.borrow_mut().remove(name));
+            FORGOTTEN.with(|forgotten| forgotten.borrow_mut().insert(Cow::Owned(name.to_string())));
+        }
+
+        /// Returns `true` if the document of `name` was dropped, see [`forget_document`].
+        fn is_forgotten(name: &str) -> bool {
+            FORGOTTEN.with(|forgotten| forgotten.borrow().contains(name))
+        }
+
+        /// Replaces the document of a registered signal.
+        fn set_document(name: &str, doc: Value) {
+            DOCUMENTS.with(|documents| {
+                if let Some(document) = documents.borrow_mut().get_mut(name) {
+                    document.set(doc);
+                }
+            });
+        }
+
+        /// Applies patches to the document of the signal `name`, returning `false` if one
+        /// fails.
+        ///
+        /// A patch which doesn't apply means that the document is out of step with the server.
+        /// Panicking would break the handler shared by every signal, so the failure is reported
+        /// in the diagnostics instead, the following patches are skipped, and the document is
+        /// replaced with its snapshot if prefetching is enabled. A failed patch leaves the
+        /// document unchanged.
+        ///
+        /// In strict mode, the patches are applied to a copy of the document, which only
+        /// replaces it if it still deserializes into the type of the signal.
+        fn apply_patches<'a>(
+            name: &str,
+            doc: &mut Value,
+            patches: impl IntoIterator<Item = &'a Patch>,
+        ) -> bool {
+            let validate = connection::strict()
+                .then(|| SIGNAL_META.with(|meta| meta.borrow().get(name).and_then(|meta| meta.validate)))
+                .flatten();
+            let Some(validate) = validate else {
+                return patch_document(name, doc, patches);
+            };
+            let mut patched = doc.clone();
+            if !patch_document(name, &mut patched, patches) {
+                return false;
+            }
+            if let Err(err) = validate(&patched) {
+                diagnostics::record_rejected_document(name, &err);
+                resync(name);
+                return false;
+            }
+            *doc = patched;
+            true
+        }
+
+        /// Applies patches to a document, stopping at the first which fails, see
+        /// [`apply_patches`].
+        fn patch_document<'a>(
+            name: &str,
+            doc: &mut Value,
+            patches: impl IntoIterator<Item = &'a Patch>,
+        ) -> bool {
+            for patch in patches {
+                if let Err(err) = json_patch::patch(doc, patch) {
+                    diagnostics::record_failed_patch(name, &err);
+                    resync(name);
+                    return false;
+                }
+            }
+            true
+        }
+
+        /// Replaces the document of a signal which is out of step with the server with its
+        /// snapshot, if prefetching is enabled.
+        fn resync(name: &str) {
+            if !prefetch::replace(name) {
+                leptos::logging::error!("{} is out of step with the server until it reconnects", name);
+            }
+        }
+
+        /// Queues the patch of an update to a signal which wasn't created yet.
+        ///
+        /// The operations are moved into a single patch per signal, applied at once when the
+        /// signal is created.
+        fn delay_patch(name: Cow<'static, str>, patch: Patch) {
+            DELAYED_UPDATES.with(|delayed| match delayed.borrow_mut().entry(name) {
+                Entry::Occupied(mut queued) => queued.get_mut().0.extend(patch.0),
+                Entry::Vacant(queued) => {
+                    queued.insert(patch);
                 }
             });
-            
+        }
+
+        /// Takes the patch queued for the signal `name`, if any.
+        fn take_delayed(name: &str) -> Option<Patch> {
+            DELAYED_UPDATES.with(|delayed| delayed.borrow_mut().remove(name))
+        }
+
+        /// Returns `true` once `provide_sse` or `provide_sse_worker` has run.
+        fn sse_initialized() -> bool {
+            SSE_INITIALIZED.with(Cell::get)
+        }
+
+        /// Marks the SSE as initialized, completing the registration of the signals created
+        /// before.
+        fn mark_initialized() {
+            SSE_INITIALIZED.with(|initialized| initialized.set(true));
+            let pending = PENDING_REGISTRATIONS.with(|pending| pending.take());
+            for register in pending {
+                register();
+            }
+        }
+
+        /// Runs `register` once the connection exists, which is right away unless the signal
+        /// was created before `provide_sse`.
+        fn when_initialized(register: impl FnOnce() + 'static) {
+            if sse_initialized() {
+                register();
+            } else {
+                PENDING_REGISTRATIONS.with(|pending| pending.borrow_mut().push(Box::new(register)));
+            }
+        }
+
+        /// Registers a signal with the connection, see [`when_initialized`].
+        ///
+        /// Only the first signal created for a name registers its document.
+        fn register_with_connection(name: Cow<'static, str>, doc: Value, first: bool) {
+            if first {
+                FORGOTTEN.with(|forgotten| forgotten.borrow_mut().remove(&name));
+                register_meta(&name, doc);
+                export::restore(&name);
+            }
+            SIGNAL_META.with(|meta| {
+                if let Some(meta) = meta.borrow_mut().get_mut(&name) {
+                    meta.signals += 1;
+                }
+            });
+            when_initialized(move || {
+                if first {
+                    prefetch::snapshot(&name);
+                }
+                connection::signal_created();
+            });
+        }
+
+        /// Releases a signal of `name` once the reactive owner creating it is cleaned up.
+        ///
+        /// The document is dropped with the last signal using it, unless documents are
+        /// retained, see [`SseOptions::retain_documents`].
+        fn dispose_on_cleanup(name: Cow<'static, str>) {
+            on_cleanup(move || {
+                connection::signal_disposed();
+                let unused = SIGNAL_META.with(|meta| {
+                    meta.borrow_mut().get_mut(&name).is_some_and(|meta| {
+                        meta.signals = meta.signals.saturating_sub(1);
+                        meta.signals == 0
+                    })
+                });
+                if unused && !connection::retain_documents() {
+                    sse_log!("Dropping the document of {}", name);
+                    forget_document(&name);
+                }
+            });
+        }
+
+        fn setup_sse_signal<T, C>(name: Cow<'static, str>, set: WriteSignal<T>)
+        where
+            T: Default + Send + Sync + 'static,
+            C: SignalCodec<T>,
+        {
+            use leptos::prelude::*;
+
+            sse_log!("Setting up SSE signal: {}", name);
+            let (signal, first) =
+                document_signal(&name, || C::encode(&T::default()).unwrap());
+            register_with_connection(name.clone(), signal.get_untracked(), first);
+            register_validator(&name, validate_codec::<T, C>);
+            dispose_on_cleanup(name.clone());
+
+            compat::effect(move || match C::decode(signal.get()) {
+                Ok(new_value) => set.set(new_value),
+                Err(err) => leptos::logging::error!("failed to decode {}: {}", name, err),
+            });
+        }
+
+        fn setup_sse_signal_local<T>(name: Cow<'static, str>, set: WriteSignal<T, Local>)
+        where
+            T: Default + Serialize + for<'de> Deserialize<'de> + 'static,
+        {
+            use leptos::prelude::*;
+
+            let (signal, first) = document_signal(&name, || {
+                serde_json::to_value(T::default()).unwrap()
+            });
+            register_with_connection(name.clone(), signal.get_untracked(), first);
+            register_validator(&name, validate_lenient::<T>);
+            dispose_on_cleanup(name.clone());
+
+            compat::effect(move || match float::from_value_lenient(signal.get()) {
+                Ok(new_value) => set.set(new_value),
+                Err(err) => leptos::logging::error!("failed to decode {}: {}", name, err),
+            });
+        }
+
+        fn setup_sse_signal_arc<T>(name: Cow<'static, str>, set: ArcWriteSignal<T>)
+        where
+            T: Default + Serialize + for<'de> Deserialize<'de> + Send + Sync + 'static,
+        {
+            let signal_name = name.clone();
+            let set = Box::new(move |doc: &Value| match float::from_value_lenient(doc.clone()) {
+                Ok(new_value) => set.set(new_value),
+                Err(err) => leptos::logging::error!("failed to decode {}: {}", signal_name, err),
+            });
+            let (doc, first) = DOCUMENTS.with(|documents| {
+                let mut documents = documents.borrow_mut();
+                match documents.get_mut(&name) {
+                    Some(Document::Arc(entry)) => {
+                        // Catch up with the updates the other signals already received
+                        set(&entry.doc);
+                        entry.sets.push(set);
+                        (entry.doc.clone(), false)
+                    }
+                    Some(Document::Signal(signal)) => {
+                        let doc = signal.get_untracked();
+                        set(&doc);
+                        follow_document(name.clone(), vec![set]);
+                        (doc, false)
+                    }
+                    None => {
+                        let doc = serde_json::to_value(T::default()).unwrap();
+                        let entry = ArcDocument { doc: doc.clone(), sets: vec![set] };
+                        documents.insert(name.clone(), Document::Arc(entry));
+                        (doc, true)
+                    }
+                }
+            });
+            register_with_connection(name.clone(), doc, first);
+            register_validator(&name, validate_lenient::<T>);
+        }
+
+        #[inline]
+        fn provide_sse_inner(options: SseOptions) -> Result<(), JsValue> {
+            // Only initialize once
+            if sse_initialized() {
+                sse_log!("SSE already initialized");
+                return Ok(());
+            }
+
+            connection::set_options(options.clone());
+            #[cfg(feature = "tauri")]
+            if options.tauri {
+                tauri::listen()?;
+                mark_initialized();
+                return Ok(());
+            }
+
+            if options.lazy {
+                sse_log!("SSE connection to {} will be opened lazily", options.url);
+            } else {
+                sse_log!("Initializing SSE connection to: {}", options.url);
+                connection::connect()?;
+            }
+
             // Mark SSE as initialized AFTER setting up the handler
-            provide_context(SseInitialized);
+            mark_initialized();
 
             Ok(())
         }
 
+        /// Handles a message received from the SSE.
+        fn handle_message(event: MessageEvent) {
+            sse_log!("SSE message received");
+            message::receive(event.data());
+        }
+
+        /// Handles the data of a message, after reassembling it if it was sent in chunks.
+        fn handle_data(ws_string: String) {
+            #[cfg(feature = "chaos")]
+            let Some(ws_string) = chaos::inject(ws_string) else {
+                return;
+            };
+            decode_data(ws_string);
+        }
+
+        /// Decodes and verifies the data of a message, then applies the update.
+        fn decode_data(ws_string: String) {
+            if connection::max_message_size().is_some_and(|max| ws_string.len() > max) {
+                refuse_oversized(signal_name(&ws_string).as_deref(), ws_string.len());
+                return;
+            }
+            #[cfg(feature = "encryption")]
+            let ws_string = match connection::session_key() {
+                Some(key) => match key.open(&ws_string) {
+                    Ok(data) => data,
+                    Err(err) => {
+                        leptos::logging::error!("Rejecting SSE message: {}", err);
+                        diagnostics::record_malformed(&err, &ws_string);
+                        return;
+                    }
+                },
+                None => ws_string,
+            };
+            sse_log!("SSE data: {}", &ws_string);
+            let update_signal = match serde_json::from_str::<ServerSignalUpdate>(&ws_string) {
+                Ok(update_signal) => update_signal,
+                Err(err) => {
+                    diagnostics::record_malformed(&err, &ws_string);
+                    return;
+                }
+            };
+            let Some(update_signal) = intern::resolve(update_signal) else {
+                diagnostics::record_malformed(&"update to a signal id which was never named", &ws_string);
+                return;
+            };
+            #[cfg(feature = "signing")]
+            if let Some(key) = connection::signing_key() {
+                if !update_signal.verify(&key) {
+                    leptos::logging::error!("Rejecting update to {} with an invalid signature", update_signal.name);
+                    return;
+                }
+            }
+            handle_update(update_signal);
+        }
+
+        /// Reads the name of the signal at the start of a message, without parsing the rest.
+        fn signal_name(data: &str) -> Option<String> {
+            if let Some(rest) = data.strip_prefix(r#"{"id":"#) {
+                let id = serde_json::Deserializer::from_str(rest).into_iter::<u32>().next()?.ok()?;
+                return intern::name(id).map(str::to_string);
+            }
+            let rest = data.strip_prefix(r#"{"name":"#)?;
+            serde_json::Deserializer::from_str(rest).into_iter::<String>().next()?.ok()
+        }
+
+        /// Skips a message over the maximum size, replacing the document of its signal with a
+        /// snapshot instead.
+        fn refuse_oversized(name: Option<&str>, size: usize) {
+            match name {
+                Some(name) if prefetch::replace(name) => {
+                    leptos::logging::warn!("Refusing SSE update of {} bytes to {}, fetching its snapshot", size, name);
+                }
+                Some(name) => {
+                    leptos::logging::error!("Refusing SSE update of {} bytes to {}, which is now out of step", size, name);
+                }
+                None => leptos::logging::error!("Refusing SSE message of {} bytes", size),
+            }
+        }
+
+        /// Applies an update, once it has been decoded and verified.
+        fn handle_update(update_signal: ServerSignalUpdate) {
+            #[cfg(feature = "tracing")]
+            let _span = tracing::info_span!(
+                "sse_update",
+                signal = %update_signal.name,
+                seq = update_signal.seq,
+                traceparent = update_signal.traceparent.as_deref(),
+            )
+            .entered();
+            if is_replayed(&update_signal.name, update_signal.seq) {
+                leptos::logging::warn!("Ignoring replayed update {:?} for {}", update_signal.seq, update_signal.name);
+                return;
+            }
+            if prefetch::is_replacing(&update_signal.name) {
+                sse_log!("Dropping update to {} until its snapshot arrives", update_signal.name);
+                return;
+            }
+            recording::record(&update_signal);
+            let Some(update_signal) = schema::migrate(update_signal) else {
+                return;
+            };
+            let (name, seq) = (update_signal.name.clone(), update_signal.seq);
+            let Some(update_signal) = filter::apply(update_signal) else {
+                sse_log!("SSE update dropped by its filter");
+                skip_update(&name, seq);
+                return;
+            };
+            stream::publish(&update_signal);
+            if pause::hold(&update_signal) {
+                return;
+            }
+            let name = &update_signal.name;
+        
+            let applied = DOCUMENTS.with(|documents| {
+                let mut documents = documents.borrow_mut();
+                let document = documents.get_mut(name)?;
+                let delayed = take_delayed(name);
+                Some(document.update(|doc| {
+                    // Apply any delayed patches first
+                    if let Some(delayed) = &delayed {
+                        apply_patches(name, doc, [delayed]);
+                    }
+                    if !rebase_document(name, update_signal.seq, doc) {
+                        // The document is stale, the patch would corrupt it
+                        resync(name);
+                        return false;
+                    }
+                    apply_patches(name, doc, [&update_signal.patch])
+                }))
+            });
+
+            match applied {
+                Some(true) => on_applied(name, &update_signal.patch),
+                // Reported by `rebase_document` or `apply_patches`, the other signals carry on
+                Some(false) => {}
+                None if is_forgotten(name) => {
+                    sse_log!("The document of {} was dropped. Dropping patch.", name);
+                }
+                None => {
+                    if connection::unknown_signal_policy() == UnknownSignalPolicy::Drop {
+                        sse_log!("No local state for update to {}. Dropping patch.", name);
+                        return;
+                    }
+                    leptos::logging::warn!("No local state for update to {}. Queuing patch.", name);
+                    delay_patch(update_signal.name, update_signal.patch);
+                }
+            }
+        }
+
         /// Provides access to the underlying EventSource for advanced use cases
+        ///
+        /// Returns `None` in worker mode, where the worker owns the EventSource.
         pub fn with_event_source<F, R>(f: F) -> Option<R>
         where
             F: FnOnce(&EventSource) -> R,
@@ -361,9 +2279,9 @@ Ensure you call `leptos_sse::provide_sse("http://localhost:3000/sse")` at the hi
                 source.borrow().as_ref().map(|es| f(es))
             })
         }
-    } else {
+    } else if #[cfg(feature = "client")] {
         #[inline]
-        fn provide_sse_inner(_url: &str) -> Result<(), JsValue> {
+        fn provide_sse_inner(_options: SseOptions) -> Result<(), JsValue> {
             Ok(())
         }
     }
@@ -0,0 +1,215 @@
+use std::borrow::Cow;
+use std::marker::PhantomData;
+#[cfg(target_arch = "wasm32")]
+use std::{cell::Cell, rc::Rc};
+
+#[cfg(target_arch = "wasm32")]
+use json_patch::{Patch, PatchOperation};
+use leptos::prelude::*;
+use serde::Deserialize;
+#[cfg(target_arch = "wasm32")]
+use serde_json::Value;
+
+#[cfg(target_arch = "wasm32")]
+use crate::{dispose_on_cleanup, document_signal, float, observers, register_with_connection};
+
+/// The items of an [`SseFor`] list, with the keys they are rendered with.
+///
+/// Items which failed to decode have no signal, and aren't rendered, but keep their place so
+/// that the keys stay aligned with the indices of the document.
+type Items<T> = ArcRwSignal<Vec<(u64, Option<ArcRwSignal<T>>)>>;
+
+/// Renders a server signal holding a `Vec`, updating the rendered items from the patches.
+///
+/// Every item gets its own signal and key. Updates to an item only set its signal, and adding
+/// or removing items only renders or removes those items, as the library already knows from
+/// the patch which items changed, without diffing the whole vector in the view:
+///
+/// ```
+/// use leptos::prelude::*;
+/// use leptos_sse::SseFor;
+/// # #[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+/// # pub struct Todo { title: String }
+///
+/// #[component]
+/// pub fn Todos() -> impl IntoView {
+///     view! {
+///         <ul>
+///             <SseFor name="todos" let:todo>
+///                 <li>{move || todo.with(|todo: &Todo| todo.title.clone())}</li>
+///             </SseFor>
+///         </ul>
+///     }
+/// }
+/// ```
+///
+/// The list shares the document of the signal `name` with the signals created for it.
+#[component]
+pub fn SseFor<T, C, V>(
+    /// The name of the server signal, whose value is a `Vec<T>`.
+    #[prop(into)]
+    name: Cow<'static, str>,
+    /// Renders an item from a signal with its current value.
+    children: C,
+    #[prop(optional)] _marker: PhantomData<fn() -> (T, V)>,
+) -> impl IntoView
+where
+    T: for<'de> Deserialize<'de> + Send + Sync + 'static,
+    C: Fn(ArcReadSignal<T>) -> V + Send + Clone + 'static,
+    V: IntoView + 'static,
+{
+    let items: Items<T> = ArcRwSignal::new(Vec::new());
+    #[cfg(target_arch = "wasm32")]
+    track(name, items.clone());
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = name;
+
+    view! {
+        <For
+            each=move || {
+                items
+                    .get()
+                    .into_iter()
+                    .filter_map(|(key, item)| Some((key, item?)))
+                    .collect::<Vec<_>>()
+            }
+            key=|(key, _)| *key
+            children=move |(_, item)| children(item.read_only())
+        />
+    }
+}
+
+/// Keeps `items` in sync with the document of the signal `name`.
+#[cfg(target_arch = "wasm32")]
+fn track<T>(name: Cow<'static, str>, items: Items<T>)
+where
+    T: for<'de> Deserialize<'de> + Send + Sync + 'static,
+{
+    let (doc, first) = document_signal(&name, || Value::Array(Vec::new()));
+    register_with_connection(name.clone(), doc.get_untracked(), first);
+    dispose_on_cleanup(name.clone());
+
+    let list = List {
+        name: name.clone(),
+        items,
+        next_key: Cell::new(0),
+    };
+    list.reset(&doc.get_untracked());
+
+    let observer = move |_: &str, patch: &Patch, doc: &Value| list.apply(patch, doc);
+    let id = observers::add(name.clone(), Rc::new(observer));
+    on_cleanup(move || observers::remove(&name, id));
+}
+
+#[cfg(target_arch = "wasm32")]
+struct List<T> {
+    name: Cow<'static, str>,
+    items: Items<T>,
+    next_key: Cell<u64>,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl<T> List<T>
+where
+    T: for<'de> Deserialize<'de> + Send + Sync + 'static,
+{
+    /// Replays the structural changes of `patch` on the keys, then sets the items which were
+    /// added or changed from `doc`, the document after the patch.
+    fn apply(&self, patch: &Patch, doc: &Value) {
+        let Some(values) = doc.as_array() else {
+            return self.reset(doc);
+        };
+        let mut items: Vec<_> = self
+            .items
+            .get_untracked()
+            .into_iter()
+            .map(|(key, item)| (key, item, false))
+            .collect();
+        let mut structural = false;
+        for operation in &patch.0 {
+            let mut tokens = operation.path().tokens();
+            let Some(token) = tokens.next() else {
+                return self.reset(doc);
+            };
+            let nested = tokens.next().is_some();
+            let index = match token.decoded().as_ref() {
+                "-" => Some(items.len()),
+                index => index.parse::<usize>().ok(),
+            };
+            let Some(index) = index.filter(|index| *index <= items.len()) else {
+                return self.reset(doc);
+            };
+            match operation {
+                PatchOperation::Test(_) => {}
+                _ if nested => match items.get_mut(index) {
+                    Some((_, _, touched)) => *touched = true,
+                    None => return self.reset(doc),
+                },
+                PatchOperation::Add(_) => {
+                    items.insert(index, (self.new_key(), None, true));
+                    structural = true;
+                }
+                PatchOperation::Remove(_) if index < items.len() => {
+                    items.remove(index);
+                    structural = true;
+                }
+                PatchOperation::Replace(_) if index < items.len() => items[index].2 = true,
+                _ => return self.reset(doc),
+            }
+        }
+        if items.len() != values.len() {
+            return self.reset(doc);
+        }
+
+        let mut rendered = Vec::with_capacity(items.len());
+        for ((key, item, touched), value) in items.into_iter().zip(values) {
+            match item {
+                Some(item) => {
+                    if touched {
+                        if let Some(value) = self.decode(value) {
+                            item.set(value);
+                        }
+                    }
+                    rendered.push((key, Some(item)));
+                }
+                // Added, or failed to decode before
+                None => {
+                    let item = touched
+                        .then(|| self.decode(value))
+                        .flatten()
+                        .map(ArcRwSignal::new);
+                    structural |= item.is_some();
+                    rendered.push((key, item));
+                }
+            }
+        }
+        if structural {
+            self.items.set(rendered);
+        }
+    }
+
+    /// Renders every item again, with new keys.
+    fn reset(&self, doc: &Value) {
+        let values = doc.as_array().map(Vec::as_slice).unwrap_or_default();
+        let items = values
+            .iter()
+            .map(|value| (self.new_key(), self.decode(value).map(ArcRwSignal::new)))
+            .collect();
+        self.items.set(items);
+    }
+
+    fn new_key(&self) -> u64 {
+        self.next_key.set(self.next_key.get() + 1);
+        self.next_key.get()
+    }
+
+    fn decode(&self, value: &Value) -> Option<T> {
+        match float::from_value_lenient(value.clone()) {
+            Ok(item) => Some(item),
+            Err(err) => {
+                leptos::logging::error!("failed to decode an item of {}: {}", self.name, err);
+                None
+            }
+        }
+    }
+}
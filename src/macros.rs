@@ -0,0 +1,171 @@
+/// Defines all the server signals of an app in one place.
+///
+/// This generates a module containing:
+///
+/// - a function per signal returning its [`SseChannel`](crate::SseChannel), used with
+///   [`SseChannel::signal`](crate::SseChannel::signal) on the client,
+/// - a `Signal` enum with a variant per signal, for exhaustive matching on signal names,
+/// - a `SignalValue` enum holding the value of any of the signals,
+/// - on the server (with the `ssr` feature and an integration enabled), a `Senders` struct with
+///   the sender half of every signal, and a single stream merging all of them.
+///
+/// Types are resolved relative to the module the macro is invoked in.
+///
+/// # Example
+///
+/// ```
+/// use leptos::prelude::*;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Clone, Default, Serialize, Deserialize)]
+/// pub struct Count {
+///     pub value: i32,
+/// }
+///
+/// #[derive(Clone, Default, Serialize, Deserialize)]
+/// pub struct ChatState {
+///     pub messages: Vec<String>,
+/// }
+///
+/// leptos_sse::define_sse_signals! {
+///     pub mod signals {
+///         counter: Count,
+///         chat: ChatState,
+///     }
+/// }
+///
+/// #[component]
+/// pub fn App() -> impl IntoView {
+///     let count = signals::counter().signal();
+///
+///     view! {
+///         <h1>"Count: " {move || count.get().value.to_string()}</h1>
+///     }
+/// }
+///
+/// fn describe(signal: signals::Signal) -> &'static str {
+///     match signal {
+///         signals::Signal::counter => "the counter",
+///         signals::Signal::chat => "the chat",
+///     }
+/// }
+/// # fn main() {}
+/// ```
+#[macro_export]
+macro_rules! define_sse_signals {
+    ($vis:vis mod $module:ident { $($name:ident : $ty:ty),* $(,)? }) => {
+        #[allow(non_camel_case_types, dead_code)]
+        $vis mod $module {
+            use super::*;
+
+            $(
+                #[doc = concat!("The `", stringify!($name), "` signal.")]
+                pub const fn $name() -> $crate::SseChannel<$ty> {
+                    $crate::SseChannel::new(stringify!($name))
+                }
+            )*
+
+            /// The signals defined in this module.
+            #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+            pub enum Signal {
+                $($name,)*
+            }
+
+            impl Signal {
+                /// All the signals, in definition order.
+                pub const ALL: &'static [Signal] = &[$(Signal::$name,)*];
+
+                /// Returns the name of the signal.
+                pub const fn name(self) -> &'static str {
+                    match self {
+                        $(Signal::$name => stringify!($name),)*
+                    }
+                }
+
+                /// Returns the signal with the given name, if it is defined.
+                pub fn from_name(name: &str) -> Option<Self> {
+                    match name {
+                        $(stringify!($name) => Some(Signal::$name),)*
+                        _ => None,
+                    }
+                }
+            }
+
+            /// The value of one of the signals defined in this module.
+            pub enum SignalValue {
+                $($name($ty),)*
+            }
+
+            impl SignalValue {
+                /// Returns which signal this is a value of.
+                pub const fn signal(&self) -> Signal {
+                    match self {
+                        $(SignalValue::$name(_) => Signal::$name,)*
+                    }
+                }
+
+                /// Decodes the json value of the signal `name`.
+                ///
+                /// Returns `None` if no signal with that name is defined.
+                pub fn from_json(
+                    name: &str,
+                    value: $crate::__private::serde_json::Value,
+                ) -> Option<Result<Self, $crate::__private::serde_json::Error>> {
+                    match Signal::from_name(name)? {
+                        $(Signal::$name => Some(
+                            $crate::__private::serde_json::from_value(value).map(SignalValue::$name)
+                        ),)*
+                    }
+                }
+            }
+
+            $crate::__define_sse_senders! { $($name : $ty),* }
+        }
+    };
+}
+
+#[doc(hidden)]
+#[cfg(all(feature = "ssr", any(feature = "axum", feature = "actix")))]
+#[macro_export]
+macro_rules! __define_sse_senders {
+    ($($name:ident : $ty:ty),*) => {
+        /// The sender halves of all the signals defined in this module.
+        pub struct Senders {
+            $(pub $name: $crate::Sender<$ty>,)*
+        }
+
+        impl Senders {
+            /// Creates a channel pair for every signal, merging the streams into one.
+            ///
+            /// The `buffer` argument applies to each signal separately.
+            pub fn channel(
+                buffer: usize,
+            ) -> Result<
+                (Self, impl $crate::__private::futures::Stream<Item = $crate::ServerSentEventsItem>),
+                $crate::__private::serde_json::Error,
+            > {
+                $(let $name = $name().updater(buffer)?;)*
+                let stream = $crate::__select_streams!($($name.1),*);
+                Ok((Senders { $($name: $name.0,)* }, stream))
+            }
+        }
+    };
+}
+
+#[doc(hidden)]
+#[cfg(not(all(feature = "ssr", any(feature = "axum", feature = "actix"))))]
+#[macro_export]
+macro_rules! __define_sse_senders {
+    ($($tt:tt)*) => {};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __select_streams {
+    ($stream:expr $(,)?) => {
+        $stream
+    };
+    ($stream:expr, $($rest:expr),+ $(,)?) => {
+        $crate::__private::futures::stream::select($stream, $crate::__select_streams!($($rest),+))
+    };
+}
@@ -0,0 +1,83 @@
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+
+use js_sys::{ArrayBuffer, Uint8Array};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::Blob;
+
+use crate::{diagnostics, handle_data};
+
+thread_local! {
+    /// Whether a `Blob` is being read, during which later messages wait in `BACKLOG`.
+    static READING: Cell<bool> = Cell::new(false);
+    static BACKLOG: RefCell<VecDeque<JsValue>> = RefCell::new(VecDeque::new());
+    /// Incremented for every new stream, so that a `Blob` of a previous one is dropped.
+    static GENERATION: Cell<u64> = Cell::new(0);
+}
+
+/// Handles the data of a message, which is text for `EventSource`, but may be an `ArrayBuffer`
+/// or a `Blob` with other transports or polyfills.
+///
+/// Messages are handled in the order they were received, even while a `Blob` is being read.
+pub(crate) fn receive(data: JsValue) {
+    if READING.with(Cell::get) {
+        BACKLOG.with(|backlog| backlog.borrow_mut().push_back(data));
+    } else {
+        process(data);
+    }
+}
+
+/// Handles the data of a message, returning after starting to read a `Blob`.
+fn process(data: JsValue) {
+    if let Some(text) = data.as_string() {
+        handle_data(text);
+    } else if let Some(buffer) = data.dyn_ref::<ArrayBuffer>() {
+        handle_bytes(Uint8Array::new(buffer).to_vec());
+    } else if let Some(blob) = data.dyn_ref::<Blob>() {
+        READING.with(|reading| reading.set(true));
+        let read = JsFuture::from(blob.array_buffer());
+        let generation = GENERATION.with(Cell::get);
+        leptos::task::spawn_local(async move {
+            let read = read.await;
+            if generation != GENERATION.with(Cell::get) {
+                return;
+            }
+            match read {
+                Ok(buffer) => handle_bytes(Uint8Array::new(&buffer).to_vec()),
+                Err(err) => diagnostics::record_malformed(&format!("{err:?}"), ""),
+            }
+            READING.with(|reading| reading.set(false));
+            drain();
+        });
+    } else {
+        diagnostics::record_malformed(&"unsupported message data", "");
+    }
+}
+
+/// Handles the messages which arrived while a `Blob` was being read.
+fn drain() {
+    while !READING.with(Cell::get) {
+        let Some(data) = BACKLOG.with(|backlog| backlog.borrow_mut().pop_front()) else {
+            return;
+        };
+        process(data);
+    }
+}
+
+fn handle_bytes(bytes: Vec<u8>) {
+    match String::from_utf8(bytes) {
+        Ok(text) => handle_data(text),
+        Err(err) => {
+            let payload = String::from_utf8_lossy(err.as_bytes()).into_owned();
+            diagnostics::record_malformed(&err, &payload);
+        }
+    }
+}
+
+/// Drops the messages of a previous stream which are still waiting for a `Blob` to be read.
+pub(crate) fn reset() {
+    GENERATION.with(|generation| generation.set(generation.get() + 1));
+    READING.with(|reading| reading.set(false));
+    BACKLOG.with(|backlog| backlog.borrow_mut().clear());
+}
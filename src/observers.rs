@@ -0,0 +1,64 @@
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use json_patch::Patch;
+use serde_json::Value;
+
+use crate::current_document;
+
+type Observer = Rc<dyn Fn(&str, &Patch, &Value)>;
+
+thread_local! {
+    static OBSERVERS: RefCell<HashMap<Cow<'static, str>, Vec<(u64, Observer)>>> =
+        RefCell::new(HashMap::new());
+    static NEXT_ID: Cell<u64> = Cell::new(0);
+}
+
+/// Adds an observer of the updates to `name`, returning its id.
+pub(crate) fn add(name: Cow<'static, str>, observer: Observer) -> u64 {
+    let id = NEXT_ID.with(|next| {
+        next.set(next.get() + 1);
+        next.get()
+    });
+    OBSERVERS.with(|observers| {
+        observers
+            .borrow_mut()
+            .entry(name)
+            .or_default()
+            .push((id, observer));
+    });
+    id
+}
+
+pub(crate) fn remove(name: &str, id: u64) {
+    OBSERVERS.with(|observers| {
+        let mut observers = observers.borrow_mut();
+        if let Some(list) = observers.get_mut(name) {
+            list.retain(|(observer_id, _)| *observer_id != id);
+            if list.is_empty() {
+                observers.remove(name);
+            }
+        }
+    });
+}
+
+/// Calls the observers of `name` after `patch` has been applied.
+pub(crate) fn notify(name: &str, patch: &Patch) {
+    // Clone the observers, so that they can add or remove observers themselves
+    let list: Vec<Observer> = OBSERVERS.with(|observers| {
+        observers.borrow().get(name).map_or_else(Vec::new, |list| {
+            list.iter().map(|(_, observer)| observer.clone()).collect()
+        })
+    });
+    if list.is_empty() {
+        return;
+    }
+    let Some(value) = current_document(name) else {
+        return;
+    };
+    for observer in list {
+        observer(name, patch, &value);
+    }
+}
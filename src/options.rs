@@ -0,0 +1,392 @@
+#[cfg(feature = "signing")]
+use std::sync::Arc;
+use std::time::Duration;
+
+use leptos::prelude::LeptosOptions;
+
+#[cfg(feature = "encryption")]
+use crate::SessionKey;
+
+/// Options for the SSE connection, passed to [`provide_sse`](crate::provide_sse).
+///
+/// A `&str` or `String` converts into options with the defaults for everything but the url,
+/// so `provide_sse("http://localhost:3000/sse")` keeps working.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use leptos::prelude::*;
+/// use leptos_sse::{ReconnectPolicy, SseOptions, UnknownSignalPolicy};
+///
+/// #[component]
+/// pub fn App() -> impl IntoView {
+///     leptos_sse::provide_sse(
+///         SseOptions::new("http://localhost:3000/sse")
+///             .with_credentials(true)
+///             .reconnect(ReconnectPolicy::Exponential {
+///                 initial: Duration::from_millis(500),
+///                 max: Duration::from_secs(30),
+///             })
+///             .heartbeat_timeout(Duration::from_secs(45))
+///             .unknown_signals(UnknownSignalPolicy::Drop)
+///             .logging(false),
+///     )
+///     .unwrap();
+///
+///     // ...
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SseOptions {
+    pub(crate) url: String,
+    pub(crate) with_credentials: bool,
+    pub(crate) reconnect: ReconnectPolicy,
+    pub(crate) max_reconnect_attempts: Option<u32>,
+    pub(crate) max_reconnect_time: Option<Duration>,
+    pub(crate) codec: Codec,
+    pub(crate) logging: bool,
+    pub(crate) heartbeat_timeout: Option<Duration>,
+    pub(crate) unknown_signals: UnknownSignalPolicy,
+    pub(crate) worker: bool,
+    pub(crate) lazy: bool,
+    pub(crate) base_path: Option<String>,
+    pub(crate) prefetch: Option<String>,
+    pub(crate) ack_url: Option<String>,
+    pub(crate) subscription_url: Option<String>,
+    pub(crate) max_message_size: Option<usize>,
+    pub(crate) strict: bool,
+    pub(crate) retain_documents: bool,
+    #[cfg(feature = "tauri")]
+    pub(crate) tauri: bool,
+    #[cfg(feature = "signing")]
+    pub(crate) signing_key: Option<Arc<[u8]>>,
+    #[cfg(feature = "encryption")]
+    pub(crate) session_key: Option<SessionKey>,
+}
+
+impl SseOptions {
+    /// Creates options for connecting to `url`, with defaults for everything else.
+    pub fn new(url: impl Into<String>) -> Self {
+        SseOptions {
+            url: url.into(),
+            with_credentials: false,
+            reconnect: ReconnectPolicy::default(),
+            max_reconnect_attempts: None,
+            max_reconnect_time: None,
+            codec: Codec::default(),
+            logging: true,
+            heartbeat_timeout: None,
+            unknown_signals: UnknownSignalPolicy::default(),
+            worker: false,
+            lazy: false,
+            base_path: None,
+            prefetch: None,
+            ack_url: None,
+            subscription_url: None,
+            max_message_size: None,
+            strict: false,
+            retain_documents: true,
+            #[cfg(feature = "tauri")]
+            tauri: false,
+            #[cfg(feature = "signing")]
+            signing_key: None,
+            #[cfg(feature = "encryption")]
+            session_key: None,
+        }
+    }
+
+    /// Creates options for the SSE endpoint at `path` on the site serving the Leptos app.
+    ///
+    /// In the browser, `path` is resolved against the page's base url, so no hostname has to
+    /// be hardcoded. Elsewhere, it is resolved against the `site_addr` of the [`LeptosOptions`].
+    #[allow(unused_variables)]
+    pub fn from_leptos_options(leptos_options: &LeptosOptions, path: &str) -> Self {
+        cfg_if::cfg_if! {
+            if #[cfg(target_arch = "wasm32")] {
+                SseOptions::new(path)
+            } else {
+                SseOptions::new(format!("http://{}{}", leptos_options.site_addr, path))
+            }
+        }
+    }
+
+    /// Returns the url of the SSE endpoint.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Sets a base path prepended to root-relative urls, such as `/sse`.
+    ///
+    /// Use this when the app is served under a sub-path, for example `/app`, so that
+    /// `provide_sse("/sse")` connects to `/app/sse`. Relative urls are resolved against the
+    /// page's base url (see the `<base>` element), and absolute urls are used as they are.
+    pub fn base_path(mut self, base_path: impl Into<String>) -> Self {
+        self.base_path = Some(base_path.into());
+        self
+    }
+
+    /// Returns the url with the base path applied, if it is root-relative.
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn url_with_base_path(&self) -> String {
+        self.apply_base_path(&self.url)
+    }
+
+    /// Prepends the base path to `url`, if it is root-relative.
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn apply_base_path(&self, url: &str) -> String {
+        match &self.base_path {
+            Some(base_path) if url.starts_with('/') && !url.starts_with("//") => {
+                format!("{}{}", base_path.trim_end_matches('/'), url)
+            }
+            _ => url.to_string(),
+        }
+    }
+
+    /// Sends cookies and other credentials with cross-origin requests. Defaults to `false`.
+    pub fn with_credentials(mut self, with_credentials: bool) -> Self {
+        self.with_credentials = with_credentials;
+        self
+    }
+
+    /// Sets how the connection is re-established after an error.
+    pub fn reconnect(mut self, reconnect: ReconnectPolicy) -> Self {
+        self.reconnect = reconnect;
+        self
+    }
+
+    /// Gives up after `attempts` failed attempts to re-establish the connection.
+    ///
+    /// The connection is then closed, and its state becomes
+    /// [`SseConnectionState::Failed`](crate::SseConnectionState::Failed), so the app can show
+    /// that realtime updates are unavailable instead of retrying forever. The count is reset
+    /// once a connection has been opened.
+    pub fn max_reconnect_attempts(mut self, attempts: u32) -> Self {
+        self.max_reconnect_attempts = Some(attempts);
+        self
+    }
+
+    /// Gives up when the connection hasn't been re-established within `time` of the first
+    /// error, see [`SseOptions::max_reconnect_attempts`].
+    pub fn max_reconnect_time(mut self, time: Duration) -> Self {
+        self.max_reconnect_time = Some(time);
+        self
+    }
+
+    /// Sets the codec updates are encoded with.
+    pub fn codec(mut self, codec: Codec) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    /// Enables or disables logging of connection events and received messages.
+    ///
+    /// Errors and warnings are always logged. Defaults to `true`.
+    pub fn logging(mut self, logging: bool) -> Self {
+        self.logging = logging;
+        self
+    }
+
+    /// Reconnects if nothing is received for `timeout`.
+    ///
+    /// SSE comments are not visible to the browser, so the server should send a `heartbeat`
+    /// event more often than this, for example by wrapping the events in a `Heartbeat` stream.
+    /// With axum, a keep-alive can also send them, with
+    /// `KeepAlive::new().event(Event::default().event("heartbeat"))`.
+    pub fn heartbeat_timeout(mut self, timeout: Duration) -> Self {
+        self.heartbeat_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets what happens to updates for signals which haven't been created yet.
+    pub fn unknown_signals(mut self, policy: UnknownSignalPolicy) -> Self {
+        self.unknown_signals = policy;
+        self
+    }
+
+    /// Runs the connection in a dedicated Web Worker, see
+    /// [`provide_sse_worker`](crate::provide_sse_worker).
+    ///
+    /// Every other option applies as it does without a worker.
+    pub fn worker(mut self, worker: bool) -> Self {
+        self.worker = worker;
+        self
+    }
+
+    /// Only opens the connection once the first signal is created, and closes it again when
+    /// the last signal is disposed.
+    ///
+    /// This avoids idle connections on routes which don't use any server signals. Signals
+    /// created with [`create_sse_arc_signal`](crate::create_sse_arc_signal) are never disposed,
+    /// so they keep the connection open. Defaults to `false`.
+    pub fn lazy(mut self, lazy: bool) -> Self {
+        self.lazy = lazy;
+        self
+    }
+
+    /// Fetches the current value of every signal from `{url}/{name}` when it is created.
+    ///
+    /// This shows real data on first paint even if the SSE connection is slow to establish,
+    /// for example behind proxies which buffer responses. The inspector route
+    /// `/sse/state/{name}` of an `SseRegistry` serves exactly this. A snapshot is only applied
+    /// if the signal hasn't received an update yet, and the first update of the stream replaces
+    /// it, since it is a diff from the default value.
+    ///
+    /// The snapshots are fetched again whenever the connection is re-established, so signals
+    /// catch up on the updates they missed while disconnected.
+    pub fn prefetch(mut self, url: impl Into<String>) -> Self {
+        self.prefetch = Some(url.into());
+        self
+    }
+
+    /// Acknowledges the applied updates by posting an [`SseAck`](crate::SseAck) to `url`.
+    ///
+    /// The acknowledgements are sent with `navigator.sendBeacon` shortly after updates are
+    /// applied, and let the server tell which clients are behind, see
+    /// `SseRegistry::lagging`. The id of the client is added to the SSE url, for the server to
+    /// match the acknowledgements with the connection, see
+    /// [`SseClientId`](crate::SseClientId).
+    pub fn ack_url(mut self, url: impl Into<String>) -> Self {
+        self.ack_url = Some(url.into());
+        self
+    }
+
+    /// Posts the signals created with
+    /// [`create_sse_dynamic_signal`](crate::create_sse_dynamic_signal) to `url`, as an
+    /// [`SseSubscription`](crate::SseSubscription).
+    ///
+    /// This lets the server stream the signals whose names are only known at runtime, such as
+    /// the document a user opens, see `SseRegistry::dynamic_events`. Like acknowledgements, the
+    /// subscriptions are sent with `navigator.sendBeacon`, and the id of the client is added to
+    /// the SSE url.
+    pub fn subscription_url(mut self, url: impl Into<String>) -> Self {
+        self.subscription_url = Some(url.into());
+        self
+    }
+
+    /// Receives the updates emitted by the backend of a Tauri app, see
+    /// [`spawn_tauri_signal`](crate::spawn_tauri_signal), instead of opening an `EventSource`.
+    ///
+    /// The url is ignored, and so are the reconnect, heartbeat and worker options, as the
+    /// events can't be lost. This needs the global Tauri API, enabled with
+    /// `app.withGlobalTauri` in `tauri.conf.json`.
+    #[cfg(feature = "tauri")]
+    pub fn tauri(mut self, tauri: bool) -> Self {
+        self.tauri = tauri;
+        self
+    }
+
+    /// Refuses messages larger than `size` bytes, instead of parsing them on the UI thread.
+    ///
+    /// The document of a signal whose update was refused is replaced with its snapshot, which
+    /// needs [`prefetch`](Self::prefetch), and its updates are dropped until the snapshot has
+    /// arrived. Without prefetching, the signal stays out of step until the next connection.
+    /// Chunked messages are refused as soon as their first chunk shows they are too large.
+    /// Servers can avoid sending them with `ServerSentEvents::max_message_size`.
+    pub fn max_message_size(mut self, size: usize) -> Self {
+        self.max_message_size = Some(size);
+        self
+    }
+
+    /// Only applies updates which leave the document of their signal readable as its type.
+    ///
+    /// Every update is applied to a copy of the document, which must deserialize into the type
+    /// of the signal before it replaces the document. An update which doesn't is dropped like a
+    /// patch which doesn't apply: it is reported in the diagnostics, and the document is
+    /// replaced with its snapshot, which needs [`prefetch`](Self::prefetch). Otherwise, such a
+    /// document is kept, and the signal keeps its last value until a later update fixes it.
+    ///
+    /// This costs a copy and a deserialization of the document per update. Signals of untyped
+    /// json, lists and series aren't checked. Defaults to `false`.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Keeps the document of a name once every signal created for it has been disposed.
+    ///
+    /// Signals created for the name again then start from the document. Without it, the
+    /// document is dropped with its last signal, and the updates to it are dropped until a
+    /// signal is created for the name again, which bounds the memory of apps creating signals
+    /// for many names over the life of the page. A signal created again starts from its default
+    /// value, so pair this with [`prefetch`](Self::prefetch) to fetch the current document.
+    /// The documents held can be listed with [`sse_documents`](crate::sse_documents). Defaults
+    /// to `true`.
+    pub fn retain_documents(mut self, retain: bool) -> Self {
+        self.retain_documents = retain;
+        self
+    }
+
+    /// Only applies updates signed with `key`, see [`ServerSignalUpdate::sign`].
+    ///
+    /// Unsigned updates and updates with an invalid signature are logged and dropped.
+    ///
+    /// [`ServerSignalUpdate::sign`]: crate::ServerSignalUpdate::sign
+    #[cfg(feature = "signing")]
+    pub fn signing_key(mut self, key: impl Into<Arc<[u8]>>) -> Self {
+        self.signing_key = Some(key.into());
+        self
+    }
+
+    /// Decrypts updates with `key`, see [`SessionKey`].
+    ///
+    /// Messages which can't be decrypted are logged and dropped.
+    #[cfg(feature = "encryption")]
+    pub fn session_key(mut self, key: SessionKey) -> Self {
+        self.session_key = Some(key);
+        self
+    }
+}
+
+impl From<&str> for SseOptions {
+    fn from(url: &str) -> Self {
+        SseOptions::new(url)
+    }
+}
+
+impl From<String> for SseOptions {
+    fn from(url: String) -> Self {
+        SseOptions::new(url)
+    }
+}
+
+/// How the connection is re-established after an error.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ReconnectPolicy {
+    /// Leave reconnecting to the browser, which retries after the `retry` interval sent by the
+    /// server, but gives up for good on HTTP errors.
+    #[default]
+    Browser,
+    /// Close the connection on the first error.
+    Never,
+    /// Reconnect with an exponentially growing delay, starting at `initial` and capped at `max`.
+    ///
+    /// The delay is reset once a connection has been opened.
+    Exponential {
+        /// The delay before the first attempt.
+        initial: Duration,
+        /// The maximum delay between attempts.
+        max: Duration,
+    },
+}
+
+/// The encoding of updates sent over the SSE.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Codec {
+    /// Updates are json encoded [`ServerSignalUpdate`](crate::ServerSignalUpdate)s.
+    #[default]
+    Json,
+}
+
+/// What happens to updates for signals which haven't been created yet.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum UnknownSignalPolicy {
+    /// Queue the patches, and apply them once the signal is created.
+    #[default]
+    Queue,
+    /// Drop the update.
+    Drop,
+}
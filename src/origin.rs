@@ -0,0 +1,71 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// An allowlist of origins which may connect to the SSE route.
+///
+/// SSE endpoints often stream user data, so they shouldn't be readable from any site. The
+/// `Origin` header is checked against the allowlist, falling back to the origin of the
+/// `Referer` header for same-origin requests where browsers omit `Origin`.
+///
+/// With axum, use [`AllowedOrigins::middleware`]. With actix, use
+/// `AllowedOrigins::check_request` in the handler.
+///
+/// ```
+/// use leptos_sse::AllowedOrigins;
+///
+/// let allowed = AllowedOrigins::new(["https://example.com"]);
+/// assert!(allowed.is_allowed(Some("https://example.com"), None));
+/// assert!(allowed.is_allowed(None, Some("https://example.com/dashboard")));
+/// assert!(!allowed.is_allowed(Some("https://evil.example"), None));
+/// assert!(!allowed.is_allowed(None, None));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct AllowedOrigins {
+    origins: Arc<HashSet<String>>,
+    allow_missing: bool,
+}
+
+impl AllowedOrigins {
+    /// Creates an allowlist of origins such as `https://example.com` or
+    /// `http://localhost:3000`.
+    pub fn new<I>(origins: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let origins = origins
+            .into_iter()
+            .map(|origin| origin.into().trim_end_matches('/').to_ascii_lowercase())
+            .collect();
+        AllowedOrigins {
+            origins: Arc::new(origins),
+            allow_missing: false,
+        }
+    }
+
+    /// Allows requests with neither an `Origin` nor a `Referer` header, such as requests from
+    /// non-browser clients. Defaults to `false`.
+    pub fn allow_missing(mut self, allow_missing: bool) -> Self {
+        self.allow_missing = allow_missing;
+        self
+    }
+
+    /// Returns `true` if a request with these `Origin` and `Referer` header values is allowed.
+    pub fn is_allowed(&self, origin: Option<&str>, referer: Option<&str>) -> bool {
+        match origin.or_else(|| referer.map(referer_origin)) {
+            Some(origin) => self
+                .origins
+                .contains(&origin.trim_end_matches('/').to_ascii_lowercase()),
+            None => self.allow_missing,
+        }
+    }
+}
+
+/// Returns the `scheme://host[:port]` part of a referer url.
+fn referer_origin(referer: &str) -> &str {
+    let start = referer.find("://").map_or(0, |i| i + 3);
+    match referer[start..].find(['/', '?', '#']) {
+        Some(end) => &referer[..start + end],
+        None => referer,
+    }
+}
@@ -0,0 +1,71 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use json_patch::Patch;
+use serde_json::{json, Value};
+
+use crate::{
+    current_document, on_applied, rebase_document, resync, set_document, ServerSignalUpdate,
+    SIGNAL_META,
+};
+
+thread_local! {
+    /// The paused signals, with the document the updates received while paused were applied
+    /// to, once there was one.
+    static PAUSED: RefCell<HashMap<Cow<'static, str>, Option<Value>>> = RefCell::new(HashMap::new());
+}
+
+pub(crate) fn pause(name: Cow<'static, str>) {
+    PAUSED.with(|paused| {
+        paused.borrow_mut().entry(name).or_default();
+    });
+}
+
+/// Resumes a signal, setting it to the document it would have had without the pause.
+pub(crate) fn resume(name: &str) {
+    let Some(Some(doc)) = PAUSED.with(|paused| paused.borrow_mut().remove(name)) else {
+        return;
+    };
+    let Ok(patch) = serde_json::from_value::<Patch>(json!([
+        { "op": "replace", "path": "", "value": doc }
+    ])) else {
+        return;
+    };
+    set_document(name, doc);
+    on_applied(name, &patch);
+}
+
+/// Applies an update to the held document of a paused signal instead of the signal.
+///
+/// Returns `false` if the signal isn't paused, or doesn't exist yet.
+pub(crate) fn hold(update: &ServerSignalUpdate) -> bool {
+    PAUSED.with(|paused| {
+        let mut paused = paused.borrow_mut();
+        let Some(held) = paused.get_mut(&*update.name) else {
+            return false;
+        };
+        if held.is_none() {
+            let default = || {
+                SIGNAL_META.with(|meta| {
+                    let meta = meta.borrow();
+                    meta.get(&*update.name).map(|meta| meta.default.clone())
+                })
+            };
+            *held = current_document(&update.name).or_else(default);
+        }
+        let Some(doc) = held else {
+            return false;
+        };
+        if !rebase_document(&update.name, update.seq, doc) {
+            // Held again from the snapshot replacing the stale document
+            *held = None;
+            resync(&update.name);
+            return true;
+        }
+        if let Err(err) = json_patch::patch(doc, &update.patch) {
+            leptos::logging::error!("failed to apply update to paused {}: {}", update.name, err);
+        }
+        true
+    })
+}
@@ -0,0 +1,184 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use js_sys::{Object, Promise, Reflect};
+use leptos::prelude::*;
+use serde_json::Value;
+use wasm_bindgen::{prelude::Closure, JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{IdbDatabase, IdbRequest, IdbTransactionMode};
+
+use crate::{current_document, set_document, SIGNAL_META};
+
+const DB_NAME: &str = "leptos_sse";
+const STORE_NAME: &str = "signals";
+
+/// How long to wait after an update before writing the document, so bursts of updates only
+/// serialize the document once.
+const STORE_DELAY: Duration = Duration::from_millis(500);
+
+thread_local! {
+    static DB: RefCell<Option<IdbDatabase>> = RefCell::new(None);
+    static PERSISTED: RefCell<HashMap<Cow<'static, str>, u32>> = RefCell::new(HashMap::new());
+    static PENDING: RefCell<HashSet<Cow<'static, str>>> = RefCell::new(HashSet::new());
+}
+
+/// Enables persistence for a signal, restoring its cached document if there is one.
+pub(crate) fn enable(name: Cow<'static, str>, version: u32) {
+    PERSISTED.with(|persisted| {
+        persisted.borrow_mut().insert(name.clone(), version);
+    });
+
+    leptos::task::spawn_local(async move {
+        match load(&name).await {
+            Ok(Some((cached_version, _, doc))) if cached_version == version => {
+                // Updates or snapshots which arrived while loading are newer than the cache
+                let current = current_document(&name);
+                let fresh = SIGNAL_META.with(|meta| match meta.borrow_mut().get_mut(&*name) {
+                    Some(meta) if meta.seq.is_none() && current.as_ref() == Some(&meta.default) => {
+                        // The cache was written from an earlier stream, so only the first update
+                        // of a stream applies to it, and any other fetches the snapshot instead
+                        meta.seq = Some(0);
+                        true
+                    }
+                    _ => false,
+                });
+                if fresh {
+                    set_document(&name, doc);
+                }
+            }
+            Ok(_) => {}
+            Err(err) => {
+                leptos::logging::warn!("failed to load cached document for {}: {:?}", name, err)
+            }
+        }
+    });
+}
+
+/// Schedules the current document of a signal to be written to IndexedDB.
+pub(crate) fn schedule_store(name: &str) {
+    let Some(version) = PERSISTED.with(|persisted| persisted.borrow().get(name).copied()) else {
+        return;
+    };
+    let newly_pending =
+        PENDING.with(|pending| pending.borrow_mut().insert(Cow::Owned(name.to_string())));
+    if !newly_pending {
+        return;
+    }
+
+    let name = name.to_string();
+    set_timeout(
+        move || {
+            PENDING.with(|pending| pending.borrow_mut().remove(name.as_str()));
+            let Some(doc) = current_document(&name) else {
+                return;
+            };
+            let seq = SIGNAL_META
+                .with(|meta| meta.borrow().get(name.as_str()).and_then(|meta| meta.seq))
+                .unwrap_or_default();
+            leptos::task::spawn_local(async move {
+                if let Err(err) = store(&name, version, seq, &doc).await {
+                    leptos::logging::warn!("failed to cache document for {}: {:?}", name, err);
+                }
+            });
+        },
+        STORE_DELAY,
+    );
+}
+
+async fn load(name: &str) -> Result<Option<(u32, u64, Value)>, JsValue> {
+    let db = database().await?;
+    let transaction = db.transaction_with_str(STORE_NAME)?;
+    let request = transaction
+        .object_store(STORE_NAME)?
+        .get(&JsValue::from_str(name))?;
+    let record = wait(&request).await?;
+    if record.is_undefined() {
+        return Ok(None);
+    }
+
+    let version = Reflect::get(&record, &"version".into())?
+        .as_f64()
+        .unwrap_or_default() as u32;
+    let seq = Reflect::get(&record, &"seq".into())?
+        .as_f64()
+        .unwrap_or_default() as u64;
+    let Some(doc) = Reflect::get(&record, &"doc".into())?.as_string() else {
+        return Ok(None);
+    };
+    let doc = serde_json::from_str(&doc).map_err(|err| JsValue::from_str(&err.to_string()))?;
+    Ok(Some((version, seq, doc)))
+}
+
+async fn store(name: &str, version: u32, seq: u64, doc: &Value) -> Result<(), JsValue> {
+    let record = Object::new();
+    Reflect::set(&record, &"version".into(), &version.into())?;
+    Reflect::set(&record, &"seq".into(), &(seq as f64).into())?;
+    Reflect::set(&record, &"doc".into(), &doc.to_string().into())?;
+
+    let db = database().await?;
+    let transaction =
+        db.transaction_with_str_and_mode(STORE_NAME, IdbTransactionMode::Readwrite)?;
+    let request = transaction
+        .object_store(STORE_NAME)?
+        .put_with_key(&record, &JsValue::from_str(name))?;
+    wait(&request).await?;
+    Ok(())
+}
+
+/// Opens the database, creating the object store on first use.
+async fn database() -> Result<IdbDatabase, JsValue> {
+    if let Some(db) = DB.with(|db| db.borrow().clone()) {
+        return Ok(db);
+    }
+
+    let factory = web_sys::window()
+        .ok_or_else(|| JsValue::from_str("no window"))?
+        .indexed_db()?
+        .ok_or_else(|| JsValue::from_str("IndexedDB is not available"))?;
+    let request = factory.open_with_u32(DB_NAME, 1)?;
+
+    let upgrade_request = request.clone();
+    let onupgradeneeded = Closure::once_into_js(move |_event: web_sys::Event| {
+        if let Ok(db) = upgrade_request.result() {
+            let db: IdbDatabase = db.unchecked_into();
+            if !db.object_store_names().contains(STORE_NAME) {
+                let _ = db.create_object_store(STORE_NAME);
+            }
+        }
+    });
+    request.set_onupgradeneeded(Some(onupgradeneeded.unchecked_ref()));
+
+    let db: IdbDatabase = wait(&request).await?.unchecked_into();
+    DB.with(|cell| {
+        *cell.borrow_mut() = Some(db.clone());
+    });
+    Ok(db)
+}
+
+/// Waits for an IndexedDB request to complete, returning its result.
+async fn wait(request: &IdbRequest) -> Result<JsValue, JsValue> {
+    let promise = Promise::new(&mut |resolve, reject| {
+        let success_request = request.clone();
+        let onsuccess = Closure::once_into_js(move |_event: web_sys::Event| {
+            let result = success_request.result().unwrap_or(JsValue::UNDEFINED);
+            let _ = resolve.call1(&JsValue::NULL, &result);
+        });
+        request.set_onsuccess(Some(onsuccess.unchecked_ref()));
+
+        let error_request = request.clone();
+        let onerror = Closure::once_into_js(move |_event: web_sys::Event| {
+            let error = error_request
+                .error()
+                .ok()
+                .flatten()
+                .map(JsValue::from)
+                .unwrap_or_else(|| JsValue::from_str("IndexedDB request failed"));
+            let _ = reject.call1(&JsValue::NULL, &error);
+        });
+        request.set_onerror(Some(onerror.unchecked_ref()));
+    });
+    JsFuture::from(promise).await
+}
@@ -0,0 +1,114 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+
+use serde_json::Value;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{RequestCredentials, RequestInit, Response};
+
+use crate::{connection, current_document, set_document, SIGNAL_META};
+
+thread_local! {
+    /// The signals whose updates are dropped until their snapshot has been fetched.
+    static REPLACING: RefCell<HashSet<String>> = RefCell::new(HashSet::new());
+}
+
+/// Fetches the snapshot of a newly created signal, if prefetching is enabled.
+pub(crate) fn snapshot(name: &str) {
+    request(name, false);
+}
+
+/// Fetches the snapshots of all signals again after the connection has been re-established, so
+/// that they catch up on what they missed without waiting for the new stream.
+pub(crate) fn resync_all() {
+    let names: Vec<String> =
+        SIGNAL_META.with(|meta| meta.borrow().keys().map(|name| name.to_string()).collect());
+    for name in names {
+        request(&name, true);
+    }
+}
+
+/// Replaces the document of a signal with its snapshot, after an update to it was skipped.
+///
+/// The updates following the skipped one don't apply to the current document, so they are
+/// dropped until the snapshot has arrived. Returns `false` if prefetching isn't enabled.
+pub(crate) fn replace(name: &str) -> bool {
+    let Some((url, with_credentials)) = connection::snapshot_url(name) else {
+        return false;
+    };
+    let newly_replacing =
+        REPLACING.with(|replacing| replacing.borrow_mut().insert(name.to_string()));
+    if !newly_replacing {
+        return true;
+    }
+    let name = name.to_string();
+    leptos::task::spawn_local(async move {
+        match fetch(&url, with_credentials).await {
+            Ok(Some(doc)) => {
+                sse_log!("Replacing {} with its snapshot", name);
+                set_document(&name, doc);
+                // The next update follows the snapshot, whatever its sequence number
+                SIGNAL_META.with(|meta| {
+                    if let Some(meta) = meta.borrow_mut().get_mut(name.as_str()) {
+                        meta.seq = None;
+                    }
+                });
+            }
+            Ok(None) => leptos::logging::warn!("no snapshot to replace {} with", name),
+            Err(err) => leptos::logging::warn!("failed to fetch snapshot of {}: {:?}", name, err),
+        }
+        REPLACING.with(|replacing| replacing.borrow_mut().remove(&name));
+    });
+    true
+}
+
+/// Returns `true` while the updates to a signal are dropped, see [`replace`].
+pub(crate) fn is_replacing(name: &str) -> bool {
+    REPLACING.with(|replacing| replacing.borrow().contains(name))
+}
+
+fn request(name: &str, resync: bool) {
+    let Some((url, with_credentials)) = connection::snapshot_url(name) else {
+        return;
+    };
+    let name = name.to_string();
+    leptos::task::spawn_local(async move {
+        match fetch(&url, with_credentials).await {
+            Ok(Some(doc)) => apply(&name, doc, resync),
+            Ok(None) => {}
+            Err(err) => leptos::logging::warn!("failed to prefetch {}: {:?}", name, err),
+        }
+    });
+}
+
+/// Applies a snapshot, unless the signal has received an update from the current stream, or has
+/// changed since it was created.
+fn apply(name: &str, doc: Value, resync: bool) {
+    let untouched = SIGNAL_META.with(|meta| {
+        meta.borrow().get(name).is_some_and(|meta| {
+            meta.seq.is_none() && (resync || current_document(name).as_ref() == Some(&meta.default))
+        })
+    });
+    if untouched {
+        sse_log!("Applying prefetched snapshot of {}", name);
+        set_document(name, doc);
+    }
+}
+
+pub(crate) async fn fetch(url: &str, with_credentials: bool) -> Result<Option<Value>, JsValue> {
+    let window = web_sys::window().ok_or_else(|| JsValue::from_str("no window"))?;
+    let init = RequestInit::new();
+    if with_credentials {
+        init.set_credentials(RequestCredentials::Include);
+    }
+    let response: Response = JsFuture::from(window.fetch_with_str_and_init(url, &init))
+        .await?
+        .unchecked_into();
+    if !response.ok() {
+        return Ok(None);
+    }
+    let text = JsFuture::from(response.text()?).await?;
+    let text = text.as_string().unwrap_or_default();
+    let doc = serde_json::from_str(&text).map_err(|err| JsValue::from_str(&err.to_string()))?;
+    Ok(Some(doc))
+}
@@ -0,0 +1,97 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use prost::Message;
+use serde_json::Value;
+
+use crate::ServerSignalUpdate;
+
+/// The named event carrying an update as a base64 encoded protobuf message.
+pub(crate) const PROTOBUF_EVENT: &str = "protobuf";
+
+/// `ServerSignalUpdate` in `proto/leptos_sse.proto`.
+#[derive(Clone, PartialEq, Message)]
+struct ProtoUpdate {
+    #[prost(string, tag = "1")]
+    name: String,
+    #[prost(message, repeated, tag = "2")]
+    patch: Vec<ProtoOperation>,
+    #[prost(uint64, optional, tag = "3")]
+    seq: Option<u64>,
+    #[prost(uint32, optional, tag = "4")]
+    version: Option<u32>,
+}
+
+/// `PatchOperation` in `proto/leptos_sse.proto`.
+#[derive(Clone, PartialEq, Message)]
+struct ProtoOperation {
+    #[prost(string, tag = "1")]
+    op: String,
+    #[prost(string, tag = "2")]
+    path: String,
+    #[prost(string, optional, tag = "3")]
+    from: Option<String>,
+    #[prost(string, optional, tag = "4")]
+    value: Option<String>,
+}
+
+pub(crate) fn encode(update: &ServerSignalUpdate) -> Result<String, serde_json::Error> {
+    let Value::Array(operations) = serde_json::to_value(update.patch())? else {
+        unreachable!("patches serialize to arrays");
+    };
+    let patch = operations
+        .into_iter()
+        .map(|mut operation| {
+            let mut field = |key: &str| operation.get_mut(key).map(Value::take);
+            let text = |value: Option<Value>| match value {
+                Some(Value::String(text)) => text,
+                _ => String::new(),
+            };
+            Ok(ProtoOperation {
+                op: text(field("op")),
+                path: text(field("path")),
+                from: field("from").map(|from| text(Some(from))),
+                value: field("value")
+                    .map(|value| serde_json::to_string(&value))
+                    .transpose()?,
+            })
+        })
+        .collect::<Result<_, serde_json::Error>>()?;
+    let message = ProtoUpdate {
+        name: update.name().to_string(),
+        patch,
+        seq: update.seq(),
+        version: update.version(),
+    };
+    Ok(STANDARD.encode(message.encode_to_vec()))
+}
+
+/// Decodes a `protobuf` event into the json of its update.
+#[cfg(feature = "testing")]
+pub(crate) fn decode(data: &str) -> Result<String, String> {
+    let bytes = STANDARD.decode(data).map_err(|err| err.to_string())?;
+    let message = ProtoUpdate::decode(bytes.as_slice()).map_err(|err| err.to_string())?;
+    let patch = message
+        .patch
+        .into_iter()
+        .map(|operation| {
+            let mut json = serde_json::Map::new();
+            json.insert("op".into(), operation.op.into());
+            json.insert("path".into(), operation.path.into());
+            if let Some(from) = operation.from {
+                json.insert("from".into(), from.into());
+            }
+            if let Some(value) = operation.value {
+                let value: Value = serde_json::from_str(&value).map_err(|err| err.to_string())?;
+                json.insert("value".into(), value);
+            }
+            Ok(Value::Object(json))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let update = serde_json::json!({
+        "name": message.name,
+        "patch": patch,
+        "seq": message.seq,
+        "version": message.version,
+    });
+    Ok(update.to_string())
+}
@@ -0,0 +1,158 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{ServerSignalUpdate, SseStateSnapshot};
+
+/// The updates received by the client over a period of time, with the documents of the signals
+/// when it started, see [`start_sse_recording`].
+///
+/// A recording serializes to JSON, so it can be saved from a live session and replayed later
+/// with [`replay_sse_recording`], without any server: for offline demos, developing components
+/// against realistic data, or regression tests. To record the updates sent by a server, see
+/// `SseRecorder`.
+///
+/// ```
+/// use leptos_sse::{replay_sse_recording, SseRecording};
+///
+/// let recording: SseRecording = serde_json::from_str(r#"{
+///     "initial": { "signals": { "counter": { "doc": { "value": 0 } } } },
+///     "updates": [
+///         { "at": 1000.0, "update": { "name": "counter", "patch": [
+///             { "op": "replace", "path": "/value", "value": 1 }
+///         ] } }
+///     ]
+/// }"#).unwrap();
+/// replay_sse_recording(recording, 1.0);
+/// ```
+///
+/// [`start_sse_recording`]: crate::start_sse_recording
+/// [`replay_sse_recording`]: crate::replay_sse_recording
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SseRecording {
+    initial: SseStateSnapshot,
+    updates: Vec<RecordedUpdate>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct RecordedUpdate {
+    /// When the update was received, in milliseconds since the recording started.
+    at: f64,
+    update: ServerSignalUpdate,
+}
+
+/// A line of the NDJSON files written by `SseUpdateLog`.
+#[derive(Deserialize)]
+struct LoggedUpdate {
+    /// When the update was published, in milliseconds since the Unix epoch.
+    at: f64,
+    update: ServerSignalUpdate,
+}
+
+impl SseRecording {
+    /// Returns the documents of the signals when the recording started.
+    pub fn initial(&self) -> &SseStateSnapshot {
+        &self.initial
+    }
+
+    /// Returns the recorded updates, in the order they were received.
+    pub fn updates(&self) -> impl Iterator<Item = &ServerSignalUpdate> {
+        self.updates.iter().map(|recorded| &recorded.update)
+    }
+
+    /// Reads the updates logged by `SseUpdateLog` on the server, from one of its NDJSON files or
+    /// several of them concatenated in order.
+    ///
+    /// The updates keep the time between them. The recording starts without any document, since
+    /// the log starts every signal with an update replacing its whole document.
+    pub fn from_ndjson(ndjson: &str) -> Result<Self, serde_json::Error> {
+        let mut started = None;
+        let mut updates = Vec::new();
+        for line in ndjson.lines().filter(|line| !line.trim().is_empty()) {
+            let logged: LoggedUpdate = serde_json::from_str(line)?;
+            let started = *started.get_or_insert(logged.at);
+            updates.push(RecordedUpdate {
+                at: logged.at - started,
+                update: logged.update,
+            });
+        }
+        Ok(SseRecording {
+            initial: SseStateSnapshot::default(),
+            updates,
+        })
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod client {
+    use std::cell::RefCell;
+    use std::time::Duration;
+
+    use leptos::prelude::*;
+
+    use super::{RecordedUpdate, SseRecording};
+    use crate::{export, handle_update, set_document, ServerSignalUpdate, SIGNAL_META};
+
+    thread_local! {
+        /// The recording in progress, with when it started.
+        static RECORDING: RefCell<Option<(f64, SseRecording)>> = RefCell::new(None);
+    }
+
+    pub(crate) fn start() {
+        let recording = SseRecording {
+            initial: export::export(),
+            updates: Vec::new(),
+        };
+        RECORDING.with(|cell| *cell.borrow_mut() = Some((js_sys::Date::now(), recording)));
+    }
+
+    pub(crate) fn stop() -> SseRecording {
+        RECORDING
+            .with(|cell| cell.borrow_mut().take())
+            .map(|(_, recording)| recording)
+            .unwrap_or_default()
+    }
+
+    /// Adds an update to the recording in progress, if any.
+    pub(crate) fn record(update: &ServerSignalUpdate) {
+        RECORDING.with(|cell| {
+            if let Some((started, recording)) = cell.borrow_mut().as_mut() {
+                recording.updates.push(RecordedUpdate {
+                    at: js_sys::Date::now() - *started,
+                    update: update.clone(),
+                });
+            }
+        });
+    }
+
+    /// Sets the signals to the initial documents of `recording`, then applies its updates with
+    /// their original timing divided by `speed`.
+    pub(crate) fn replay(recording: SseRecording, speed: f64) {
+        for (name, signal) in recording.initial.signals {
+            SIGNAL_META.with(|meta| {
+                if let Some(meta) = meta.borrow_mut().get_mut(name.as_str()) {
+                    meta.seq = signal.seq;
+                    meta.rebase_pending = false;
+                }
+            });
+            set_document(&name, signal.doc);
+        }
+        for recorded in recording.updates {
+            let delay = Duration::from_secs_f64((recorded.at / speed).max(0.0) / 1000.0);
+            set_timeout(move || apply(recorded.update), delay);
+        }
+    }
+
+    fn apply(update: ServerSignalUpdate) {
+        // A new stream started while recording
+        if update.seq == Some(1) {
+            SIGNAL_META.with(|meta| {
+                if let Some(meta) = meta.borrow_mut().get_mut(&*update.name) {
+                    meta.rebase_pending = true;
+                }
+            });
+        }
+        handle_update(update);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) use client::*;
@@ -0,0 +1,2014 @@
+use std::any::Any;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicIsize, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use futures::future;
+use futures::stream::{self, Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::{watch, Notify, OwnedMutexGuard};
+use tokio::task::JoinHandle;
+use tokio::time::{self, MissedTickBehavior};
+use tokio_stream::wrappers::WatchStream;
+
+use crate::control::SignalFailure;
+use crate::encoder::TraceContext;
+use crate::{
+    CrdtState, PatchRewrite, ServerSignalUpdate, SignalDescriptor, SseAck, SseClientId,
+    SseSubscription,
+};
+
+/// How many times in a row a producer can fail before it is stopped, by default.
+const DEFAULT_MAX_PRODUCER_FAILURES: u32 = 3;
+
+/// A registry of the open SSE connections of a server.
+///
+/// Streams are listed in the registry with [`ServerSentEvents::track`], which makes it possible
+/// to see which clients are connected, which signals they receive, and when they last received
+/// an update. This helps answering "why isn't this client updating" in production.
+///
+/// The registry also keeps the latest value sent for every signal, see [`SseRegistry::value`].
+/// Enable the `inspector` feature for routes serving the connections and the values as json.
+///
+/// Values can also be published through the registry, which forwards them to the streams
+/// created with `SseRegistry::events`, see [`SseRegistry::publish`]. This makes it possible to
+/// update a signal for every client from anywhere on the server, for example from a server
+/// function, see [`publish_sse_signal`](crate::publish_sse_signal).
+///
+/// ```
+/// use leptos_sse::{ServerSentEvents, SseRegistry};
+///
+/// # #[derive(Default, serde::Serialize)]
+/// # struct Count { value: i32 }
+/// let registry = SseRegistry::new();
+///
+/// // In the SSE handler
+/// let connection = registry.connect("user 42");
+/// let (sender, events) = ServerSentEvents::<()>::channel::<Count>("counter", 8).unwrap();
+/// let events = events.track(&connection);
+///
+/// assert_eq!(registry.connections()[0].subscriptions[0].name, "counter");
+/// ```
+///
+/// [`ServerSentEvents::track`]: crate::ServerSentEvents::track
+#[derive(Clone, Debug, Default)]
+pub struct SseRegistry {
+    inner: Arc<Mutex<RegistryState>>,
+}
+
+#[derive(Default)]
+struct RegistryState {
+    next_id: u64,
+    connections: BTreeMap<u64, ConnectionState>,
+    values: HashMap<String, Value>,
+    channels: HashMap<String, SignalChannel>,
+    /// Incremented after every published change, once all of its values are set.
+    commits: watch::Sender<u64>,
+    /// The number of changes published for every signal.
+    generations: HashMap<String, u64>,
+    /// Notified when a client acknowledges updates, or a connection is removed.
+    acks: Arc<Notify>,
+    keep_duplicates: bool,
+    rewrites: HashMap<String, PatchRewrite>,
+    projections: HashMap<String, SignalProjection>,
+    crdts: HashMap<String, CrdtState>,
+    derived: Vec<DerivedSignal>,
+    disconnect_hooks: Vec<DisconnectHook>,
+    producers: BTreeMap<String, ProducerInfo>,
+    max_producer_failures: Option<u32>,
+    /// The signals every client subscribed to at runtime, see [`SseRegistry::dynamic_events`].
+    dynamic: HashMap<String, watch::Sender<BTreeSet<String>>>,
+    /// The JSON Schemas of the signals, see [`SseRegistry::describe`].
+    schemas: HashMap<String, Value>,
+}
+
+/// A signal computed from other signals, see [`SseRegistry::derive`].
+struct DerivedSignal {
+    name: String,
+    inputs: Vec<String>,
+    compute: Box<DeriveFn>,
+}
+
+type DeriveFn = dyn Fn(&[Value]) -> Result<Value, serde_json::Error> + Send + Sync;
+
+type DisconnectHook = Arc<dyn Fn(&ConnectionInfo) + Send + Sync>;
+
+/// Projects the value of a signal for one connection, see [`SseRegistry::project`].
+#[derive(Clone)]
+pub(crate) struct SignalProjection(Arc<ProjectionFn>);
+
+type ProjectionFn = dyn Fn(&SseConnection, &mut Value) + Send + Sync;
+
+impl SignalProjection {
+    pub(crate) fn apply(&self, connection: &SseConnection, value: &mut Value) {
+        (self.0)(connection, value);
+    }
+}
+
+impl fmt::Debug for SignalProjection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SignalProjection").finish_non_exhaustive()
+    }
+}
+
+/// The published values of a signal, and the number of streams sending them.
+#[derive(Debug)]
+struct SignalChannel {
+    value: watch::Sender<Option<Value>>,
+    subscribers: watch::Sender<usize>,
+    /// Set when the producer of the signal stops after failing repeatedly.
+    failure: watch::Sender<Option<SignalFailure>>,
+    sent: Arc<SentCounters>,
+    /// The trace context of the last published value, see [`Transaction::trace_context`].
+    trace: Arc<Mutex<Option<Arc<str>>>>,
+    /// Held by the [`UpdateGuard`] of the signal, so that guarded writes don't overlap.
+    writer: Arc<tokio::sync::Mutex<()>>,
+}
+
+/// The updates sent by the streams subscribed to a signal, see [`SseRegistry::signal_stats`].
+#[derive(Debug)]
+struct SentCounters {
+    #[cfg_attr(not(feature = "metrics"), allow(dead_code))]
+    name: Arc<str>,
+    updates: AtomicU64,
+    bytes: AtomicU64,
+}
+
+impl fmt::Debug for RegistryState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RegistryState")
+            .field("connections", &self.connections)
+            .field("values", &self.values)
+            .field("channels", &self.channels)
+            .field("rewrites", &self.rewrites)
+            .field("projections", &self.projections)
+            .field("producers", &self.producers)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RegistryState {
+    /// Sets and publishes the values staged by a transaction.
+    ///
+    /// Values equal to the last one published for their signal are skipped, unless duplicates
+    /// are kept, and the transaction isn't published at all if none of its values changed.
+    /// The signals derived from the changed signals are recomputed and published with them.
+    /// Values of signals in merge mode are written to their state first. The published values,
+    /// derived ones included, carry the trace context `trace`.
+    fn commit(&mut self, staged: Vec<(String, Value)>, trace: Option<Arc<str>>) {
+        let keep_duplicates = self.keep_duplicates;
+        let mut changed = false;
+        let mut staged = staged;
+        // Derived signals may depend on each other, but a cycle must not loop forever
+        for _ in 0..=self.derived.len() {
+            if staged.is_empty() {
+                break;
+            }
+            let mut modified = Vec::new();
+            for (name, value) in staged {
+                let value = match self.crdts.get_mut(&name) {
+                    Some(state) => match state.write(&value) {
+                        true => state.value(),
+                        false => continue,
+                    },
+                    None => value,
+                };
+                let channel = self.channel(&name);
+                let sent = channel.value.send_if_modified(|current| {
+                    if !keep_duplicates && current.as_ref() == Some(&value) {
+                        return false;
+                    }
+                    *current = Some(value.clone());
+                    *channel.trace.lock().unwrap_or_else(|err| err.into_inner()) = trace.clone();
+                    true
+                });
+                if sent {
+                    *self.generations.entry(name.clone()).or_default() += 1;
+                    modified.push(name.clone());
+                }
+                self.values.insert(name, value);
+            }
+            changed |= !modified.is_empty();
+            staged = self.derive(&modified);
+        }
+        if changed {
+            self.commits.send_modify(|commits| *commits += 1);
+        }
+    }
+
+    /// Computes the signals derived from any of the signals `changed`.
+    fn derive(&self, changed: &[String]) -> Vec<(String, Value)> {
+        self.derived
+            .iter()
+            .filter(|derived| derived.inputs.iter().any(|input| changed.contains(input)))
+            .filter_map(|derived| {
+                let inputs: Vec<_> = derived
+                    .inputs
+                    .iter()
+                    .map(|input| self.values.get(input).cloned().unwrap_or_default())
+                    .collect();
+                let value = (derived.compute)(&inputs).ok()?;
+                Some((derived.name.clone(), value))
+            })
+            .collect()
+    }
+
+    /// Lists a new producer of the signal `name`, clearing the failures of the previous one.
+    fn start_producer(&mut self, name: &str) {
+        let info = ProducerInfo {
+            name: name.to_string(),
+            consecutive_failures: 0,
+            failures: 0,
+            last_error: None,
+            tripped: false,
+        };
+        self.producers.insert(name.to_string(), info);
+        self.channel(name).failure.send_replace(None);
+    }
+
+    fn producer_succeeded(&mut self, name: &str) {
+        if let Some(info) = self.producers.get_mut(name) {
+            info.consecutive_failures = 0;
+        }
+    }
+
+    /// Counts a failure of the producer of the signal `name`, returning whether it must stop.
+    fn producer_failed(&mut self, name: &str, err: &ProducerError) -> bool {
+        let limit = self
+            .max_producer_failures
+            .unwrap_or(DEFAULT_MAX_PRODUCER_FAILURES);
+        let Some(info) = self.producers.get_mut(name) else {
+            return false;
+        };
+        info.consecutive_failures += 1;
+        info.failures += 1;
+        info.last_error = Some(err.to_string());
+        if info.consecutive_failures < limit {
+            return false;
+        }
+        info.tripped = true;
+        let failure = SignalFailure {
+            name: name.to_string(),
+            error: err.to_string(),
+        };
+        self.channel(name).failure.send_replace(Some(failure));
+        true
+    }
+
+    /// Returns the channel of the signal `name`.
+    fn channel(&mut self, name: &str) -> &SignalChannel {
+        if !self.channels.contains_key(name) {
+            let (value, _) = watch::channel(self.values.get(name).cloned());
+            let (subscribers, _) = watch::channel(0);
+            let (failure, _) = watch::channel(None);
+            let sent = Arc::new(SentCounters {
+                name: name.into(),
+                updates: AtomicU64::new(0),
+                bytes: AtomicU64::new(0),
+            });
+            let channel = SignalChannel {
+                value,
+                subscribers,
+                failure,
+                sent,
+                trace: Arc::default(),
+                writer: Arc::default(),
+            };
+            self.channels.insert(name.to_string(), channel);
+        }
+        &self.channels[name]
+    }
+}
+
+/// Counts a stream as a subscriber of a signal until it is dropped, and the updates it sends.
+#[derive(Debug)]
+pub(crate) struct Subscriber {
+    subscribers: watch::Sender<usize>,
+    sent: Arc<SentCounters>,
+}
+
+impl Subscriber {
+    fn new(channel: &SignalChannel) -> Self {
+        Subscriber::count(&channel.subscribers, &channel.sent)
+    }
+
+    fn count(subscribers: &watch::Sender<usize>, sent: &Arc<SentCounters>) -> Self {
+        subscribers.send_modify(|count| *count += 1);
+        #[cfg(feature = "metrics")]
+        metrics::gauge!("leptos_sse_subscribers", "signal" => sent.name.to_string()).increment(1);
+        Subscriber {
+            subscribers: subscribers.clone(),
+            sent: sent.clone(),
+        }
+    }
+
+    /// Records an update of `size` bytes sent by the stream.
+    pub(crate) fn record_sent(&self, size: usize) {
+        self.sent.updates.fetch_add(1, Ordering::Relaxed);
+        self.sent.bytes.fetch_add(size as u64, Ordering::Relaxed);
+        #[cfg(feature = "metrics")]
+        {
+            let name = self.sent.name.to_string();
+            metrics::counter!("leptos_sse_updates_sent_total", "signal" => name.clone())
+                .increment(1);
+            metrics::histogram!("leptos_sse_update_bytes", "signal" => name).record(size as f64);
+        }
+    }
+}
+
+impl Clone for Subscriber {
+    fn clone(&self) -> Self {
+        Subscriber::count(&self.subscribers, &self.sent)
+    }
+}
+
+impl Drop for Subscriber {
+    fn drop(&mut self) {
+        self.subscribers.send_modify(|count| *count -= 1);
+        #[cfg(feature = "metrics")]
+        metrics::gauge!("leptos_sse_subscribers", "signal" => self.sent.name.to_string())
+            .decrement(1);
+    }
+}
+
+#[derive(Debug)]
+struct ConnectionState {
+    label: String,
+    client: Option<String>,
+    connected_at: u64,
+    subscriptions: Vec<Subscription>,
+    closed: Arc<AtomicBool>,
+}
+
+impl ConnectionState {
+    fn info(&self, id: u64) -> ConnectionInfo {
+        let subscriptions: Vec<_> = self
+            .subscriptions
+            .iter()
+            .map(|subscription| {
+                let stats = &subscription.stats;
+                let last_update = stats.last_update.load(Ordering::Relaxed);
+                let acked_seq = stats.acked_seq.load(Ordering::Relaxed);
+                SubscriptionInfo {
+                    name: subscription.name.clone(),
+                    updates: stats.updates.load(Ordering::Relaxed),
+                    queue_depth: stats
+                        .queued
+                        .as_ref()
+                        .map(|queued| queued.load(Ordering::Relaxed).max(0) as usize),
+                    last_update: (last_update != 0).then_some(last_update),
+                    sent_seq: stats.sent_seq.load(Ordering::Relaxed),
+                    acked_seq: (acked_seq != 0).then_some(acked_seq),
+                }
+            })
+            .collect();
+        let last_activity = subscriptions
+            .iter()
+            .filter_map(|subscription| subscription.last_update)
+            .max()
+            .unwrap_or(self.connected_at);
+        ConnectionInfo {
+            id,
+            label: self.label.clone(),
+            client: self.client.clone(),
+            connected_at: self.connected_at,
+            last_activity,
+            subscriptions,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Subscription {
+    name: String,
+    stats: Arc<SubscriptionStats>,
+}
+
+/// Statistics a tracked stream updates without locking the registry.
+#[derive(Debug, Default)]
+pub(crate) struct SubscriptionStats {
+    updates: AtomicU64,
+    last_update: AtomicU64,
+    queued: Option<Arc<AtomicIsize>>,
+    sent_seq: AtomicU64,
+    acked_seq: AtomicU64,
+    /// The generation of the signal once the stream sent its latest value.
+    sent_generation: AtomicU64,
+}
+
+impl SubscriptionStats {
+    pub(crate) fn record_update(&self, seq: u64) {
+        self.updates.fetch_add(1, Ordering::Relaxed);
+        self.last_update.store(now(), Ordering::Relaxed);
+        self.sent_seq.store(seq, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if the client hasn't acknowledged the last update sent.
+    fn is_behind(&self) -> bool {
+        self.acked_seq.load(Ordering::Relaxed) < self.sent_seq.load(Ordering::Relaxed)
+    }
+}
+
+impl SseRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        SseRegistry::default()
+    }
+
+    /// Registers a new connection with a label, such as a user or session id.
+    ///
+    /// The connection is listed until the last stream tracked with it, and the returned
+    /// [`SseConnection`], have been dropped, or until it is disconnected, see
+    /// [`SseRegistry::disconnect`].
+    pub fn connect(&self, label: impl Into<String>) -> SseConnection {
+        let label: Arc<str> = label.into().into();
+        let closed = Arc::new(AtomicBool::new(false));
+        let mut state = self.lock();
+        state.next_id += 1;
+        let id = state.next_id;
+        state.connections.insert(
+            id,
+            ConnectionState {
+                label: label.to_string(),
+                client: None,
+                connected_at: now(),
+                subscriptions: Vec::new(),
+                closed: closed.clone(),
+            },
+        );
+        SseConnection {
+            handle: Arc::new(ConnectionHandle {
+                id,
+                label,
+                registry: Arc::downgrade(&self.inner),
+                closed,
+            }),
+        }
+    }
+
+    /// Returns the open connections, oldest first.
+    pub fn connections(&self) -> Vec<ConnectionInfo> {
+        self.lock()
+            .connections
+            .iter()
+            .map(|(id, connection)| connection.info(*id))
+            .collect()
+    }
+
+    /// Records the updates a client acknowledged, see [`SseAck`].
+    ///
+    /// The acknowledgements apply to the connections of the client, see
+    /// [`SseConnection::set_client`]. Returns whether the client has any connection.
+    ///
+    /// ```ignore
+    /// async fn ack(State(registry): State<SseRegistry>, Json(ack): Json<SseAck>) {
+    ///     registry.ack(&ack);
+    /// }
+    /// ```
+    pub fn ack(&self, ack: &SseAck) -> bool {
+        let state = self.lock();
+        let connections = state
+            .connections
+            .values()
+            .filter(|connection| connection.client.as_deref() == Some(ack.client.as_str()));
+        let mut found = false;
+        for connection in connections {
+            found = true;
+            for subscription in &connection.subscriptions {
+                if let Some(seq) = ack.seqs.get(&subscription.name) {
+                    subscription
+                        .stats
+                        .acked_seq
+                        .fetch_max(*seq, Ordering::Relaxed);
+                }
+            }
+        }
+        // After the acknowledgements are recorded, so that `flush` sees them once woken
+        state.acks.notify_waiters();
+        found
+    }
+
+    /// Sets the signals a client subscribed to at runtime, see [`SseSubscription`].
+    ///
+    /// The streams returned by `SseRegistry::dynamic_events` for the client start sending the
+    /// signals it subscribed to, and stop sending the other ones. Returns whether the client
+    /// has such a stream open.
+    ///
+    /// ```ignore
+    /// async fn subscribe(State(registry): State<SseRegistry>, Json(sub): Json<SseSubscription>) {
+    ///     registry.update_subscription(&sub);
+    /// }
+    /// ```
+    pub fn update_subscription(&self, subscription: &SseSubscription) -> bool {
+        let mut state = self.lock();
+        // Forget the clients which are gone, since clients may come and go for a long time
+        state
+            .dynamic
+            .retain(|_, signals| signals.receiver_count() > 0);
+        let signals = state
+            .dynamic
+            .entry(subscription.client.clone())
+            .or_insert_with(|| watch::Sender::new(BTreeSet::new()));
+        signals.send_replace(subscription.signals.clone());
+        signals.receiver_count() > 0
+    }
+
+    /// Returns the connections which haven't acknowledged the last update of the signal `name`
+    /// sent to them, oldest first.
+    ///
+    /// This tells whether critical state actually reached the users. Only connections with a
+    /// client acknowledging updates are listed, see [`SseRegistry::ack`].
+    pub fn lagging(&self, name: &str) -> Vec<ConnectionInfo> {
+        self.lock()
+            .connections
+            .iter()
+            .filter(|(_, connection)| connection.client.is_some())
+            .filter(|(_, connection)| {
+                let mut subscriptions = connection.subscriptions.iter();
+                subscriptions
+                    .any(|subscription| subscription.name == name && subscription.stats.is_behind())
+            })
+            .map(|(id, connection)| connection.info(*id))
+            .collect()
+    }
+
+    /// Waits until every connection receiving the signal `name` has acknowledged its latest
+    /// value, or `timeout` elapsed.
+    ///
+    /// Returns `false` on timeout. This makes it possible to hold back a workflow until users
+    /// have actually seen a change:
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # async fn maintenance(registry: leptos_sse::SseRegistry) {
+    /// registry.publish("alert", "Maintenance starts in 5 minutes").unwrap();
+    /// if !registry.flush("alert", Duration::from_secs(30)).await {
+    ///     let lagging = registry.lagging("alert");
+    ///     // ...
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// Only the connections open when called are waited for, and only those with a client
+    /// acknowledging updates, see [`SseRegistry::ack`]. Connections closing in the meantime are
+    /// no longer waited for.
+    ///
+    /// The connections are checked again whenever one of their streams sends the latest value,
+    /// a client acknowledges updates, or a connection closes.
+    pub async fn flush(&self, name: &str, timeout: Duration) -> bool {
+        let (generation, subscriptions, acks) = {
+            let state = self.lock();
+            let generation = state.generations.get(name).copied().unwrap_or_default();
+            let subscriptions: Vec<_> = state
+                .connections
+                .values()
+                .filter(|connection| connection.client.is_some())
+                .flat_map(|connection| {
+                    let subscriptions = connection.subscriptions.iter();
+                    let subscriptions =
+                        subscriptions.filter(|subscription| subscription.name == name);
+                    subscriptions
+                        .map(|subscription| (connection.closed.clone(), subscription.stats.clone()))
+                })
+                .collect();
+            (generation, subscriptions, state.acks.clone())
+        };
+        let delivered = || {
+            subscriptions.iter().all(|(closed, stats)| {
+                closed.load(Ordering::Relaxed)
+                    || (stats.sent_generation.load(Ordering::Relaxed) >= generation
+                        && !stats.is_behind())
+            })
+        };
+        let wait = async {
+            loop {
+                let acked = acks.notified();
+                if delivered() {
+                    return;
+                }
+                acked.await;
+            }
+        };
+        time::timeout(timeout, wait).await.is_ok()
+    }
+
+    /// Removes the connection `id` from the registry, and ends the streams tracked with it.
+    ///
+    /// Returns whether the connection was open.
+    pub fn disconnect(&self, id: u64) -> bool {
+        disconnect(&self.inner, id)
+    }
+
+    /// Disconnects the connections which received no update for `max_idle`, and returns how
+    /// many were disconnected.
+    ///
+    /// Long-running servers should reap idle connections regularly, see
+    /// [`SseRegistry::spawn_reaper`], since clients can vanish without the server noticing.
+    pub fn reap_idle(&self, max_idle: Duration) -> usize {
+        let cutoff = now().saturating_sub(max_idle.as_millis() as u64);
+        let idle: Vec<_> = self
+            .lock()
+            .connections
+            .iter()
+            .filter(|(id, connection)| connection.info(**id).last_activity < cutoff)
+            .map(|(id, _)| *id)
+            .collect();
+        idle.into_iter()
+            .filter(|id| disconnect(&self.inner, *id))
+            .count()
+    }
+
+    /// Spawns a task reaping idle connections every `period`, see [`SseRegistry::reap_idle`].
+    ///
+    /// The task runs until aborted. This must be called within a Tokio runtime.
+    pub fn spawn_reaper(&self, period: Duration, max_idle: Duration) -> JoinHandle<()> {
+        let registry = self.clone();
+        tokio::spawn(async move {
+            let mut interval = time::interval(period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                registry.reap_idle(max_idle);
+            }
+        })
+    }
+
+    /// Calls `hook` with every connection leaving the registry, whether its handles were
+    /// dropped or it was disconnected.
+    ///
+    /// Hooks run on the thread removing the connection, and must not block.
+    pub fn on_disconnect(&self, hook: impl Fn(&ConnectionInfo) + Send + Sync + 'static) {
+        self.lock().disconnect_hooks.push(Arc::new(hook));
+    }
+
+    /// Returns the latest value published for a signal, see [`SseRegistry::publish`].
+    ///
+    /// This is useful for debugging clients which diverged from the server, and for consumers
+    /// which only need the latest value rather than a stream of patches.
+    pub fn value(&self, name: &str) -> Option<Value> {
+        self.lock().values.get(name).cloned()
+    }
+
+    /// Sets the value of a signal without publishing it, for example to seed it before any
+    /// stream has sent it.
+    pub fn set_value(&self, name: impl Into<String>, value: Value) {
+        self.lock().values.insert(name.into(), value);
+    }
+
+    /// Publishes a new value of the signal `name` to all of its streams.
+    ///
+    /// Streams created with `SseRegistry::events` send the value as a patch to their client.
+    /// Values published in quick succession may be coalesced, so that slow clients only
+    /// receive the latest one. A value equal to the last one published is skipped, see
+    /// [`SseRegistry::keep_duplicates`].
+    pub fn publish<T>(&self, name: &str, value: &T) -> Result<(), serde_json::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.transaction(|tx| tx.publish(name, value))
+    }
+
+    /// Updates the value of the signal `name` in place, and publishes it, see
+    /// [`SseRegistry::publish`].
+    ///
+    /// `update` receives the latest value, or `T::default()` if none has been published yet.
+    /// Concurrent updates are applied one after the other.
+    pub fn update<T>(
+        &self,
+        name: &str,
+        update: impl FnOnce(&mut T),
+    ) -> Result<(), serde_json::Error>
+    where
+        T: Default + Serialize + DeserializeOwned,
+    {
+        self.transaction(|tx| tx.update(name, update))
+    }
+
+    /// Returns the version of the signal `name`, which is the number of changes published for
+    /// it, see [`SseRegistry::compare_update`].
+    pub fn version(&self, name: &str) -> u64 {
+        self.lock()
+            .generations
+            .get(name)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Returns the latest value of the signal `name` together with its version, read at once.
+    pub fn value_with_version(&self, name: &str) -> (Option<Value>, u64) {
+        let state = self.lock();
+        let version = state.generations.get(name).copied().unwrap_or_default();
+        (state.values.get(name).cloned(), version)
+    }
+
+    /// Updates the value of the signal `name` in place, only if its version is still
+    /// `expected`, see [`SseRegistry::update`].
+    ///
+    /// This prevents lost updates from writers which read the value, do some work, then write
+    /// back a value computed from it: if another writer published in between, the update
+    /// fails with [`CompareUpdateError::Conflict`], and can be retried with the new value.
+    /// Returns the version after the update, which is unchanged if the value was.
+    ///
+    /// ```
+    /// use leptos_sse::{CompareUpdateError, SseRegistry};
+    ///
+    /// let registry = SseRegistry::new();
+    /// let (_, version) = registry.value_with_version("counter");
+    /// registry.publish("counter", &5).unwrap();
+    ///
+    /// let result = registry.compare_update("counter", version, |count: &mut u32| *count += 1);
+    /// assert!(matches!(result, Err(CompareUpdateError::Conflict { current: 1 })));
+    /// let version = registry.compare_update("counter", 1, |count: &mut u32| *count += 1);
+    /// assert_eq!(version.unwrap(), 2);
+    /// assert_eq!(registry.value("counter").unwrap(), 6);
+    /// ```
+    pub fn compare_update<T>(
+        &self,
+        name: &str,
+        expected: u64,
+        update: impl FnOnce(&mut T),
+    ) -> Result<u64, CompareUpdateError>
+    where
+        T: Default + Serialize + DeserializeOwned,
+    {
+        let mut state = self.lock();
+        let current = state.generations.get(name).copied().unwrap_or_default();
+        if current != expected {
+            return Err(CompareUpdateError::Conflict { current });
+        }
+        let mut tx = Transaction {
+            values: &state.values,
+            staged: Vec::new(),
+            trace: None,
+        };
+        tx.update(name, update)?;
+        let (staged, trace) = (tx.staged, tx.trace);
+        state.commit(staged, trace);
+        Ok(state.generations.get(name).copied().unwrap_or_default())
+    }
+
+    /// Sets whether values equal to the last one published for their signal are sent again.
+    ///
+    /// Duplicates are skipped by default, which saves bandwidth when a producer polls data
+    /// which rarely changes. Keep them if clients rely on receiving every value, such as to
+    /// keep a signal with a time to live from going stale, see
+    /// [`set_sse_ttl`](crate::set_sse_ttl).
+    pub fn keep_duplicates(&self, keep: bool) {
+        self.lock().keep_duplicates = keep;
+    }
+
+    /// Rewrites the patches of the signal `name` with `rewrite` in every stream created by this
+    /// registry from now on, see [`PatchRewrite`].
+    ///
+    /// ```
+    /// use leptos_sse::{PatchRewrite, SseRegistry};
+    ///
+    /// let registry = SseRegistry::new();
+    /// registry.rewrite("user", PatchRewrite::strip("/password_hash"));
+    /// ```
+    pub fn rewrite(&self, name: impl Into<String>, rewrite: PatchRewrite) {
+        self.lock().rewrites.insert(name.into(), rewrite);
+    }
+
+    /// Projects the values of the signal `name` for every connection with `projection`, such
+    /// as to remove the fields a user may not see.
+    ///
+    /// The projection runs on every value before it is diffed, so neither the patches nor the
+    /// snapshots sent to a connection contain what it removed. It applies to the streams tracked
+    /// with a connection of this registry from now on, see `ServerSentEvents::track`, and not to
+    /// batched streams, which aren't sent to a single connection.
+    ///
+    /// ```
+    /// use leptos_sse::SseRegistry;
+    ///
+    /// let registry = SseRegistry::new();
+    /// registry.project("employees", |connection, employees| {
+    ///     if !connection.label().starts_with("admin:") {
+    ///         for employee in employees.as_array_mut().into_iter().flatten() {
+    ///             employee.as_object_mut().map(|employee| employee.remove("salary"));
+    ///         }
+    ///     }
+    /// });
+    /// ```
+    pub fn project(
+        &self,
+        name: impl Into<String>,
+        projection: impl Fn(&SseConnection, &mut Value) + Send + Sync + 'static,
+    ) {
+        let projection = SignalProjection(Arc::new(projection));
+        self.lock().projections.insert(name.into(), projection);
+    }
+
+    /// Defines the signal `name`, computed from the signals `inputs` whenever one of them
+    /// changes.
+    ///
+    /// `compute` receives the values of the inputs in order, `null` for those which were never
+    /// published. The derived value is published with the values it was computed from, and
+    /// streams of the derived signal only send it when it actually changes, so clients can
+    /// subscribe to a cheap summary instead of the full documents:
+    ///
+    /// ```
+    /// use leptos_sse::SseRegistry;
+    ///
+    /// let registry = SseRegistry::new();
+    /// registry.derive("summary", ["orders", "users"], |inputs| {
+    ///     let count = |value: &serde_json::Value| value.as_array().map_or(0, Vec::len);
+    ///     (count(&inputs[0]), count(&inputs[1]))
+    /// });
+    ///
+    /// registry.publish("orders", &["apples", "pears"]).unwrap();
+    /// assert_eq!(registry.value("summary").unwrap(), serde_json::json!([2, 0]));
+    /// ```
+    ///
+    /// `compute` runs while the registry is locked, so it must not use the registry. Values
+    /// which can't be serialized are skipped.
+    pub fn derive<T, I>(
+        &self,
+        name: impl Into<String>,
+        inputs: I,
+        compute: impl Fn(&[Value]) -> T + Send + Sync + 'static,
+    ) where
+        T: Serialize,
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let derived = DerivedSignal {
+            name: name.into(),
+            inputs: inputs.into_iter().map(Into::into).collect(),
+            compute: Box::new(move |inputs| serde_json::to_value(compute(inputs))),
+        };
+        let mut state = self.lock();
+        let published: Vec<_> = derived
+            .inputs
+            .iter()
+            .filter(|input| state.values.contains_key(*input))
+            .cloned()
+            .collect();
+        state.derived.push(derived);
+        if !published.is_empty() {
+            let staged = state.derive(&published);
+            state.commit(staged, None);
+        }
+    }
+
+    /// Puts the signal `name` in merge mode, so that the values written by several replicas of
+    /// it converge instead of overwriting each other, see [`CrdtState`].
+    ///
+    /// Every server instance publishing the signal is a replica with a unique name. Values
+    /// published locally are written to the state of this replica, and the states of the other
+    /// replicas are merged with [`SseRegistry::merge`], so concurrent updates of different
+    /// fields are all kept:
+    ///
+    /// ```
+    /// use leptos_sse::SseRegistry;
+    /// use serde_json::json;
+    ///
+    /// let (east, west) = (SseRegistry::new(), SseRegistry::new());
+    /// east.crdt("settings", "east");
+    /// west.crdt("settings", "west");
+    ///
+    /// east.publish("settings", &json!({ "theme": "dark" })).unwrap();
+    /// west.publish("settings", &json!({ "language": "fr" })).unwrap();
+    ///
+    /// // Exchanged over the bus connecting the instances
+    /// east.merge("settings", &west.crdt_state("settings").unwrap());
+    /// west.merge("settings", &east.crdt_state("settings").unwrap());
+    ///
+    /// assert_eq!(east.value("settings"), west.value("settings"));
+    /// ```
+    ///
+    /// Values in merge mode must be json objects, others are skipped. The latest value of the
+    /// signal, if any, is written to the state of this replica.
+    pub fn crdt(&self, name: impl Into<String>, replica: impl Into<String>) {
+        let name = name.into();
+        let mut state = self.lock();
+        let mut crdt = CrdtState::new(replica.into());
+        if let Some(value) = state.values.get(&name) {
+            crdt.write(value);
+        }
+        state.crdts.insert(name, crdt);
+    }
+
+    /// Returns the state of the signal `name` in merge mode, to send to the other replicas.
+    pub fn crdt_state(&self, name: &str) -> Option<CrdtState> {
+        self.lock().crdts.get(name).cloned()
+    }
+
+    /// Merges the state of another replica of the signal `name`, and publishes the merged value
+    /// if it changed, see [`SseRegistry::crdt`].
+    ///
+    /// Returns `false` if the signal isn't in merge mode.
+    pub fn merge(&self, name: &str, other: &CrdtState) -> bool {
+        let mut state = self.lock();
+        let Some(crdt) = state.crdts.get_mut(name) else {
+            return false;
+        };
+        if crdt.merge(other) {
+            let value = crdt.value();
+            state.commit(vec![(name.to_string(), value)], None);
+        }
+        true
+    }
+
+    /// Returns the rewrite of the signal `name`, if any.
+    pub(crate) fn rewrite_of(&self, name: &str) -> Option<PatchRewrite> {
+        self.lock().rewrites.get(name).cloned()
+    }
+
+    /// Publishes the values of several signals at once.
+    ///
+    /// The values are only published if `transaction` succeeds, and streams created with
+    /// `SseRegistry::batched_events` send all of them in a single event, so that their clients
+    /// never see some of the values without the others.
+    ///
+    /// ```
+    /// use leptos_sse::SseRegistry;
+    ///
+    /// let registry = SseRegistry::new();
+    /// registry
+    ///     .transaction(|tx| {
+    ///         tx.update("list", |list: &mut Vec<String>| list.push("milk".to_string()))?;
+    ///         tx.update("count", |count: &mut usize| *count += 1)
+    ///     })
+    ///     .unwrap();
+    ///
+    /// assert_eq!(registry.value("count").unwrap(), 1);
+    /// ```
+    pub fn transaction<R>(
+        &self,
+        transaction: impl FnOnce(&mut Transaction<'_>) -> Result<R, serde_json::Error>,
+    ) -> Result<R, serde_json::Error> {
+        let mut state = self.lock();
+        let mut tx = Transaction {
+            values: &state.values,
+            staged: Vec::new(),
+            trace: None,
+        };
+        let result = transaction(&mut tx)?;
+        let (staged, trace) = (tx.staged, tx.trace);
+        state.commit(staged, trace);
+        Ok(result)
+    }
+
+    /// Returns the trace context of the last value published for the signal `name`.
+    pub(crate) fn trace_context(&self, name: &str) -> TraceContext {
+        let trace = self.lock().channel(name).trace.clone();
+        TraceContext::new(move || {
+            let trace = trace.lock().unwrap_or_else(|err| err.into_inner());
+            trace.as_deref().map(str::to_string)
+        })
+    }
+
+    /// Returns a stream of the values published for the signal `name`, starting with the latest
+    /// one.
+    ///
+    /// The stream counts as a subscriber of the signal until it is dropped.
+    pub fn subscribe(&self, name: &str) -> impl Stream<Item = Value> + Send + 'static {
+        let subscriber = self.subscriber(name);
+        self.values(name).map(move |value| {
+            let _ = &subscriber;
+            value
+        })
+    }
+
+    /// Waits until a value of the signal `name` satisfying `condition` has been published, and
+    /// returns it.
+    ///
+    /// The latest value is checked first, then the values published after it, read from the
+    /// same change stream as the SSE clients. This lets tests and orchestration code await a
+    /// state transition:
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use leptos_sse::SseRegistry;
+    ///
+    /// # #[derive(Default, serde::Serialize, serde::Deserialize)]
+    /// # struct Job { done: bool }
+    /// # async fn run(registry: SseRegistry) {
+    /// let job = tokio::time::timeout(
+    ///     Duration::from_secs(60),
+    ///     registry.wait_for("job:42", |job: &Job| job.done),
+    /// )
+    /// .await
+    /// .expect("job 42 timed out")
+    /// .unwrap();
+    /// # }
+    /// ```
+    ///
+    /// Like for the clients, values published in quick succession may be coalesced, so a state
+    /// which only lasts until the next value may be missed. Fails if a value doesn't
+    /// deserialize into `T`, and doesn't count as a subscriber of the signal.
+    pub async fn wait_for<T>(
+        &self,
+        name: &str,
+        mut condition: impl FnMut(&T) -> bool,
+    ) -> Result<T, serde_json::Error>
+    where
+        T: DeserializeOwned,
+    {
+        let mut values = std::pin::pin!(self.values(name));
+        while let Some(value) = values.next().await {
+            let value = T::deserialize(value)?;
+            if condition(&value) {
+                return Ok(value);
+            }
+        }
+        // The registry was dropped, so no value will ever be published
+        future::pending().await
+    }
+
+    /// Returns a receiver of the number of subscribers of the signal `name`, see
+    /// [`SseRegistry::spawn_interval`] for what counts as a subscriber.
+    ///
+    /// Producers can use it to pause expensive work while nobody is connected:
+    ///
+    /// ```
+    /// # async fn poll_database() -> i32 { 0 }
+    /// # async fn produce(registry: leptos_sse::SseRegistry) {
+    /// let mut subscribers = registry.watch_subscriber_count("orders");
+    /// loop {
+    ///     if subscribers.wait_for(|count| *count > 0).await.is_err() {
+    ///         break;
+    ///     }
+    ///     registry.publish("orders", &poll_database().await).unwrap();
+    /// #   break;
+    /// }
+    /// # }
+    /// ```
+    pub fn watch_subscriber_count(&self, name: &str) -> watch::Receiver<usize> {
+        self.lock().channel(name).subscribers.subscribe()
+    }
+
+    /// Spawns a task publishing the value returned by `producer` every `period`, while the
+    /// signal `name` has at least one subscriber.
+    ///
+    /// Subscribers are the streams created with `SseRegistry::events` or
+    /// [`SseRegistry::subscribe`], and the streams tracked with
+    /// [`ServerSentEvents::track`](crate::ServerSentEvents::track). The producer runs right
+    /// away when the first subscriber arrives, so that it receives a fresh value.
+    ///
+    /// The producer is guarded by a circuit breaker: a panic, or a value which can't be
+    /// serialized, counts as a failure and the task carries on. After 3 failures in a row, see
+    /// [`SseRegistry::max_producer_failures`], the producer is stopped and the task ends with
+    /// the last error. The streams created with `SseRegistry::events` then send a `failure`
+    /// event, which clients report in their diagnostics, and the producer is listed as tripped
+    /// by [`SseRegistry::producers`]. The task runs until aborted otherwise. This must be
+    /// called within a Tokio runtime.
+    ///
+    /// ```ignore
+    /// let metrics = registry.spawn_interval("metrics", Duration::from_secs(5), collect_metrics);
+    /// // On shutdown
+    /// metrics.abort();
+    /// ```
+    pub fn spawn_interval<T>(
+        &self,
+        name: &str,
+        period: Duration,
+        mut producer: impl FnMut() -> T + Send + 'static,
+    ) -> JoinHandle<Result<(), ProducerError>>
+    where
+        T: Serialize + 'static,
+    {
+        let sender = self.sender::<T>(name);
+        let mut subscribers = self.watch_subscriber_count(name);
+        self.lock().start_producer(name);
+        tokio::spawn(async move {
+            let mut interval = time::interval(period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                if *subscribers.borrow_and_update() == 0 {
+                    // The registry, and so the sender, lives as long as this task
+                    let _ = subscribers.wait_for(|count| *count > 0).await;
+                    interval.reset_immediately();
+                    continue;
+                }
+                let produced = panic::catch_unwind(AssertUnwindSafe(&mut producer))
+                    .map_err(|panic| ProducerError::Panicked(panic_message(&*panic)))
+                    .and_then(|value| sender.send(&value).map_err(ProducerError::Serialize));
+                let mut state = sender.registry.lock();
+                match produced {
+                    Ok(()) => state.producer_succeeded(&sender.name),
+                    Err(err) if state.producer_failed(&sender.name, &err) => return Err(err),
+                    Err(_) => {}
+                }
+            }
+        })
+    }
+
+    /// Sets how many times in a row a producer spawned with [`SseRegistry::spawn_interval`] can
+    /// fail before it is stopped, 3 by default.
+    pub fn max_producer_failures(&self, limit: u32) {
+        self.lock().max_producer_failures = Some(limit.max(1));
+    }
+
+    /// Returns the producers spawned with [`SseRegistry::spawn_interval`], with their failures.
+    ///
+    /// A tripped producer was stopped after failing too many times in a row, and its signal no
+    /// longer updates until a producer is spawned for it again.
+    pub fn producers(&self) -> Vec<ProducerInfo> {
+        self.lock().producers.values().cloned().collect()
+    }
+
+    /// Returns statistics of every signal, to find the chatty signals which are worth
+    /// coalescing or throttling.
+    ///
+    /// The updates sent are counted for the streams created with `SseRegistry::events`, and the
+    /// streams tracked with [`ServerSentEvents::track`](crate::ServerSentEvents::track), so a
+    /// value published once counts once per subscriber. With the `metrics` feature, the
+    /// updates, their sizes and the subscribers are also recorded with the [`metrics`] crate,
+    /// labeled with the name of the signal.
+    ///
+    /// ```
+    /// use leptos_sse::SseRegistry;
+    ///
+    /// let registry = SseRegistry::new();
+    /// registry.publish("counter", &1).unwrap();
+    ///
+    /// let stats = registry.signal_stats();
+    /// assert_eq!((stats[0].name.as_str(), stats[0].published), ("counter", 1));
+    /// ```
+    ///
+    /// [`metrics`]: https://docs.rs/metrics
+    pub fn signal_stats(&self) -> Vec<SignalStats> {
+        let state = self.lock();
+        let mut stats: Vec<_> = state
+            .channels
+            .iter()
+            .map(|(name, channel)| {
+                let updates_sent = channel.sent.updates.load(Ordering::Relaxed);
+                let bytes_sent = channel.sent.bytes.load(Ordering::Relaxed);
+                SignalStats {
+                    name: name.clone(),
+                    published: state.generations.get(name).copied().unwrap_or_default(),
+                    subscribers: *channel.subscribers.borrow(),
+                    updates_sent,
+                    bytes_sent,
+                    average_update_size: bytes_sent.checked_div(updates_sent).unwrap_or_default(),
+                }
+            })
+            .collect();
+        stats.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+        stats
+    }
+
+    /// Returns a stream of the failures of the producer of the signal `name`.
+    pub(crate) fn failures(
+        &self,
+        name: &str,
+    ) -> impl Stream<Item = SignalFailure> + Send + 'static {
+        let receiver = self.lock().channel(name).failure.subscribe();
+        WatchStream::from_changes(receiver).filter_map(future::ready)
+    }
+
+    /// Returns a stream of the values published for the signal `name`, without counting it as a
+    /// subscriber.
+    pub(crate) fn values(&self, name: &str) -> impl Stream<Item = Value> + Send + 'static {
+        let receiver = self.lock().channel(name).value.subscribe();
+        WatchStream::new(receiver).filter_map(future::ready)
+    }
+
+    /// Returns a stream of the values of the signals `names` paired with their name, counted as
+    /// a subscriber of every signal.
+    pub(crate) fn named_values(
+        &self,
+        names: &[Cow<'static, str>],
+    ) -> impl Stream<Item = (Cow<'static, str>, Value)> + Send + 'static {
+        stream::select_all(names.iter().map(|name| {
+            let name = name.clone();
+            let subscriber = self.subscriber(&name);
+            self.values(&name)
+                .map(move |value| {
+                    let _ = &subscriber;
+                    (name.clone(), value)
+                })
+                .boxed()
+        }))
+    }
+
+    /// Returns a stream of the updates of the signals `names`, with the updates of every
+    /// transaction in a single batch.
+    pub(crate) fn batches(
+        &self,
+        names: Vec<Cow<'static, str>>,
+    ) -> impl Stream<Item = UpdateBatch> + Send + 'static {
+        let mut commits = self.lock().commits.subscribe();
+        commits.mark_changed();
+        let signals: Vec<_> = names
+            .into_iter()
+            .map(|name| BatchedSignal {
+                _subscriber: self.subscriber(&name),
+                rewrite: self.rewrite_of(&name),
+                name,
+                value: Value::Null,
+                seq: 0,
+            })
+            .collect();
+        let registry = self.clone();
+        stream::unfold((signals, commits), move |(mut signals, mut commits)| {
+            let registry = registry.clone();
+            async move {
+                loop {
+                    commits.changed().await.ok()?;
+                    let state = registry.lock();
+                    let updates: Vec<_> = signals
+                        .iter_mut()
+                        .filter_map(|signal| signal.update(state.values.get(&*signal.name)?))
+                        .collect();
+                    if !updates.is_empty() {
+                        return Some((UpdateBatch(updates), (signals, commits)));
+                    }
+                }
+            }
+        })
+    }
+
+    /// Sets the JSON Schema of the values of the signal `name`, listed by the discovery route.
+    ///
+    /// The schema is only informative, for clients and tools rendering the signals, see
+    /// [`SseRegistry::signal_descriptors`]. Values aren't validated against it.
+    pub fn describe(&self, name: impl Into<String>, schema: Value) {
+        self.lock().schemas.insert(name.into(), schema);
+    }
+
+    /// Sets the JSON Schema of the signal `name` to the one generated for `T`, see
+    /// [`SseRegistry::describe`].
+    ///
+    /// ```
+    /// use leptos_sse::SseRegistry;
+    /// use schemars::JsonSchema;
+    /// use serde::Serialize;
+    ///
+    /// #[derive(Serialize, JsonSchema)]
+    /// struct Count {
+    ///     value: i32,
+    /// }
+    ///
+    /// let registry = SseRegistry::new();
+    /// registry.describe_type::<Count>("counter");
+    /// let schema = registry.signal_schema("counter").unwrap();
+    /// assert_eq!(schema["properties"]["value"]["type"], "integer");
+    /// ```
+    #[cfg(feature = "schemars")]
+    pub fn describe_type<T>(&self, name: impl Into<String>)
+    where
+        T: schemars::JsonSchema,
+    {
+        self.describe(name, schemars::schema_for!(T).to_value());
+    }
+
+    /// Returns the JSON Schema of the signal `name`, if it was described.
+    pub fn signal_schema(&self, name: &str) -> Option<Value> {
+        self.lock().schemas.get(name).cloned()
+    }
+
+    /// Returns the signals which have been published or described, sorted by name.
+    ///
+    /// This is what the discovery route serves, for clients to find out which signals exist at
+    /// runtime, see [`SignalDescriptor`].
+    ///
+    /// ```
+    /// use leptos_sse::SseRegistry;
+    /// use serde_json::json;
+    ///
+    /// let registry = SseRegistry::new();
+    /// registry.publish("counter", &1).unwrap();
+    /// registry.describe("orders", json!({ "type": "array" }));
+    ///
+    /// let names: Vec<_> = registry.signal_descriptors().into_iter().map(|s| s.name).collect();
+    /// assert_eq!(names, ["counter", "orders"]);
+    /// ```
+    pub fn signal_descriptors(&self) -> Vec<SignalDescriptor> {
+        let state = self.lock();
+        let names: BTreeSet<&String> = state.values.keys().chain(state.schemas.keys()).collect();
+        names
+            .into_iter()
+            .map(|name| SignalDescriptor {
+                name: name.clone(),
+                schema: state.schemas.get(name).cloned(),
+            })
+            .collect()
+    }
+
+    /// Merges the streams opened with `open` for the signals `client` subscribed to, see
+    /// [`SseRegistry::update_subscription`].
+    pub(crate) fn dynamic<S, F>(&self, client: &SseClientId, open: F) -> DynamicStreams<S, F>
+    where
+        F: FnMut(&str) -> S,
+    {
+        let (names, unnamed) = match &client.0 {
+            Some(client) => {
+                let mut state = self.lock();
+                let signals = state
+                    .dynamic
+                    .entry(client.clone())
+                    .or_insert_with(|| watch::Sender::new(BTreeSet::new()));
+                (signals.subscribe(), None)
+            }
+            // Without an id, the client can't subscribe to anything, and the stream stays idle
+            None => {
+                let signals = watch::Sender::new(BTreeSet::new());
+                (signals.subscribe(), Some(signals))
+            }
+        };
+        DynamicStreams {
+            names: Some(WatchStream::new(names)),
+            _unnamed: unnamed,
+            open,
+            streams: Vec::new(),
+            next: 0,
+        }
+    }
+
+    /// Counts a new subscriber of the signal `name`.
+    pub(crate) fn subscriber(&self, name: &str) -> Subscriber {
+        Subscriber::new(self.lock().channel(name))
+    }
+
+    /// Returns a handle publishing values of the signal `name`, see [`SignalSender`].
+    pub fn sender<T>(&self, name: impl Into<Arc<str>>) -> SignalSender<T> {
+        SignalSender {
+            registry: self.clone(),
+            name: name.into(),
+            marker: PhantomData,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, RegistryState> {
+        lock(&self.inner)
+    }
+}
+
+/// The streams of the signals a client subscribed to, opened and dropped as its subscriptions
+/// change, see [`SseRegistry::update_subscription`].
+///
+/// The streams are polled in turn, so that a busy signal can't hold back the others. A stream
+/// which ends isn't opened again until the client subscribes to its signal again.
+pub(crate) struct DynamicStreams<S, F> {
+    names: Option<WatchStream<BTreeSet<String>>>,
+    _unnamed: Option<watch::Sender<BTreeSet<String>>>,
+    open: F,
+    streams: Vec<(String, Option<Pin<Box<S>>>)>,
+    next: usize,
+}
+
+impl<S, F> Stream for DynamicStreams<S, F>
+where
+    S: Stream,
+    F: FnMut(&str) -> S + Unpin,
+{
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<S::Item>> {
+        let this = &mut *self;
+        while let Some(names) = &mut this.names {
+            match names.poll_next_unpin(cx) {
+                Poll::Ready(Some(names)) => {
+                    this.streams.retain(|(name, _)| names.contains(name));
+                    for name in names {
+                        if !this.streams.iter().any(|(open, _)| *open == name) {
+                            let stream = Box::pin((this.open)(&name));
+                            this.streams.push((name, Some(stream)));
+                        }
+                    }
+                }
+                // The registry is gone, the open streams carry on until they end
+                Poll::Ready(None) => this.names = None,
+                Poll::Pending => break,
+            }
+        }
+
+        let count = this.streams.len();
+        for offset in 0..count {
+            let index = (this.next + offset) % count;
+            let Some(stream) = &mut this.streams[index].1 else {
+                continue;
+            };
+            match stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    this.next = (index + 1) % count;
+                    return Poll::Ready(Some(item));
+                }
+                Poll::Ready(None) => this.streams[index].1 = None,
+                Poll::Pending => {}
+            }
+        }
+        if this.names.is_none() && this.streams.iter().all(|(_, stream)| stream.is_none()) {
+            return Poll::Ready(None);
+        }
+        Poll::Pending
+    }
+}
+
+/// The updates of several signals, to be applied together.
+///
+/// Converts into an SSE event, which is a plain update for a single signal, or a `batch` event
+/// otherwise, see [`SseRegistry::transaction`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UpdateBatch(pub Vec<ServerSignalUpdate>);
+
+/// The values staged by [`SseRegistry::transaction`].
+#[derive(Debug)]
+pub struct Transaction<'a> {
+    values: &'a HashMap<String, Value>,
+    staged: Vec<(String, Value)>,
+    trace: Option<Arc<str>>,
+}
+
+impl Transaction<'_> {
+    /// Stages a new value of the signal `name`, see [`SseRegistry::publish`].
+    pub fn publish<T>(&mut self, name: &str, value: &T) -> Result<(), serde_json::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        let value = serde_json::to_value(value)?;
+        self.stage(name, value);
+        Ok(())
+    }
+
+    /// Updates the value of the signal `name` in place, see [`SseRegistry::update`].
+    ///
+    /// `update` receives the value staged by this transaction, if any.
+    pub fn update<T>(
+        &mut self,
+        name: &str,
+        update: impl FnOnce(&mut T),
+    ) -> Result<(), serde_json::Error>
+    where
+        T: Default + Serialize + DeserializeOwned,
+    {
+        let mut value = match self.value(name) {
+            Some(value) => T::deserialize(value)?,
+            None => T::default(),
+        };
+        update(&mut value);
+        self.publish(name, &value)
+    }
+
+    /// Attaches a W3C trace context, as a `traceparent` header value, to the updates of the
+    /// values published by this transaction.
+    ///
+    /// The streams created with `SseRegistry::events` send it with their next update, see
+    /// [`ServerSignalUpdate::with_trace_context`], so that a state change can be traced from
+    /// the request which published it to the browser:
+    ///
+    /// ```
+    /// use leptos_sse::SseRegistry;
+    ///
+    /// # let traceparent = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+    /// let registry = SseRegistry::new();
+    /// registry
+    ///     .transaction(|tx| {
+    ///         tx.trace_context(traceparent);
+    ///         tx.publish("counter", &1)
+    ///     })
+    ///     .unwrap();
+    /// ```
+    pub fn trace_context(&mut self, traceparent: impl Into<String>) {
+        self.trace = Some(traceparent.into().into());
+    }
+
+    /// Returns the value of the signal `name`, as staged by this transaction.
+    pub fn value(&self, name: &str) -> Option<&Value> {
+        match self.staged.iter().find(|(staged, _)| staged == name) {
+            Some((_, value)) => Some(value),
+            None => self.values.get(name),
+        }
+    }
+
+    fn stage(&mut self, name: &str, value: Value) {
+        match self.staged.iter_mut().find(|(staged, _)| staged == name) {
+            Some((_, staged)) => *staged = value,
+            None => self.staged.push((name.to_string(), value)),
+        }
+    }
+}
+
+/// A signal of a batched stream, and the value it last sent.
+struct BatchedSignal {
+    name: Cow<'static, str>,
+    value: Value,
+    seq: u64,
+    rewrite: Option<PatchRewrite>,
+    _subscriber: Subscriber,
+}
+
+impl BatchedSignal {
+    /// Returns the update to `value`, unless it was already sent.
+    ///
+    /// The first update replaces the whole document, like in a versioned stream.
+    fn update(&mut self, value: &Value) -> Option<ServerSignalUpdate> {
+        if self.seq != 0 && *value == self.value {
+            return None;
+        }
+        let mut update =
+            ServerSignalUpdate::new_from_json::<Value>(self.name.clone(), &self.value, value);
+        if let Some(rewrite) = &self.rewrite {
+            rewrite.apply(&mut update.patch);
+        }
+        self.seq += 1;
+        self.value.clone_from(value);
+        Some(update.with_seq(self.seq))
+    }
+}
+
+/// A handle publishing the values of a signal through an [`SseRegistry`].
+///
+/// The handle is cheap to clone and doesn't depend on the web framework, so it can be moved
+/// into background tasks or handed to other crates.
+///
+/// ```
+/// use leptos_sse::SseRegistry;
+///
+/// # #[derive(Default, serde::Serialize, serde::Deserialize)]
+/// # struct Count { value: i32 }
+/// let registry = SseRegistry::new();
+/// let counter = registry.sender::<Count>("counter");
+///
+/// std::thread::spawn(move || {
+///     counter.update(|count| count.value += 1).unwrap();
+/// })
+/// .join()
+/// .unwrap();
+///
+/// assert_eq!(registry.value("counter").unwrap()["value"], 1);
+/// ```
+pub struct SignalSender<T> {
+    registry: SseRegistry,
+    name: Arc<str>,
+    marker: PhantomData<fn(T)>,
+}
+
+impl<T> SignalSender<T> {
+    /// Returns the name of the signal.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Publishes a new value of the signal, see [`SseRegistry::publish`].
+    pub fn send(&self, value: &T) -> Result<(), serde_json::Error>
+    where
+        T: Serialize,
+    {
+        self.registry.publish(&self.name, value)
+    }
+
+    /// Updates the value of the signal in place, see [`SseRegistry::update`].
+    pub fn update(&self, update: impl FnOnce(&mut T)) -> Result<(), serde_json::Error>
+    where
+        T: Default + Serialize + DeserializeOwned,
+    {
+        self.registry.update(&self.name, update)
+    }
+
+    /// Returns a guard holding the latest value of the signal, which is published when the
+    /// guard is dropped, see [`UpdateGuard`].
+    ///
+    /// The value is `T::default()` if none has been published yet. Waits for the guard of any
+    /// other writer of the signal to be dropped first, so that guarded writes apply one after
+    /// the other, even across `.await` points:
+    ///
+    /// ```
+    /// use leptos_sse::SseRegistry;
+    ///
+    /// # #[derive(Default, serde::Serialize, serde::Deserialize)]
+    /// # struct Cart { items: Vec<String> }
+    /// # async fn fetch_item() -> String { "milk".to_string() }
+    /// # async fn add_item(registry: SseRegistry) {
+    /// let cart = registry.sender::<Cart>("cart");
+    /// let mut guard = cart.write().await.unwrap();
+    /// guard.items.push(fetch_item().await);
+    /// // Published when the guard is dropped
+    /// # }
+    /// ```
+    pub async fn write(&self) -> Result<UpdateGuard<T>, serde_json::Error>
+    where
+        T: Default + Serialize + DeserializeOwned,
+    {
+        let writer = self.registry.lock().channel(&self.name).writer.clone();
+        let permit = writer.lock_owned().await;
+        let snapshot = self.registry.value(&self.name);
+        let value = match &snapshot {
+            Some(value) => T::deserialize(value)?,
+            None => T::default(),
+        };
+        Ok(UpdateGuard {
+            sender: self.clone(),
+            value,
+            snapshot,
+            committed: false,
+            _permit: permit,
+        })
+    }
+}
+
+impl<T> Clone for SignalSender<T> {
+    fn clone(&self) -> Self {
+        SignalSender {
+            registry: self.registry.clone(),
+            name: self.name.clone(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> fmt::Debug for SignalSender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SignalSender")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The value of a signal being modified, which is published when the guard is dropped, see
+/// [`SignalSender::write`].
+///
+/// The guard dereferences to the value. When it is dropped, the value is published if it
+/// differs from the one the guard started from, so a modified value can't be left unsent,
+/// unless it is dropped while unwinding from a panic.
+/// Errors serializing the value are lost when it is dropped, use [`UpdateGuard::commit`] to
+/// handle them. Values published for the signal by other means while the guard is held may
+/// be overwritten.
+pub struct UpdateGuard<T>
+where
+    T: Serialize,
+{
+    sender: SignalSender<T>,
+    value: T,
+    /// The value the guard started from, if any.
+    snapshot: Option<Value>,
+    committed: bool,
+    _permit: OwnedMutexGuard<()>,
+}
+
+impl<T> UpdateGuard<T>
+where
+    T: Serialize,
+{
+    /// Publishes the value if it changed, and releases the guard.
+    pub fn commit(mut self) -> Result<(), serde_json::Error> {
+        self.committed = true;
+        self.publish()
+    }
+
+    fn publish(&self) -> Result<(), serde_json::Error> {
+        let value = serde_json::to_value(&self.value)?;
+        if self.snapshot.as_ref() == Some(&value) {
+            return Ok(());
+        }
+        self.sender.registry.transaction(|tx| {
+            tx.stage(&self.sender.name, value);
+            Ok(())
+        })
+    }
+}
+
+impl<T> Deref for UpdateGuard<T>
+where
+    T: Serialize,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for UpdateGuard<T>
+where
+    T: Serialize,
+{
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T> Drop for UpdateGuard<T>
+where
+    T: Serialize,
+{
+    fn drop(&mut self) {
+        // A panic may have left the value half modified
+        if !self.committed && !std::thread::panicking() {
+            let _ = self.publish();
+        }
+    }
+}
+
+impl<T> fmt::Debug for UpdateGuard<T>
+where
+    T: Serialize + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UpdateGuard")
+            .field("name", &self.sender.name)
+            .field("value", &self.value)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A connection listed in an [`SseRegistry`].
+///
+/// Cloning the connection is cheap, and all clones refer to the same connection.
+#[derive(Clone, Debug)]
+pub struct SseConnection {
+    handle: Arc<ConnectionHandle>,
+}
+
+#[derive(Debug)]
+struct ConnectionHandle {
+    id: u64,
+    label: Arc<str>,
+    registry: Weak<Mutex<RegistryState>>,
+    closed: Arc<AtomicBool>,
+}
+
+impl Drop for ConnectionHandle {
+    fn drop(&mut self) {
+        if let Some(registry) = self.registry.upgrade() {
+            disconnect(&registry, self.id);
+        }
+    }
+}
+
+impl SseConnection {
+    /// Returns the id of the connection within its registry.
+    pub fn id(&self) -> u64 {
+        self.handle.id
+    }
+
+    /// Returns the label passed to [`SseRegistry::connect`], such as the user of the connection.
+    pub fn label(&self) -> &str {
+        &self.handle.label
+    }
+
+    /// Sets the client of the connection, whose acknowledgements are recorded for it, see
+    /// [`SseRegistry::ack`].
+    pub fn set_client(&self, client: SseClientId) {
+        if let Some(registry) = self.handle.registry.upgrade() {
+            if let Some(connection) = lock(&registry).connections.get_mut(&self.handle.id) {
+                connection.client = client.0;
+            }
+        }
+    }
+
+    /// Removes the connection from its registry, and ends the streams tracked with it.
+    pub fn disconnect(&self) {
+        if let Some(registry) = self.handle.registry.upgrade() {
+            disconnect(&registry, self.handle.id);
+        }
+    }
+
+    /// Returns whether the connection has been disconnected, see [`SseRegistry::disconnect`].
+    pub fn is_closed(&self) -> bool {
+        self.handle.closed.load(Ordering::Relaxed)
+    }
+
+    /// Records that a stream of this connection sent `value` for a signal.
+    ///
+    /// Only publishing sets the values of the registry, since the stream may have sent a value
+    /// which was already replaced by a newer one.
+    pub(crate) fn record_value(&self, name: &str, value: &Value, stats: &SubscriptionStats) {
+        if let Some(registry) = self.handle.registry.upgrade() {
+            let state = lock(&registry);
+            if state.values.get(name) == Some(value) {
+                let generation = state.generations.get(name).copied().unwrap_or_default();
+                stats.sent_generation.store(generation, Ordering::Relaxed);
+                // A flush may now only wait for the acknowledgement
+                state.acks.notify_waiters();
+            }
+        }
+    }
+
+    /// Returns the projection of the signal `name` for the connections of its registry, if any.
+    pub(crate) fn projection(&self, name: &str) -> Option<SignalProjection> {
+        let registry = self.handle.registry.upgrade()?;
+        let projection = lock(&registry).projections.get(name).cloned();
+        projection
+    }
+
+    /// Counts a new subscriber of the signal `name`, unless the registry has been dropped.
+    pub(crate) fn subscriber(&self, name: &str) -> Option<Subscriber> {
+        let registry = self.handle.registry.upgrade()?;
+        let subscriber = Subscriber::new(lock(&registry).channel(name));
+        Some(subscriber)
+    }
+
+    /// Lists a signal stream under this connection.
+    pub(crate) fn subscribe(
+        &self,
+        name: &str,
+        queued: Option<Arc<AtomicIsize>>,
+    ) -> Arc<SubscriptionStats> {
+        let stats = Arc::new(SubscriptionStats {
+            queued,
+            ..SubscriptionStats::default()
+        });
+        if let Some(registry) = self.handle.registry.upgrade() {
+            if let Some(connection) = lock(&registry).connections.get_mut(&self.handle.id) {
+                connection.subscriptions.push(Subscription {
+                    name: name.to_string(),
+                    stats: stats.clone(),
+                });
+            }
+        }
+        stats
+    }
+}
+
+/// A connection listed in an [`SseRegistry`]. Times are milliseconds since the unix epoch.
+#[derive(Clone, Debug, Serialize)]
+#[non_exhaustive]
+pub struct ConnectionInfo {
+    /// The id of the connection.
+    pub id: u64,
+    /// The label passed to [`SseRegistry::connect`].
+    pub label: String,
+    /// The client acknowledging updates, see [`SseConnection::set_client`].
+    pub client: Option<String>,
+    /// When the connection was registered.
+    pub connected_at: u64,
+    /// When the connection last received an update, or when it was registered.
+    pub last_activity: u64,
+    /// The signal streams of the connection.
+    pub subscriptions: Vec<SubscriptionInfo>,
+}
+
+/// A signal stream of a connection. Times are milliseconds since the unix epoch.
+#[derive(Clone, Debug, Serialize)]
+#[non_exhaustive]
+pub struct SubscriptionInfo {
+    /// The name of the signal.
+    pub name: String,
+    /// The number of updates sent.
+    pub updates: u64,
+    /// The number of values waiting in the channel, for streams created with
+    /// [`ServerSentEvents::channel`](crate::ServerSentEvents::channel).
+    pub queue_depth: Option<usize>,
+    /// When the last update was sent.
+    pub last_update: Option<u64>,
+    /// The sequence number of the last update sent.
+    pub sent_seq: u64,
+    /// The sequence number of the last update the client acknowledged, see
+    /// [`SseRegistry::ack`].
+    pub acked_seq: Option<u64>,
+}
+
+/// Statistics of a signal, see [`SseRegistry::signal_stats`].
+#[derive(Clone, Debug, Serialize)]
+#[non_exhaustive]
+pub struct SignalStats {
+    /// The name of the signal.
+    pub name: String,
+    /// The number of changed values published through the registry.
+    pub published: u64,
+    /// The number of streams subscribed to the signal.
+    pub subscribers: usize,
+    /// The number of updates sent, to all subscribers.
+    pub updates_sent: u64,
+    /// The size of the updates sent, in bytes.
+    pub bytes_sent: u64,
+    /// The average size of an update, in bytes.
+    pub average_update_size: u64,
+}
+
+/// A producer spawned with [`SseRegistry::spawn_interval`].
+#[derive(Clone, Debug, Serialize)]
+#[non_exhaustive]
+pub struct ProducerInfo {
+    /// The name of the signal.
+    pub name: String,
+    /// The number of times in a row the producer failed.
+    pub consecutive_failures: u32,
+    /// The number of times the producer failed.
+    pub failures: u64,
+    /// The error of the last failure.
+    pub last_error: Option<String>,
+    /// Whether the producer was stopped after failing too many times in a row.
+    pub tripped: bool,
+}
+
+/// A failure of a producer spawned with [`SseRegistry::spawn_interval`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ProducerError {
+    /// The producer panicked, with the panic message.
+    Panicked(String),
+    /// The value returned by the producer couldn't be serialized.
+    Serialize(serde_json::Error),
+}
+
+impl fmt::Display for ProducerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProducerError::Panicked(message) => write!(f, "producer panicked: {message}"),
+            ProducerError::Serialize(err) => write!(f, "serialization failed: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for ProducerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ProducerError::Panicked(_) => None,
+            ProducerError::Serialize(err) => Some(err),
+        }
+    }
+}
+
+/// The failure of [`SseRegistry::compare_update`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum CompareUpdateError {
+    /// The signal was updated since the expected version, and is now at version `current`.
+    Conflict {
+        /// The current version of the signal.
+        current: u64,
+    },
+    /// The value couldn't be converted from or to json.
+    Json(serde_json::Error),
+}
+
+impl From<serde_json::Error> for CompareUpdateError {
+    fn from(err: serde_json::Error) -> Self {
+        CompareUpdateError::Json(err)
+    }
+}
+
+impl fmt::Display for CompareUpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompareUpdateError::Conflict { current } => {
+                write!(
+                    f,
+                    "the signal was updated concurrently, to version {current}"
+                )
+            }
+            CompareUpdateError::Json(err) => write!(f, "json conversion failed: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for CompareUpdateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CompareUpdateError::Conflict { .. } => None,
+            CompareUpdateError::Json(err) => Some(err),
+        }
+    }
+}
+
+/// Returns the message of a panic, which is usually a string.
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    match panic.downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => panic
+            .downcast_ref::<String>()
+            .cloned()
+            .unwrap_or_else(|| "unknown panic".to_string()),
+    }
+}
+
+/// Removes a connection and runs the disconnect hooks, outside the lock.
+fn disconnect(registry: &Mutex<RegistryState>, id: u64) -> bool {
+    let (info, hooks) = {
+        let mut state = lock(registry);
+        let Some(connection) = state.connections.remove(&id) else {
+            return false;
+        };
+        connection.closed.store(true, Ordering::Relaxed);
+        state.acks.notify_waiters();
+        (connection.info(id), state.disconnect_hooks.clone())
+    };
+    for hook in hooks {
+        hook(&info);
+    }
+    true
+}
+
+/// Serializes updates, which only contain strings and json values, so this can't fail.
+pub(crate) fn to_json(updates: &impl Serialize) -> String {
+    serde_json::to_string(updates).expect("updates serialize to json")
+}
+
+/// Locks the registry, ignoring poisoning since the state stays consistent.
+fn lock(registry: &Mutex<RegistryState>) -> MutexGuard<'_, RegistryState> {
+    registry.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Returns the time in milliseconds since the Unix epoch.
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis() as u64)
+}
@@ -0,0 +1,107 @@
+use std::borrow::Cow;
+use std::future::{Future, IntoFuture};
+use std::pin::Pin;
+
+use futures::StreamExt;
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{create_sse_signal, use_sse_updates};
+
+/// A server signal which can be awaited until its first update has arrived.
+///
+/// Created with [`create_sse_resource`].
+pub struct SseResource<T: 'static> {
+    signal: ReadSignal<T>,
+    ready: LocalResource<()>,
+}
+
+impl<T: 'static> Clone for SseResource<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: 'static> Copy for SseResource<T> {}
+
+impl<T> SseResource<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    /// Returns the current value, or `None` if no update has arrived yet.
+    ///
+    /// Reading this inside `<Suspense>` suspends until the first update has arrived.
+    pub fn get(&self) -> Option<T> {
+        self.ready.get().map(|_| self.signal.get())
+    }
+
+    /// Returns the underlying signal, which is `T::default()` until the first update.
+    pub fn signal(&self) -> ReadSignal<T> {
+        self.signal
+    }
+}
+
+impl<T> IntoFuture for SseResource<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    type Output = T;
+    type IntoFuture = Pin<Box<dyn Future<Output = T> + Send>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(async move {
+            self.ready.await;
+            self.signal.get()
+        })
+    }
+}
+
+/// Creates a server signal which suspends until its first update has arrived.
+///
+/// This allows using `<Suspense>` fallbacks instead of rendering `T::default()` placeholders
+/// while waiting for the server. The resource never resolves during server-side rendering, so
+/// the fallback is rendered on the server.
+///
+/// Only updates received after the resource is created count as its first update.
+///
+/// # Example
+///
+/// ```
+/// use leptos::prelude::*;
+/// use leptos_sse::create_sse_resource;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Clone, Default, Serialize, Deserialize)]
+/// pub struct Count {
+///     pub value: i32,
+/// }
+///
+/// #[component]
+/// pub fn App() -> impl IntoView {
+///     let count = create_sse_resource::<Count>("counter");
+///
+///     view! {
+///         <Suspense fallback=|| "Loading...">
+///             <h1>"Count: " {move || Suspend::new(async move { count.await.value.to_string() })}</h1>
+///         </Suspense>
+///     }
+/// }
+/// ```
+pub fn create_sse_resource<T>(name: impl Into<Cow<'static, str>>) -> SseResource<T>
+where
+    T: Default + Serialize + for<'de> Deserialize<'de> + Send + Sync + 'static,
+{
+    let name = name.into();
+    let signal = create_sse_signal::<T>(name.clone());
+    let ready = LocalResource::new(move || first_update(name.clone()));
+    SseResource { signal, ready }
+}
+
+async fn first_update(name: Cow<'static, str>) {
+    let mut updates = std::pin::pin!(use_sse_updates());
+    while let Some(update) = updates.next().await {
+        if update.name() == name {
+            break;
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use std::fmt;
+use std::sync::Arc;
+
+use json_patch::jsonptr::{Pointer, PointerBuf};
+use json_patch::{Patch, PatchOperation};
+use serde_json::Value;
+
+/// A hook transforming the json patches of a signal before they are sent.
+///
+/// This is an escape hatch for schema evolution and redaction, such as renaming fields for
+/// older clients or keeping private fields from ever leaving the server:
+///
+/// ```
+/// use leptos_sse::PatchRewrite;
+///
+/// let rewrite = PatchRewrite::strip("/password").then(PatchRewrite::rename("/name", "/login"));
+/// ```
+///
+/// Use it for one stream with `ServerSentEvents::rewrite`, or for every stream of a signal with
+/// [`SseRegistry::rewrite`](crate::SseRegistry::rewrite). The whole document of a rewritten
+/// signal is always sent as a json patch, never as a binary snapshot.
+#[derive(Clone)]
+pub struct PatchRewrite(Arc<dyn Fn(&mut Patch) + Send + Sync>);
+
+impl PatchRewrite {
+    /// Creates a rewrite calling `rewrite` with every patch.
+    ///
+    /// The rewritten patch must still apply to the document the client has, which is the
+    /// default value of the signal with the rewritten patches applied.
+    pub fn new(rewrite: impl Fn(&mut Patch) + Send + Sync + 'static) -> Self {
+        PatchRewrite(Arc::new(rewrite))
+    }
+
+    /// Creates a rewrite removing the field at `pointer`, and everything below it, from the
+    /// patches.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pointer` isn't a valid json pointer.
+    pub fn strip(pointer: &str) -> Self {
+        let pointer = parse(pointer);
+        PatchRewrite::new(move |patch| {
+            patch.0.retain_mut(|operation| {
+                if source_of(operation).is_some_and(|from| from.starts_with(&pointer)) {
+                    return false;
+                }
+                let (path, value) = parts(operation);
+                if path.starts_with(&pointer) {
+                    return false;
+                }
+                if let (Some(value), Some(relative)) = (value, pointer.strip_prefix(path)) {
+                    relative.delete(value);
+                }
+                true
+            });
+        })
+    }
+
+    /// Creates a rewrite moving the field at `from` to `to` in the patches.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `from` or `to` isn't a valid json pointer.
+    pub fn rename(from: &str, to: &str) -> Self {
+        let (from, to) = (parse(from), parse(to));
+        PatchRewrite::new(move |patch| {
+            for operation in &mut patch.0 {
+                if let Some(source) = source_of(operation) {
+                    if let Some(rest) = source.strip_prefix(&from) {
+                        *source = to.concat(rest);
+                    }
+                }
+                let (path, value) = parts(operation);
+                if let Some(rest) = path.strip_prefix(&from) {
+                    *path = to.concat(rest);
+                } else if let (Some(value), Some(relative)) = (value, from.strip_prefix(path)) {
+                    // The value of a parent, such as the whole document
+                    let target = to.strip_prefix(path).map(Pointer::to_buf);
+                    if let (Some(field), Some(target)) = (relative.delete(&mut *value), target) {
+                        let _ = target.assign(value, field);
+                    }
+                }
+            }
+        })
+    }
+
+    /// Returns a rewrite applying this rewrite, then `next`.
+    pub fn then(self, next: PatchRewrite) -> Self {
+        PatchRewrite::new(move |patch| {
+            self.apply(patch);
+            next.apply(patch);
+        })
+    }
+
+    pub(crate) fn apply(&self, patch: &mut Patch) {
+        (self.0)(patch);
+    }
+}
+
+impl fmt::Debug for PatchRewrite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("PatchRewrite").finish_non_exhaustive()
+    }
+}
+
+fn parse(pointer: &str) -> PointerBuf {
+    PointerBuf::parse(pointer).expect("invalid json pointer")
+}
+
+/// Returns the source path of a `move` or `copy` operation.
+fn source_of(operation: &mut PatchOperation) -> Option<&mut PointerBuf> {
+    match operation {
+        PatchOperation::Move(op) => Some(&mut op.from),
+        PatchOperation::Copy(op) => Some(&mut op.from),
+        _ => None,
+    }
+}
+
+/// Returns the path of an operation, and the value it sets, if any.
+fn parts(operation: &mut PatchOperation) -> (&mut PointerBuf, Option<&mut Value>) {
+    match operation {
+        PatchOperation::Add(op) => (&mut op.path, Some(&mut op.value)),
+        PatchOperation::Replace(op) => (&mut op.path, Some(&mut op.value)),
+        PatchOperation::Test(op) => (&mut op.path, Some(&mut op.value)),
+        PatchOperation::Remove(op) => (&mut op.path, None),
+        PatchOperation::Move(op) => (&mut op.path, None),
+        PatchOperation::Copy(op) => (&mut op.path, None),
+    }
+}
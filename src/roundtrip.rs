@@ -0,0 +1,71 @@
+use std::fmt::Debug;
+
+use proptest::prelude::*;
+use serde_json::Value;
+
+use crate::SignalCodec;
+
+/// Generates arbitrary json values, nested up to a few levels.
+///
+/// Numbers are integers and finite floats, since json has no other numbers.
+///
+/// ```ignore
+/// proptest! {
+///     #[test]
+///     fn patches_roundtrip(old in arb_json(), new in arb_json()) {
+///         roundtrip(&old, &new);
+///     }
+/// }
+/// ```
+pub fn arb_json() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::Bool),
+        any::<i64>().prop_map(Value::from),
+        any::<f64>()
+            .prop_filter("json numbers are finite", |float| float.is_finite())
+            .prop_map(Value::from),
+        ".{0,8}".prop_map(Value::String),
+    ];
+    leaf.prop_recursive(4, 64, 8, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..8).prop_map(Value::Array),
+            prop::collection::btree_map(".{0,8}", inner, 0..8)
+                .prop_map(|map| Value::Object(map.into_iter().collect())),
+        ]
+    })
+}
+
+/// Asserts that applying the patch from `old` to `new` to `old` yields `new`, as it does on
+/// the client.
+#[track_caller]
+pub fn roundtrip(old: &Value, new: &Value) {
+    let patch = json_patch::diff(old, new);
+    let mut patched = old.clone();
+    if let Err(err) = json_patch::patch(&mut patched, &patch) {
+        panic!("patch {patch:?} from {old} to {new} doesn't apply: {err}");
+    }
+    assert_eq!(
+        &patched, new,
+        "patch {patch:?} from {old} doesn't yield {new}"
+    );
+}
+
+/// Asserts that sending `new` after `old` with the codec `C` yields `new` on the client.
+///
+/// This checks custom [`SignalCodec`]s, whose decoding must undo their encoding.
+#[track_caller]
+pub fn roundtrip_codec<T, C>(old: &T, new: &T)
+where
+    T: Debug + PartialEq,
+    C: SignalCodec<T>,
+{
+    let old_doc = C::encode(old).expect("old value encodes");
+    let new_doc = C::encode(new).expect("new value encodes");
+    roundtrip(&old_doc, &new_doc);
+    let decoded = C::decode(new_doc).expect("patched document decodes");
+    assert_eq!(
+        &decoded, new,
+        "decoding the document of {new:?} yields another value"
+    );
+}
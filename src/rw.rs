@@ -0,0 +1,75 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use json_patch::Patch;
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    current_document, dispose_on_cleanup, document_signal, float, observers, register_validator,
+    register_with_connection, validate_lenient,
+};
+
+/// Registers the document of a writable signal, and sets `signal` to every new document, or to
+/// the value `resolve` merges it into after a local write.
+pub(crate) fn setup<T>(
+    name: Cow<'static, str>,
+    signal: RwSignal<T>,
+    resolve: impl Fn(T, T) -> T + 'static,
+) where
+    T: Default + Serialize + for<'de> Deserialize<'de> + Send + Sync + 'static,
+{
+    let (doc, first) = document_signal(&name, || serde_json::to_value(T::default()).unwrap());
+    register_with_connection(name.clone(), doc.get_untracked(), first);
+    register_validator(&name, validate_lenient::<T>);
+    dispose_on_cleanup(name.clone());
+
+    // The document the value was last set from, which it differs from after a local write
+    let synced = RefCell::new(
+        signal
+            .with_untracked(serde_json::to_value)
+            .unwrap_or_default(),
+    );
+    let signal_name = name.clone();
+    let sync = move |doc: &Value| {
+        let server = match float::from_value_lenient(doc.clone()) {
+            Ok(server) => server,
+            Err(err) => {
+                leptos::logging::error!("failed to decode {}: {}", signal_name, err);
+                return;
+            }
+        };
+        let local = signal.with_untracked(serde_json::to_value).ok();
+        let value = if local.as_ref() != Some(&*synced.borrow()) {
+            let local = signal
+                .try_update_untracked(std::mem::take)
+                .unwrap_or_default();
+            resolve(local, server)
+        } else {
+            server
+        };
+        *synced.borrow_mut() = doc.clone();
+        signal.set(value);
+    };
+    sync(&doc.get_untracked());
+
+    let observer = move |_: &str, _: &Patch, doc: &Value| sync(doc);
+    let id = observers::add(name.clone(), Rc::new(observer));
+    on_cleanup(move || observers::remove(&name, id));
+}
+
+/// Discards a pending local write, setting `signal` back to the current document of `name`.
+pub(crate) fn revert<T>(name: &str, signal: RwSignal<T>)
+where
+    T: for<'de> Deserialize<'de> + Send + Sync + 'static,
+{
+    let Some(doc) = current_document(name) else {
+        return;
+    };
+    match float::from_value_lenient(doc) {
+        Ok(value) => signal.set(value),
+        Err(err) => leptos::logging::error!("failed to decode {}: {}", name, err),
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use serde_json::Value;
+
+/// The schema version of a signal the client expects, and how to migrate older versions.
+///
+/// While a new version of a server is being deployed, clients may be talking to a server which
+/// sends an older shape of a signal. Register the schema with [`set_sse_schema`], and updates
+/// sent with an older version are migrated before they are applied.
+///
+/// ```
+/// use leptos_sse::{set_sse_schema, SseSchema};
+///
+/// // Version 2 renamed `count` to `value`
+/// set_sse_schema(
+///     "counter",
+///     SseSchema::new(2).migration(1, |mut doc| {
+///         if let Some(count) = doc.as_object_mut().and_then(|doc| doc.remove("count")) {
+///             doc["value"] = count;
+///         }
+///         doc
+///     }),
+/// );
+/// ```
+///
+/// The server declares the version it sends with `ServerSentEvents::schema_version`.
+///
+/// [`set_sse_schema`]: crate::set_sse_schema
+#[derive(Clone)]
+pub struct SseSchema {
+    pub(crate) version: u32,
+    pub(crate) migrations: BTreeMap<u32, Rc<dyn Fn(Value) -> Value>>,
+}
+
+impl SseSchema {
+    /// Creates a schema expecting `version`, without any migrations.
+    pub fn new(version: u32) -> Self {
+        SseSchema {
+            version,
+            migrations: BTreeMap::new(),
+        }
+    }
+
+    /// Adds a migration of a document from version `from` to version `from + 1`.
+    pub fn migration(mut self, from: u32, migrate: impl Fn(Value) -> Value + 'static) -> Self {
+        self.migrations.insert(from, Rc::new(migrate));
+        self
+    }
+
+    /// Migrates `doc` from version `from` to the expected version.
+    #[cfg(all(target_arch = "wasm32", feature = "client"))]
+    fn migrate(&self, from: u32, mut doc: Value) -> Result<Value, String> {
+        if from > self.version {
+            return Err(format!(
+                "version {} is newer than the expected version {}",
+                from, self.version
+            ));
+        }
+        for version in from..self.version {
+            let migrate = self
+                .migrations
+                .get(&version)
+                .ok_or_else(|| format!("no migration from version {}", version))?;
+            doc = migrate(doc);
+        }
+        Ok(doc)
+    }
+}
+
+impl std::fmt::Debug for SseSchema {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SseSchema")
+            .field("version", &self.version)
+            .field("migrations", &self.migrations.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "client"))]
+mod client {
+    use std::borrow::Cow;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+
+    use json_patch::Patch;
+    use serde_json::{json, Value};
+
+    use super::SseSchema;
+    use crate::ServerSignalUpdate;
+
+    thread_local! {
+        static SCHEMAS: RefCell<HashMap<Cow<'static, str>, SseSchema>> = RefCell::new(HashMap::new());
+        /// The documents of signals sent with another version, in the shape the server sends.
+        static RAW_DOCUMENTS: RefCell<HashMap<String, Value>> = RefCell::new(HashMap::new());
+    }
+
+    pub(crate) fn set(name: Cow<'static, str>, schema: SseSchema) {
+        SCHEMAS.with(|schemas| {
+            schemas.borrow_mut().insert(name, schema);
+        });
+    }
+
+    /// Converts an update sent with another schema version into an update replacing the whole
+    /// document with the migrated one.
+    ///
+    /// Returns `None` if the update can't be migrated, in which case it must be dropped.
+    pub(crate) fn migrate(update: ServerSignalUpdate) -> Option<ServerSignalUpdate> {
+        let Some(version) = update.version else {
+            return Some(update);
+        };
+        let Some(schema) = SCHEMAS.with(|schemas| schemas.borrow().get(&*update.name).cloned())
+        else {
+            return Some(update);
+        };
+        if schema.version == version {
+            return Some(update);
+        }
+
+        let raw = RAW_DOCUMENTS.with(|documents| {
+            let mut documents = documents.borrow_mut();
+            let doc = documents.entry(update.name.to_string()).or_default();
+            // Versioned streams start by replacing the whole document
+            if update.seq == Some(1) {
+                *doc = Value::Null;
+            }
+            json_patch::patch(doc, &update.patch).map(|()| doc.clone())
+        });
+        let doc = match raw {
+            Ok(doc) => schema.migrate(version, doc),
+            Err(err) => Err(err.to_string()),
+        };
+        match doc {
+            Ok(doc) => {
+                let patch: Patch = serde_json::from_value(json!([
+                    { "op": "replace", "path": "", "value": doc }
+                ]))
+                .ok()?;
+                Some(ServerSignalUpdate { patch, ..update })
+            }
+            Err(err) => {
+                leptos::logging::error!("Dropping update to {}: {}", update.name, err);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "client"))]
+pub(crate) use client::*;
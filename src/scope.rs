@@ -0,0 +1,56 @@
+use std::fmt;
+
+/// Returns the name of the signal `name` scoped to a user or session, such as `cart:42`.
+///
+/// Scoped signals keep the values of one user from being broadcast to everyone. Use the same
+/// scoped name on the client and the server:
+///
+/// ```
+/// use leptos_sse::scoped;
+///
+/// assert_eq!(scoped("cart", 42), "cart:42");
+/// ```
+///
+/// The scope must be known to the client, such as from a server function, and the server must
+/// only send a scoped signal on the connections of its user, see [`SseScope`].
+pub fn scoped(name: &str, scope: impl fmt::Display) -> String {
+    format!("{name}:{scope}")
+}
+
+/// The scope of the signals of a request, such as a user or session id.
+///
+/// Authentication middleware inserts the scope into the request extensions, and SSE handlers
+/// extract it to scope their signals, so that the scope can't be chosen by the client:
+///
+/// ```ignore
+/// // In the authentication middleware
+/// request.extensions_mut().insert(SseScope::new(session.user_id));
+///
+/// // In the SSE handler
+/// async fn handle_sse(scope: SseScope) -> Sse<impl Stream<Item = ServerSentEventsItem>> {
+///     let cart = ServerSentEvents::new(scope.name("cart"), cart_updates(&scope)).unwrap();
+///     Sse::new(cart)
+/// }
+/// ```
+///
+/// Requests without a scope are rejected with `401 Unauthorized`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SseScope(pub String);
+
+impl SseScope {
+    /// Creates a scope.
+    pub fn new(scope: impl Into<String>) -> Self {
+        SseScope(scope.into())
+    }
+
+    /// Returns the name of the signal `name` in this scope, see [`scoped`].
+    pub fn name(&self, name: &str) -> String {
+        scoped(name, &self.0)
+    }
+}
+
+impl fmt::Display for SseScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
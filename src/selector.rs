@@ -0,0 +1,90 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use json_patch::jsonptr::{Pointer, PointerBuf};
+use json_patch::{Patch, PatchOperation};
+use leptos::prelude::*;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{dispose_on_cleanup, document_signal, float, observers, register_with_connection};
+
+/// Registers the document of a selector, and keeps `set` up to date with the value at
+/// `pointer` mapped by `map`.
+pub(crate) fn setup<T, U>(
+    name: Cow<'static, str>,
+    pointer: PointerBuf,
+    default: impl FnOnce() -> Value,
+    set: WriteSignal<U>,
+    map: impl Fn(T) -> U + 'static,
+) where
+    T: Default + for<'de> Deserialize<'de>,
+    U: Send + Sync + 'static,
+{
+    let (signal, first) = document_signal(&name, default);
+    register_with_connection(name.clone(), signal.get_untracked(), first);
+    dispose_on_cleanup(name.clone());
+
+    // The value at the pointer the result was computed from, once it has been computed
+    let selected: RefCell<Option<Option<Value>>> = RefCell::new(None);
+    let (signal_name, path) = (name.clone(), pointer.clone());
+    let select = move |doc: &Value| {
+        let value = doc.pointer(path.as_str());
+        if selected
+            .borrow()
+            .as_ref()
+            .is_some_and(|selected| selected.as_ref() == value)
+        {
+            return;
+        }
+        let input = match value {
+            Some(value) => match float::from_value_lenient(value.clone()) {
+                Ok(input) => input,
+                Err(err) => {
+                    leptos::logging::error!(
+                        "failed to decode {} of {}: {}",
+                        path,
+                        signal_name,
+                        err
+                    );
+                    return;
+                }
+            },
+            None => T::default(),
+        };
+        *selected.borrow_mut() = Some(value.cloned());
+        set.set(map(input));
+    };
+    select(&signal.get_untracked());
+
+    let observer = move |_: &str, patch: &Patch, doc: &Value| {
+        if touches(patch, &pointer) {
+            select(doc);
+        }
+    };
+    let id = observers::add(name.clone(), Rc::new(observer));
+    on_cleanup(move || observers::remove(&name, id));
+}
+
+/// Returns `true` if `patch` may change the value at `pointer`.
+///
+/// Adding or removing an element of an array moves the elements after it, so these count for
+/// every pointer into the array.
+fn touches(patch: &Patch, pointer: &Pointer) -> bool {
+    let overlaps = |path: &Pointer| path.starts_with(pointer) || pointer.starts_with(path);
+    let shifts = |path: &Pointer| {
+        path.parent()
+            .is_some_and(|parent| pointer.starts_with(parent))
+    };
+    patch.0.iter().any(|operation| match operation {
+        PatchOperation::Add(op) => overlaps(&op.path) || shifts(&op.path),
+        PatchOperation::Remove(op) => overlaps(&op.path) || shifts(&op.path),
+        PatchOperation::Move(op) => {
+            overlaps(&op.path) || shifts(&op.path) || overlaps(&op.from) || shifts(&op.from)
+        }
+        PatchOperation::Copy(op) => overlaps(&op.path) || shifts(&op.path),
+        PatchOperation::Replace(op) => overlaps(&op.path),
+        PatchOperation::Test(_) => false,
+    })
+}
@@ -0,0 +1,114 @@
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use json_patch::{Patch, PatchOperation};
+use leptos::prelude::*;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{dispose_on_cleanup, document_signal, float, observers, register_with_connection};
+
+/// Registers the document of a series, and appends the values added at its tail to `set`.
+pub(crate) fn setup<T>(name: Cow<'static, str>, capacity: usize, set: WriteSignal<VecDeque<T>>)
+where
+    T: for<'de> Deserialize<'de> + Send + Sync + 'static,
+{
+    let (doc, first) = document_signal(&name, || Value::Array(Vec::new()));
+    register_with_connection(name.clone(), doc.get_untracked(), first);
+    dispose_on_cleanup(name.clone());
+
+    let series = Series {
+        name: name.clone(),
+        capacity,
+        set,
+        len: Cell::new(0),
+    };
+    series.resync(&doc.get_untracked());
+
+    let observer = move |_: &str, patch: &Patch, doc: &Value| series.apply(patch, doc);
+    let id = observers::add(name.clone(), Rc::new(observer));
+    on_cleanup(move || observers::remove(&name, id));
+}
+
+struct Series<T> {
+    name: Cow<'static, str>,
+    capacity: usize,
+    set: WriteSignal<VecDeque<T>>,
+    /// The length of the array on the server, whose last values are buffered.
+    len: Cell<usize>,
+}
+
+impl<T> Series<T>
+where
+    T: for<'de> Deserialize<'de> + Send + Sync + 'static,
+{
+    /// Appends the values added at the tail of the array, and copies the buffered values from
+    /// `doc` again after any other change.
+    fn apply(&self, patch: &Patch, doc: &Value) {
+        let mut appended = Vec::new();
+        for operation in &patch.0 {
+            let len = self.len.get();
+            let index =
+                operation
+                    .path()
+                    .tokens()
+                    .next()
+                    .and_then(|token| match token.decoded().as_ref() {
+                        "-" => Some(len),
+                        index => index.parse::<usize>().ok(),
+                    });
+            let top_level = operation.path().count() == 1;
+            match (operation, index) {
+                (PatchOperation::Add(add), Some(index)) if top_level && index == len => {
+                    appended.push(&add.value);
+                    self.len.set(len + 1);
+                }
+                // Removing values before the buffered ones, such as trimming the front
+                (PatchOperation::Remove(_), Some(index))
+                    if top_level && index + self.capacity < len =>
+                {
+                    self.len.set(len - 1);
+                }
+                (PatchOperation::Test(_), _) => {}
+                _ => return self.resync(doc),
+            }
+        }
+        if appended.is_empty() {
+            return;
+        }
+        let values: Vec<T> = appended
+            .into_iter()
+            .filter_map(|value| self.decode(value))
+            .collect();
+        let capacity = self.capacity;
+        self.set.update(|buffer| {
+            buffer.extend(values);
+            let excess = buffer.len().saturating_sub(capacity);
+            buffer.drain(..excess);
+        });
+    }
+
+    /// Buffers the last values of `doc` again.
+    fn resync(&self, doc: &Value) {
+        let values = doc.as_array().map(Vec::as_slice).unwrap_or_default();
+        self.len.set(values.len());
+        let start = values.len().saturating_sub(self.capacity);
+        let buffer = values[start..]
+            .iter()
+            .filter_map(|value| self.decode(value))
+            .collect();
+        self.set.set(buffer);
+    }
+
+    fn decode(&self, value: &Value) -> Option<T> {
+        match float::from_value_lenient(value.clone()) {
+            Ok(value) => Some(value),
+            Err(err) => {
+                leptos::logging::error!("failed to decode a value of {}: {}", self.name, err);
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[cfg(all(
+    feature = "ssr",
+    any(feature = "actix", feature = "axum", feature = "spin")
+))]
+use crate::ServerSignalUpdate;
+
+/// The whole document of a signal, sent as base64 encoded CBOR instead of a json patch.
+///
+/// The first update of a stream replaces the whole document, which for large states costs far
+/// more than all the patches after it. CBOR is used rather than bincode, since json values
+/// aren't self-describing enough for bincode to encode them.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Snapshot {
+    pub(crate) name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) id: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) seq: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) version: Option<u32>,
+    pub(crate) data: String,
+}
+
+/// Encodes `doc`, the document `update` was diffed to, as a `snapshot` event.
+#[cfg(all(
+    feature = "ssr",
+    any(feature = "actix", feature = "axum", feature = "spin")
+))]
+pub(crate) fn encode(
+    update: &ServerSignalUpdate,
+    doc: &Value,
+) -> Result<String, serde_json::Error> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(doc, &mut bytes).map_err(serde::ser::Error::custom)?;
+    let snapshot = Snapshot {
+        name: update.name().to_string(),
+        id: update.id,
+        seq: update.seq(),
+        version: update.version(),
+        data: STANDARD.encode(bytes),
+    };
+    serde_json::to_string(&snapshot)
+}
+
+/// Decodes a `snapshot` event into the json of an update replacing the whole document.
+#[cfg(any(all(target_arch = "wasm32", feature = "client"), feature = "testing"))]
+pub(crate) fn decode(data: &str) -> Result<String, String> {
+    let snapshot: Snapshot = serde_json::from_str(data).map_err(|err| err.to_string())?;
+    let bytes = STANDARD
+        .decode(&snapshot.data)
+        .map_err(|err| err.to_string())?;
+    let doc: Value = ciborium::from_reader(bytes.as_slice()).map_err(|err| err.to_string())?;
+    let update = serde_json::json!({
+        "name": snapshot.name,
+        "id": snapshot.id,
+        "patch": [{ "op": "replace", "path": "", "value": doc }],
+        "seq": snapshot.seq,
+        "version": snapshot.version,
+    });
+    Ok(update.to_string())
+}
@@ -0,0 +1,181 @@
+use std::borrow::Cow;
+use std::pin::Pin;
+#[cfg(feature = "signing")]
+use std::sync::Arc;
+use std::task::Poll;
+
+use futures::sink::SinkExt;
+use futures::stream::{Stream, StreamExt};
+use pin_project_lite::pin_project;
+use serde::Serialize;
+use serde_json::Value;
+use spin_sdk::http::{Fields, OutgoingResponse, ResponseOutparam};
+
+use crate::encoder::UpdateEncoder;
+use crate::float;
+#[cfg(feature = "encryption")]
+use crate::SessionKey;
+use crate::{NonFinite, ServerSignalUpdate, SignalName, SseFormat};
+
+pin_project! {
+    /// The `text/event-stream` body of a signal owned by the server, for runtimes without an
+    /// HTTP framework integration, such as WASI.
+    ///
+    /// This encodes the updates the same way as the `ServerSentEvents` of the actix and axum
+    /// integrations, but yields the bytes of the body, so it only needs an executor to poll it.
+    /// See [`respond_spin`] for serving it from a Spin component.
+    #[derive(Clone, Debug)]
+    pub struct SseBody<S> {
+        name: Cow<'static, str>,
+        #[pin]
+        stream: S,
+        json_value: Value,
+        seq: u64,
+        encoder: UpdateEncoder,
+        non_finite: NonFinite,
+    }
+}
+
+impl<S> SseBody<S> {
+    /// Creates the body of the signal `name` from a stream of its values, initializing `T` to
+    /// default.
+    ///
+    /// This function can fail if serialization of `T` fails.
+    pub fn new<T>(name: impl Into<Cow<'static, str>>, stream: S) -> Result<Self, serde_json::Error>
+    where
+        T: Default + Serialize,
+        S: Stream<Item = T>,
+    {
+        Ok(SseBody {
+            name: name.into(),
+            stream,
+            json_value: serde_json::to_value(T::default())?,
+            seq: 0,
+            encoder: UpdateEncoder::default(),
+            non_finite: NonFinite::default(),
+        })
+    }
+
+    /// Creates the body of a signal named after the type `T`, see [`SignalName`].
+    pub fn by_type<T>(stream: S) -> Result<Self, serde_json::Error>
+    where
+        T: SignalName + Default + Serialize,
+        S: Stream<Item = T>,
+    {
+        Self::new(T::signal_name(), stream)
+    }
+
+    /// Signs every update with `key`, see [`ServerSignalUpdate::sign`].
+    #[cfg(feature = "signing")]
+    pub fn signed(mut self, key: impl Into<Arc<[u8]>>) -> Self {
+        self.encoder.signing_key = Some(key.into());
+        self
+    }
+
+    /// Encrypts every update with `key`, see [`SessionKey`].
+    #[cfg(feature = "encryption")]
+    pub fn encrypted(mut self, key: SessionKey) -> Self {
+        self.encoder.encrypt_with(key);
+        self
+    }
+
+    /// Sends the updates in `format`, usually negotiated with the client, see [`SseFormat`].
+    pub fn format(mut self, format: SseFormat) -> Self {
+        self.encoder.format = format;
+        self
+    }
+
+    /// Sets how non-finite floats in the values are encoded, see [`NonFinite`].
+    pub fn non_finite(mut self, non_finite: NonFinite) -> Self {
+        self.non_finite = non_finite;
+        self
+    }
+}
+
+impl<S> Stream for SseBody<S>
+where
+    S: Stream,
+    S::Item: Serialize,
+{
+    type Item = Result<Vec<u8>, serde_json::Error>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let value = match this.stream.poll_next(cx) {
+            Poll::Ready(Some(value)) => value,
+            Poll::Ready(None) => return Poll::Ready(None),
+            Poll::Pending => return Poll::Pending,
+        };
+        let mut encode = || -> Result<Vec<u8>, serde_json::Error> {
+            let new_json = float::to_value_with(&value, *this.non_finite)?;
+            let update = ServerSignalUpdate::new_from_json::<S::Item>(
+                this.name.clone(),
+                this.json_value,
+                &new_json,
+            );
+            *this.seq += 1;
+            let encoded = this
+                .encoder
+                .encode(update.with_seq(*this.seq), Some(&new_json))?;
+            *this.json_value = new_json;
+            Ok(frame(encoded.event, &encoded.data).into_bytes())
+        };
+        Poll::Ready(Some(encode()))
+    }
+}
+
+/// Formats an SSE event, with one `data` field per line of `data`.
+fn frame(event: Option<&str>, data: &str) -> String {
+    let mut frame = String::with_capacity(data.len() + 16);
+    if let Some(event) = event {
+        frame.push_str("event: ");
+        frame.push_str(event);
+        frame.push('\n');
+    }
+    for line in data.split('\n') {
+        frame.push_str("data: ");
+        frame.push_str(line);
+        frame.push('\n');
+    }
+    frame.push('\n');
+    frame
+}
+
+/// Streams `body` as the `text/event-stream` response of a Spin HTTP component.
+///
+/// The response ends with the body, or when the client goes away. Values which can't be
+/// serialized are skipped.
+///
+/// ```ignore
+/// #[http_component]
+/// async fn handle_sse(_request: IncomingRequest, response: ResponseOutparam) {
+///     let body = SseBody::new("counter", counts()).unwrap();
+///     leptos_sse::respond_spin(body, response).await;
+/// }
+/// ```
+pub async fn respond_spin<S>(body: S, response_out: ResponseOutparam)
+where
+    S: Stream<Item = Result<Vec<u8>, serde_json::Error>>,
+{
+    let headers = Fields::from_list(&[
+        ("content-type".to_string(), b"text/event-stream".to_vec()),
+        ("cache-control".to_string(), b"no-cache".to_vec()),
+    ])
+    .expect("valid SSE headers");
+    let response = OutgoingResponse::new(headers);
+    let mut sink = response.take_body();
+    response_out.set(response);
+
+    let mut body = std::pin::pin!(body);
+    while let Some(chunk) = body.next().await {
+        let Ok(chunk) = chunk else {
+            continue;
+        };
+        if sink.send(chunk).await.is_err() {
+            break;
+        }
+    }
+}
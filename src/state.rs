@@ -0,0 +1,121 @@
+#[cfg(target_arch = "wasm32")]
+use std::cell::Cell;
+use std::time::Duration;
+
+use leptos::prelude::*;
+
+/// The state of the SSE connection, see
+/// [`use_sse_connection_state`](crate::use_sse_connection_state).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SseConnectionState {
+    /// The connection has not been opened yet, or is being opened.
+    #[default]
+    Connecting,
+    /// The connection is open.
+    Open,
+    /// The connection failed, and is being re-established.
+    Reconnecting,
+    /// The connection was closed because no signal needs it, see
+    /// [`SseOptions::lazy`](crate::SseOptions::lazy).
+    Closed,
+    /// The connection failed, and won't be re-established.
+    ///
+    /// This happens when the [`ReconnectPolicy`] gives up, or when the limits set with
+    /// [`SseOptions::max_reconnect_attempts`] or [`SseOptions::max_reconnect_time`] are reached.
+    ///
+    /// [`ReconnectPolicy`]: crate::ReconnectPolicy
+    /// [`SseOptions::max_reconnect_attempts`]: crate::SseOptions::max_reconnect_attempts
+    /// [`SseOptions::max_reconnect_time`]: crate::SseOptions::max_reconnect_time
+    Failed,
+}
+
+/// A reconnection of the SSE connection, see
+/// [`use_sse_reconnect_event`](crate::use_sse_reconnect_event).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SseReconnectEvent {
+    /// The connection was lost, and is being re-established.
+    Retrying {
+        /// The number of the attempt, starting at 1 after the connection was last open.
+        attempt: u32,
+        /// How long until the next attempt, or `None` when the browser decides, see
+        /// [`ReconnectPolicy::Browser`](crate::ReconnectPolicy::Browser).
+        delay: Option<Duration>,
+        /// Why the connection is being re-established.
+        cause: SseReconnectCause,
+    },
+    /// The connection is open again.
+    Recovered {
+        /// The number of attempts it took.
+        attempts: u32,
+        /// How long the connection was down.
+        downtime: Duration,
+    },
+}
+
+/// Why the connection is being re-established, see [`SseReconnectEvent::Retrying`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SseReconnectCause {
+    /// The connection failed, or couldn't be opened.
+    Error,
+    /// No message arrived within the heartbeat timeout, see
+    /// [`SseOptions::heartbeat_timeout`](crate::SseOptions::heartbeat_timeout).
+    HeartbeatTimeout,
+    /// The server asked the client to reconnect, for example before shutting down.
+    ServerRequest,
+}
+
+thread_local! {
+    static STATE: ArcRwSignal<SseConnectionState> = ArcRwSignal::new(SseConnectionState::default());
+    static RECONNECT: ArcRwSignal<Option<SseReconnectEvent>> = ArcRwSignal::new(None);
+    /// When the connection was lost, and the number of the last attempt to re-establish it.
+    #[cfg(target_arch = "wasm32")]
+    static DOWN: Cell<Option<(f64, u32)>> = Cell::new(None);
+}
+
+/// Returns the signal holding the state of the connection of this page.
+pub(crate) fn signal() -> ArcReadSignal<SseConnectionState> {
+    STATE.with(|state| state.read_only())
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn set(new_state: SseConnectionState) {
+    STATE.with(|state| {
+        if state.get_untracked() != new_state {
+            state.set(new_state);
+        }
+    });
+}
+
+/// Returns the signal holding the last reconnection of the connection of this page.
+pub(crate) fn reconnect_signal() -> ArcReadSignal<Option<SseReconnectEvent>> {
+    RECONNECT.with(|reconnect| reconnect.read_only())
+}
+
+/// Records an attempt to re-establish the connection.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn retrying(attempt: u32, delay: Option<Duration>, cause: SseReconnectCause) {
+    let since = DOWN
+        .with(Cell::get)
+        .map_or_else(js_sys::Date::now, |(since, _)| since);
+    DOWN.with(|down| down.set(Some((since, attempt))));
+    let event = SseReconnectEvent::Retrying {
+        attempt,
+        delay,
+        cause,
+    };
+    RECONNECT.with(|reconnect| reconnect.set(Some(event)));
+}
+
+/// Records that the connection is open again, if it was lost.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn recovered() {
+    let Some((since, attempts)) = DOWN.with(Cell::take) else {
+        return;
+    };
+    let downtime = Duration::from_secs_f64((js_sys::Date::now() - since).max(0.0) / 1000.0);
+    let event = SseReconnectEvent::Recovered { attempts, downtime };
+    RECONNECT.with(|reconnect| reconnect.set(Some(event)));
+}
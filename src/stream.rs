@@ -0,0 +1,25 @@
+use std::cell::RefCell;
+
+use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+use crate::ServerSignalUpdate;
+
+thread_local! {
+    static SUBSCRIBERS: RefCell<Vec<UnboundedSender<ServerSignalUpdate>>> = RefCell::new(Vec::new());
+}
+
+/// Subscribes to every update received from the SSE connection.
+pub(crate) fn subscribe() -> UnboundedReceiver<ServerSignalUpdate> {
+    let (sender, receiver) = mpsc::unbounded();
+    SUBSCRIBERS.with(|subscribers| subscribers.borrow_mut().push(sender));
+    receiver
+}
+
+/// Forwards an update to all subscribers, dropping the ones whose stream has been dropped.
+pub(crate) fn publish(update: &ServerSignalUpdate) {
+    SUBSCRIBERS.with(|subscribers| {
+        subscribers
+            .borrow_mut()
+            .retain(|sender| sender.unbounded_send(update.clone()).is_ok());
+    });
+}
@@ -0,0 +1,127 @@
+/// The name of the Tauri event carrying the updates, as JSON strings.
+///
+/// See [`SseOptions::tauri`](crate::SseOptions::tauri) for applying them in the webview.
+pub const TAURI_EVENT: &str = "leptos_sse://update";
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::backend::*;
+
+#[cfg(all(target_arch = "wasm32", feature = "client"))]
+pub(crate) use self::webview::listen;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod backend {
+    use std::borrow::Cow;
+
+    use futures::stream::{Stream, StreamExt};
+    use serde::Serialize;
+    use tauri::async_runtime::{self, JoinHandle};
+    use tauri::{AppHandle, Emitter, Runtime};
+
+    use super::TAURI_EVENT;
+    use crate::ServerSignalUpdate;
+
+    /// Emits the updates of the signal `name` to every webview of `app`, as its values arrive
+    /// from `stream`.
+    ///
+    /// Like the SSE integrations, the first update is diffed from `T::default()` and the
+    /// following ones from the previous value. The returned task runs on Tauri's async runtime
+    /// until the stream ends, or the app exits. The webview applies the updates with
+    /// [`SseOptions::tauri`](crate::SseOptions::tauri), with the same signals as over SSE.
+    ///
+    /// This function can fail if serialization of `T` fails.
+    ///
+    /// ```ignore
+    /// tauri::Builder::default()
+    ///     .setup(|app| {
+    ///         leptos_sse::spawn_tauri_signal(app.handle().clone(), "counter", counts())?;
+    ///         Ok(())
+    ///     })
+    /// ```
+    pub fn spawn_tauri_signal<R, S, T>(
+        app: AppHandle<R>,
+        name: impl Into<Cow<'static, str>>,
+        stream: S,
+    ) -> Result<JoinHandle<()>, serde_json::Error>
+    where
+        R: Runtime,
+        S: Stream<Item = T> + Send + 'static,
+        T: Default + Serialize,
+    {
+        let name = name.into();
+        let mut json_value = serde_json::to_value(T::default())?;
+        Ok(async_runtime::spawn(async move {
+            let mut stream = std::pin::pin!(stream);
+            let mut seq = 0;
+            while let Some(value) = stream.next().await {
+                let Ok(new_json) = serde_json::to_value(&value) else {
+                    continue;
+                };
+                seq += 1;
+                let update =
+                    ServerSignalUpdate::new_from_json::<T>(name.clone(), &json_value, &new_json)
+                        .with_seq(seq);
+                json_value = new_json;
+                let Ok(payload) = serde_json::to_string(&update) else {
+                    continue;
+                };
+                if app.emit(TAURI_EVENT, payload).is_err() {
+                    break;
+                }
+            }
+        }))
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "client"))]
+mod webview {
+    use std::cell::RefCell;
+
+    use js_sys::{Function, Reflect};
+    use wasm_bindgen::{prelude::Closure, JsCast, JsValue};
+
+    use super::TAURI_EVENT;
+    use crate::state::{self, SseConnectionState};
+    use crate::{handle_data, rebase_all_documents};
+
+    thread_local! {
+        static LISTENER: RefCell<Option<Closure<dyn FnMut(JsValue)>>> = RefCell::new(None);
+    }
+
+    /// Applies the updates emitted by the Tauri backend.
+    ///
+    /// This uses the global Tauri API, which must be enabled with `app.withGlobalTauri` in
+    /// `tauri.conf.json`.
+    pub(crate) fn listen() -> Result<(), JsValue> {
+        let window = web_sys::window().ok_or_else(|| JsValue::from_str("no window"))?;
+        let event = Reflect::get(&window, &"__TAURI__".into())
+            .and_then(|tauri| Reflect::get(&tauri, &"event".into()))
+            .ok()
+            .filter(|event| event.is_object())
+            .ok_or_else(|| {
+                JsValue::from_str("the Tauri API is missing, enable `app.withGlobalTauri`")
+            })?;
+        let listen: Function = Reflect::get(&event, &"listen".into())?.dyn_into()?;
+
+        let onupdate = Closure::wrap(Box::new(move |event: JsValue| {
+            let payload = Reflect::get(&event, &"payload".into())
+                .ok()
+                .and_then(|payload| payload.as_string());
+            if let Some(payload) = payload {
+                handle_data(payload);
+            }
+        }) as Box<dyn FnMut(JsValue)>);
+        listen.call2(
+            &event,
+            &JsValue::from_str(TAURI_EVENT),
+            onupdate.as_ref().unchecked_ref(),
+        )?;
+        LISTENER.with(|listener| *listener.borrow_mut() = Some(onupdate));
+
+        // Every task of the backend numbers its updates from 1
+        rebase_all_documents();
+        state::set(SseConnectionState::Open);
+        sse_log!("Listening to Tauri events for server signals");
+        Ok(())
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+use std::pin::pin;
+
+use futures::stream::{Stream, StreamExt};
+use json_patch::{Patch, PatchOperation};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::chunk::{Chunk, CHUNK_EVENT};
+use crate::control::BATCH_EVENT;
+#[cfg(feature = "cbor")]
+use crate::control::SNAPSHOT_EVENT;
+#[cfg(feature = "msgpack")]
+use crate::format::msgpack::{self, MSGPACK_EVENT};
+#[cfg(feature = "protobuf")]
+use crate::protobuf::{self, PROTOBUF_EVENT};
+use crate::ServerSignalUpdate;
+
+/// Records the updates sent by an SSE stream, to test server code without an HTTP server.
+///
+/// The stream is rendered as the web framework would, and the updates are read back from the
+/// response body, including chunked and batched updates. Encrypted updates can't be read back.
+///
+/// ```ignore
+/// let (sender, events) = ServerSentEvents::<()>::channel::<Count>("counter", 8)?;
+/// sender.send(Count { value: 1 }).await?;
+/// drop(sender);
+///
+/// let recorder = SseRecorder::record(events).await;
+/// recorder.assert_patch_path_eq("counter", "/value", 1);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SseRecorder {
+    updates: Vec<(String, Patch)>,
+    error: Option<String>,
+    /// The names of the interned signals, by id.
+    ids: HashMap<u32, String>,
+}
+
+impl SseRecorder {
+    /// Returns the recorded updates, in the order they were sent.
+    pub fn updates(&self) -> &[(String, Patch)] {
+        &self.updates
+    }
+
+    /// Returns the recorded patches of the signal `name`.
+    pub fn patches<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Patch> + 'a {
+        self.updates
+            .iter()
+            .filter(move |(update, _)| update == name)
+            .map(|(_, patch)| patch)
+    }
+
+    /// Returns the error which ended the stream, if any.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Returns the value a client would have after applying the patches of `name` to `initial`,
+    /// usually the json of `T::default()`.
+    pub fn value(&self, name: &str, initial: Value) -> Result<Value, json_patch::PatchError> {
+        let mut value = initial;
+        for patch in self.patches(name) {
+            json_patch::patch(&mut value, patch)?;
+        }
+        Ok(value)
+    }
+
+    /// Asserts that the last patch of `name` setting `path`, a json pointer, sets it to
+    /// `expected`.
+    #[track_caller]
+    pub fn assert_patch_path_eq(&self, name: &str, path: &str, expected: impl Serialize) {
+        let expected = serde_json::to_value(expected).expect("expected value serializes to json");
+        let actual = self
+            .patches(name)
+            .flat_map(|patch| patch.0.iter())
+            .filter_map(|operation| match operation {
+                PatchOperation::Add(add) if add.path.as_str() == path => Some(&add.value),
+                PatchOperation::Replace(replace) if replace.path.as_str() == path => {
+                    Some(&replace.value)
+                }
+                _ => None,
+            })
+            .last();
+        match actual {
+            Some(actual) => assert_eq!(
+                actual, &expected,
+                "unexpected value of {path} in the patches of {name}"
+            ),
+            None => panic!(
+                "no patch of {name} sets {path}, the patches are {:?}",
+                self.patches(name).collect::<Vec<_>>()
+            ),
+        }
+    }
+
+    /// Records the updates of an SSE response body, until it ends or `count` updates were read.
+    pub(crate) async fn record_body<B>(body: B, count: usize) -> Self
+    where
+        B: Stream<Item = Result<Vec<u8>, String>>,
+    {
+        let mut recorder = SseRecorder::default();
+        let mut parser = Parser::default();
+        let mut body = pin!(body);
+        while recorder.updates.len() < count {
+            match body.next().await {
+                Some(Ok(bytes)) => parser.feed(&bytes, &mut recorder),
+                Some(Err(err)) => {
+                    recorder.error = Some(err);
+                    break;
+                }
+                None => break,
+            }
+        }
+        recorder
+    }
+
+    fn push(&mut self, update: ServerSignalUpdate) {
+        let name = match update.id {
+            Some(id) if update.name.is_empty() => self.ids.get(&id).cloned().unwrap_or_default(),
+            Some(id) => {
+                self.ids.insert(id, update.name.to_string());
+                update.name.into_owned()
+            }
+            None => update.name.into_owned(),
+        };
+        self.updates.push((name, update.patch));
+    }
+}
+
+/// Splits an SSE response body into events, and reassembles chunked updates.
+#[derive(Default)]
+struct Parser {
+    buffer: Vec<u8>,
+    chunks: HashMap<(String, u64), Vec<String>>,
+}
+
+impl Parser {
+    fn feed(&mut self, bytes: &[u8], recorder: &mut SseRecorder) {
+        self.buffer.extend_from_slice(bytes);
+        while let Some(end) = self.buffer.windows(2).position(|window| window == b"\n\n") {
+            let frame: Vec<u8> = self.buffer.drain(..end + 2).collect();
+            self.event(&String::from_utf8_lossy(&frame), recorder);
+        }
+    }
+
+    fn event(&mut self, frame: &str, recorder: &mut SseRecorder) {
+        let mut event = None;
+        let mut data = Vec::new();
+        for line in frame.lines() {
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "event" => event = Some(value),
+                "data" => data.push(value),
+                _ => {}
+            }
+        }
+        if data.is_empty() {
+            return;
+        }
+        let data = data.join("\n");
+        match event {
+            None | Some("message") => self.update(&data, recorder),
+            Some(BATCH_EVENT) => {
+                if let Ok(updates) = serde_json::from_str::<Vec<ServerSignalUpdate>>(&data) {
+                    updates.into_iter().for_each(|update| recorder.push(update));
+                }
+            }
+            Some(CHUNK_EVENT) => {
+                let Ok(chunk) = serde_json::from_str::<Chunk>(&data) else {
+                    return;
+                };
+                let key = (chunk.name, chunk.id);
+                let parts = self.chunks.entry(key.clone()).or_default();
+                parts.push(chunk.data);
+                if chunk.index + 1 == chunk.count {
+                    let message = self.chunks.remove(&key).unwrap_or_default().concat();
+                    self.update(&message, recorder);
+                }
+            }
+            #[cfg(feature = "cbor")]
+            Some(SNAPSHOT_EVENT) => {
+                if let Ok(update) = crate::snapshot::decode(&data) {
+                    self.update(&update, recorder);
+                }
+            }
+            #[cfg(feature = "msgpack")]
+            Some(MSGPACK_EVENT) => {
+                if let Ok(update) = msgpack::decode(&data) {
+                    self.update(&update, recorder);
+                }
+            }
+            #[cfg(feature = "protobuf")]
+            Some(PROTOBUF_EVENT) => {
+                if let Ok(update) = protobuf::decode(&data) {
+                    self.update(&update, recorder);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn update(&self, data: &str, recorder: &mut SseRecorder) {
+        if let Ok(update) = serde_json::from_str::<ServerSignalUpdate>(data) {
+            recorder.push(update);
+        }
+    }
+}
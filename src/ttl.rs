@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+/// How long the data of a signal stays fresh without updates, see [`set_sse_ttl`].
+///
+/// For dashboards, showing old numbers as if they were live can be worse than showing nothing.
+/// Once a signal hasn't received an update for the time to live, it is flagged as stale, see
+/// [`use_sse_stale`], and optionally reset to its default value.
+///
+/// ```
+/// use std::time::Duration;
+/// use leptos_sse::{set_sse_ttl, SseTtl};
+///
+/// set_sse_ttl("metrics", SseTtl::new(Duration::from_secs(30)).reset(true));
+/// ```
+///
+/// [`set_sse_ttl`]: crate::set_sse_ttl
+/// [`use_sse_stale`]: crate::use_sse_stale
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SseTtl {
+    pub(crate) ttl: Duration,
+    pub(crate) reset: bool,
+}
+
+impl SseTtl {
+    /// Expires the data after `ttl` without updates.
+    pub fn new(ttl: Duration) -> Self {
+        SseTtl { ttl, reset: false }
+    }
+
+    /// Also resets the signal to its default value when the data expires. Defaults to `false`.
+    pub fn reset(mut self, reset: bool) -> Self {
+        self.reset = reset;
+        self
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod client {
+    use std::borrow::Cow;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+
+    use leptos::prelude::*;
+
+    use super::SseTtl;
+    use crate::{set_document, SIGNAL_META};
+
+    struct Expiry {
+        ttl: Option<SseTtl>,
+        stale: ArcRwSignal<bool>,
+        timer: Option<TimeoutHandle>,
+    }
+
+    impl Default for Expiry {
+        fn default() -> Self {
+            Expiry {
+                ttl: None,
+                stale: ArcRwSignal::new(false),
+                timer: None,
+            }
+        }
+    }
+
+    thread_local! {
+        static EXPIRIES: RefCell<HashMap<Cow<'static, str>, Expiry>> = RefCell::new(HashMap::new());
+    }
+
+    /// Sets the time to live of a signal, starting its timer.
+    pub(crate) fn set(name: Cow<'static, str>, ttl: SseTtl) {
+        EXPIRIES.with(|expiries| {
+            expiries.borrow_mut().entry(name.clone()).or_default().ttl = Some(ttl);
+        });
+        touch(&name);
+    }
+
+    /// Returns the stale flag of a signal.
+    pub(crate) fn stale(name: Cow<'static, str>) -> ArcReadSignal<bool> {
+        EXPIRIES.with(|expiries| {
+            expiries
+                .borrow_mut()
+                .entry(name)
+                .or_default()
+                .stale
+                .read_only()
+        })
+    }
+
+    /// Marks the data of a signal as fresh, and restarts its timer.
+    pub(crate) fn touch(name: &str) {
+        EXPIRIES.with(|expiries| {
+            let mut expiries = expiries.borrow_mut();
+            let Some(expiry) = expiries.get_mut(name) else {
+                return;
+            };
+            if let Some(timer) = expiry.timer.take() {
+                timer.clear();
+            }
+            if expiry.stale.get_untracked() {
+                expiry.stale.set(false);
+            }
+            let Some(ttl) = expiry.ttl else {
+                return;
+            };
+            let name = name.to_string();
+            expiry.timer = set_timeout_with_handle(move || expire(&name), ttl.ttl).ok();
+        });
+    }
+
+    fn expire(name: &str) {
+        let reset = EXPIRIES.with(|expiries| {
+            let mut expiries = expiries.borrow_mut();
+            let expiry = expiries.get_mut(name)?;
+            expiry.timer = None;
+            expiry.stale.set(true);
+            Some(expiry.ttl?.reset)
+        });
+        if reset == Some(true) {
+            let default =
+                SIGNAL_META.with(|meta| meta.borrow().get(name).map(|meta| meta.default.clone()));
+            if let Some(default) = default {
+                set_document(name, default);
+            }
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) use client::*;
@@ -0,0 +1,195 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::path::PathBuf;
+
+use futures::stream::StreamExt;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::task::JoinHandle;
+
+use crate::registry::now;
+use crate::{PatchRewrite, ServerSignalUpdate, SseRegistry};
+
+/// A log of the updates of signals of an [`SseRegistry`] written to NDJSON files, see
+/// [`SseRegistry::spawn_update_log`].
+///
+/// Every line is a json object with the update, the same frame the browsers receive, and when
+/// it was published, in milliseconds since the Unix epoch:
+///
+/// ```json
+/// {"at":1700000000000,"update":{"name":"counter","patch":[{"op":"replace","path":"/value","value":1}],"seq":2}}
+/// ```
+///
+/// The files are named `updates-{at}.ndjson` after when they were started, so they sort in
+/// order. Once a file reaches `max_file_size`, the log continues in a new one, which starts by
+/// replacing the whole document of every signal, so each file can be replayed on its own, for
+/// example with `SseRecording::from_ndjson`.
+///
+/// ```
+/// use leptos_sse::SseUpdateLog;
+///
+/// let log = SseUpdateLog::new("/var/log/app/signals")
+///     .signal("orders")
+///     .max_file_size(16 * 1024 * 1024)
+///     .max_files(10);
+/// ```
+#[derive(Clone, Debug)]
+pub struct SseUpdateLog {
+    dir: PathBuf,
+    signals: Vec<Cow<'static, str>>,
+    max_file_size: u64,
+    max_files: Option<usize>,
+}
+
+impl SseUpdateLog {
+    /// Creates a log writing its files to the directory `dir`, without any signal.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        SseUpdateLog {
+            dir: dir.into(),
+            signals: Vec::new(),
+            max_file_size: 64 * 1024 * 1024,
+            max_files: None,
+        }
+    }
+
+    /// Logs the updates of the signal `name`.
+    pub fn signal(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+        self.signals.push(name.into());
+        self
+    }
+
+    /// Sets the size in bytes after which the log continues in a new file, 64 MiB by default.
+    pub fn max_file_size(mut self, bytes: u64) -> Self {
+        self.max_file_size = bytes;
+        self
+    }
+
+    /// Removes the oldest files written by this log beyond `files`. All of them are kept by
+    /// default.
+    pub fn max_files(mut self, files: usize) -> Self {
+        self.max_files = Some(files.max(1));
+        self
+    }
+
+    /// Creates a new file, removing the oldest ones beyond `max_files`.
+    async fn rotate(&self, files: &mut VecDeque<PathBuf>) -> io::Result<File> {
+        fs::create_dir_all(&self.dir).await?;
+        let mut at = now();
+        let (path, file) = loop {
+            let path = self.dir.join(format!("updates-{at}.ndjson"));
+            match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+                .await
+            {
+                Ok(file) => break (path, file),
+                // Rotated within the same millisecond
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => at += 1,
+                Err(err) => return Err(err),
+            }
+        };
+        files.push_back(path);
+        while self.max_files.is_some_and(|max| files.len() > max) {
+            if let Some(oldest) = files.pop_front() {
+                fs::remove_file(oldest).await?;
+            }
+        }
+        Ok(file)
+    }
+}
+
+#[derive(Serialize)]
+struct LoggedUpdate<'a> {
+    at: u64,
+    update: &'a ServerSignalUpdate,
+}
+
+/// The last value logged for a signal in the current file.
+#[derive(Default)]
+struct Logged {
+    value: Value,
+    seq: u64,
+}
+
+impl Logged {
+    /// Returns the update from the value logged last to `value`.
+    fn update(
+        &mut self,
+        name: Cow<'static, str>,
+        value: Value,
+        rewrite: Option<&PatchRewrite>,
+    ) -> ServerSignalUpdate {
+        let mut update = ServerSignalUpdate::new_from_json::<Value>(name, &self.value, &value);
+        if let Some(rewrite) = rewrite {
+            rewrite.apply(&mut update.patch);
+        }
+        self.seq += 1;
+        self.value = value;
+        update.with_seq(self.seq)
+    }
+
+    /// Returns an update replacing the whole document, which starts a new stream.
+    fn restart(
+        &mut self,
+        name: Cow<'static, str>,
+        rewrite: Option<&PatchRewrite>,
+    ) -> ServerSignalUpdate {
+        let value = std::mem::take(&mut self.value);
+        self.seq = 0;
+        self.update(name, value, rewrite)
+    }
+}
+
+async fn write_update(file: &mut File, update: &ServerSignalUpdate) -> io::Result<u64> {
+    let mut line = serde_json::to_vec(&LoggedUpdate { at: now(), update })?;
+    line.push(b'\n');
+    file.write_all(&line).await?;
+    file.flush().await?;
+    Ok(line.len() as u64)
+}
+
+impl SseRegistry {
+    /// Spawns a task appending the updates of the signals of `log` to its files, see
+    /// [`SseUpdateLog`].
+    ///
+    /// The log counts as a subscriber of its signals. Values published in quick succession may
+    /// be coalesced, like for the streams of slow clients. The task runs until aborted, or until
+    /// writing fails, returning the error. This must be called within a Tokio runtime.
+    pub fn spawn_update_log(&self, log: SseUpdateLog) -> JoinHandle<io::Result<()>> {
+        let rewrites: HashMap<_, _> = log
+            .signals
+            .iter()
+            .filter_map(|name| Some((name.clone(), self.rewrite_of(name)?)))
+            .collect();
+        let mut values = self.named_values(&log.signals);
+        tokio::spawn(async move {
+            let mut files = VecDeque::new();
+            let mut file = log.rotate(&mut files).await?;
+            let mut size = 0;
+            let mut logged: HashMap<Cow<'static, str>, Logged> = HashMap::new();
+            while let Some((name, value)) = values.next().await {
+                if size >= log.max_file_size {
+                    file = log.rotate(&mut files).await?;
+                    size = 0;
+                    // The new file starts with the whole document of every signal
+                    for (name, signal) in &mut logged {
+                        let update = signal.restart(name.clone(), rewrites.get(name));
+                        size += write_update(&mut file, &update).await?;
+                    }
+                }
+                let signal = logged.entry(name.clone()).or_default();
+                if signal.seq != 0 && value == signal.value {
+                    continue;
+                }
+                let rewrite = rewrites.get(&name);
+                let update = signal.update(name, value, rewrite);
+                size += write_update(&mut file, &update).await?;
+            }
+            Ok(())
+        })
+    }
+}
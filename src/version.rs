@@ -0,0 +1,55 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use leptos::prelude::*;
+
+/// The version signals of a signal, created when they are first requested.
+#[derive(Default)]
+struct Tracking {
+    version: ArcRwSignal<u64>,
+    /// When the last update was applied, in milliseconds since the Unix epoch.
+    updated: ArcRwSignal<Option<f64>>,
+}
+
+thread_local! {
+    static VERSIONS: RefCell<HashMap<Cow<'static, str>, Tracking>> = RefCell::new(HashMap::new());
+}
+
+/// Returns the version signal of a signal.
+pub(crate) fn version(name: Cow<'static, str>) -> ArcReadSignal<u64> {
+    VERSIONS.with(|versions| {
+        versions
+            .borrow_mut()
+            .entry(name)
+            .or_default()
+            .version
+            .read_only()
+    })
+}
+
+/// Returns the signal with the time the last update was applied to a signal.
+pub(crate) fn last_updated(name: Cow<'static, str>) -> ArcReadSignal<Option<f64>> {
+    VERSIONS.with(|versions| {
+        versions
+            .borrow_mut()
+            .entry(name)
+            .or_default()
+            .updated
+            .read_only()
+    })
+}
+
+/// Counts an update applied to a signal.
+pub(crate) fn bump(name: &str) {
+    let tracking = VERSIONS.with(|versions| {
+        versions
+            .borrow()
+            .get(name)
+            .map(|tracking| (tracking.version.clone(), tracking.updated.clone()))
+    });
+    if let Some((version, updated)) = tracking {
+        version.update(|version| *version += 1);
+        updated.set(Some(js_sys::Date::now()));
+    }
+}
@@ -0,0 +1,138 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use futures::stream::StreamExt;
+use serde_json::Value;
+use tokio::task::JoinHandle;
+use tokio::time;
+
+use crate::{ServerSignalUpdate, SseRegistry};
+
+/// A webhook mirroring the updates of signals of an [`SseRegistry`], see
+/// [`SseRegistry::spawn_webhook`].
+///
+/// Every update is posted as the json of a [`ServerSignalUpdate`], the same frame the browsers
+/// receive, so the receiver applies them like a client does. A failed post is retried
+/// `retries` times, waiting `retry_delay` before the first retry and twice as long before each
+/// of the following ones.
+///
+/// ```
+/// use std::time::Duration;
+/// use leptos_sse::SseWebhook;
+///
+/// let webhook = SseWebhook::new("https://example.com/hooks/orders")
+///     .signal("orders")
+///     .signal("stock")
+///     .retries(5)
+///     .retry_delay(Duration::from_secs(1));
+/// ```
+#[derive(Clone, Debug)]
+pub struct SseWebhook {
+    url: String,
+    signals: Vec<Cow<'static, str>>,
+    retries: u32,
+    retry_delay: Duration,
+    client: reqwest::Client,
+}
+
+impl SseWebhook {
+    /// Creates a webhook posting to `url`, without any signal.
+    pub fn new(url: impl Into<String>) -> Self {
+        SseWebhook {
+            url: url.into(),
+            signals: Vec::new(),
+            retries: 3,
+            retry_delay: Duration::from_millis(500),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Mirrors the updates of the signal `name`.
+    pub fn signal(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+        self.signals.push(name.into());
+        self
+    }
+
+    /// Sets how many times a failed post is retried, 3 by default.
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Sets how long to wait before retrying a failed post the first time, 500ms by default.
+    pub fn retry_delay(mut self, delay: Duration) -> Self {
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Posts with `client`, to set headers, timeouts or TLS options.
+    pub fn client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Posts `update`, retrying until it is accepted with a success status or the retries run
+    /// out.
+    async fn deliver(&self, update: &ServerSignalUpdate) -> bool {
+        let mut delay = self.retry_delay;
+        for attempt in 0..=self.retries {
+            if attempt > 0 {
+                time::sleep(delay).await;
+                delay = delay.saturating_mul(2);
+            }
+            let response = self.client.post(&self.url).json(update).send().await;
+            if response.is_ok_and(|response| response.status().is_success()) {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// The updates delivered to a webhook for a signal.
+#[derive(Default)]
+struct Delivered {
+    value: Value,
+    seq: u64,
+}
+
+impl SseRegistry {
+    /// Spawns a task posting the updates of the signals of `webhook` to its url, see
+    /// [`SseWebhook`].
+    ///
+    /// The webhook counts as a subscriber of its signals, and receives the latest value of every
+    /// signal first, replacing the whole document. The updates are posted one at a time, in the
+    /// order they were published. An update still failing after its retries is dropped, and the
+    /// next one is computed from the last value delivered, so the receiver never applies a patch
+    /// to a document it doesn't have.
+    ///
+    /// The task runs until aborted. This must be called within a Tokio runtime.
+    pub fn spawn_webhook(&self, webhook: SseWebhook) -> JoinHandle<()> {
+        let rewrites: HashMap<_, _> = webhook
+            .signals
+            .iter()
+            .filter_map(|name| Some((name.clone(), self.rewrite_of(name)?)))
+            .collect();
+        let mut values = self.named_values(&webhook.signals);
+        tokio::spawn(async move {
+            let mut delivered: HashMap<Cow<'static, str>, Delivered> = HashMap::new();
+            while let Some((name, value)) = values.next().await {
+                let signal = delivered.entry(name.clone()).or_default();
+                if signal.seq != 0 && value == signal.value {
+                    continue;
+                }
+                let mut update =
+                    ServerSignalUpdate::new_from_json::<Value>(name.clone(), &signal.value, &value);
+                if let Some(rewrite) = rewrites.get(&name) {
+                    rewrite.apply(&mut update.patch);
+                }
+                let update = update.with_seq(signal.seq + 1);
+                if webhook.deliver(&update).await {
+                    signal.seq += 1;
+                    signal.value = value;
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,134 @@
+use std::cell::{Cell, RefCell};
+
+use js_sys::{Array, Object, Reflect};
+use wasm_bindgen::{prelude::Closure, JsCast, JsValue};
+use web_sys::{
+    Blob, BlobPropertyBag, EventSource, EventTarget, MessageEvent, MessageEventInit, Url, Worker,
+};
+
+/// Source of the dedicated worker.
+///
+/// The worker owns the `EventSource`, and posts the type and data of every event it receives
+/// to the main thread without parsing them. The main thread handles the events like those of an
+/// `EventSource` it owns, so only the messages cross threads, never the documents. Every event
+/// carries the id of the connection it was received on, so that events still in flight when
+/// the connection is replaced are dropped.
+const WORKER_SOURCE: &str = r#"
+let source = null;
+
+self.onmessage = (event) => {
+  const msg = event.data;
+  if (source) source.close();
+  source = null;
+  if (msg.type !== "connect") return;
+  const es = new EventSource(msg.url, { withCredentials: msg.withCredentials });
+  for (const type of ["open", "error", "message", ...msg.events]) {
+    es.addEventListener(type, (event) => {
+      self.postMessage({ id: msg.id, type, data: event.data, readyState: es.readyState });
+    });
+  }
+  source = es;
+};
+"#;
+
+thread_local! {
+    static WORKER: RefCell<Option<Worker>> = RefCell::new(None);
+    /// The target the events of the current connection are dispatched on.
+    static RELAY: RefCell<Option<EventTarget>> = RefCell::new(None);
+    /// The id of the current connection, incremented whenever it is opened or closed.
+    static CONNECTION: Cell<u32> = Cell::new(0);
+    /// The `readyState` of the worker's `EventSource`, as of the last event it relayed.
+    static READY_STATE: Cell<Option<u16>> = Cell::new(None);
+}
+
+/// Opens an `EventSource` for `url` in the worker, replacing any previous one, and returns the
+/// target its `open`, `error`, `message` and named `events` are dispatched on.
+pub(crate) fn open(
+    url: &str,
+    with_credentials: bool,
+    events: &[&str],
+) -> Result<EventTarget, JsValue> {
+    let worker = match WORKER.with(|worker| worker.borrow().clone()) {
+        Some(worker) => worker,
+        None => spawn()?,
+    };
+    let id = CONNECTION.with(|connection| {
+        connection.set(connection.get() + 1);
+        connection.get()
+    });
+    let relay = EventTarget::new()?;
+    RELAY.with(|cell| *cell.borrow_mut() = Some(relay.clone()));
+    READY_STATE.with(|state| state.set(Some(EventSource::CONNECTING)));
+
+    let msg = Object::new();
+    Reflect::set(&msg, &"type".into(), &"connect".into())?;
+    Reflect::set(&msg, &"id".into(), &id.into())?;
+    Reflect::set(&msg, &"url".into(), &url.into())?;
+    Reflect::set(&msg, &"withCredentials".into(), &with_credentials.into())?;
+    let names = events.iter().map(|&event| JsValue::from_str(event));
+    Reflect::set(&msg, &"events".into(), &names.collect::<Array>())?;
+    worker.post_message(&msg)?;
+    Ok(relay)
+}
+
+/// Closes the `EventSource` of the worker, if any.
+pub(crate) fn close() {
+    WORKER.with(|worker| {
+        let Some(worker) = worker.borrow().clone() else {
+            return;
+        };
+        CONNECTION.with(|connection| connection.set(connection.get() + 1));
+        RELAY.with(|relay| relay.borrow_mut().take());
+        READY_STATE.with(|state| state.set(Some(EventSource::CLOSED)));
+        let msg = Object::new();
+        let _ = Reflect::set(&msg, &"type".into(), &"close".into());
+        if let Err(err) = worker.post_message(&msg) {
+            leptos::logging::error!("failed to close the SSE worker connection: {:?}", err);
+        }
+    });
+}
+
+/// Returns the `readyState` of the worker's `EventSource`, unless it was never opened.
+pub(crate) fn ready_state() -> Option<u16> {
+    READY_STATE.with(Cell::get)
+}
+
+fn spawn() -> Result<Worker, JsValue> {
+    let options = BlobPropertyBag::new();
+    options.set_type("application/javascript");
+    let blob = Blob::new_with_str_sequence_and_options(
+        &Array::of1(&JsValue::from_str(WORKER_SOURCE)),
+        &options,
+    )?;
+    let script_url = Url::create_object_url_with_blob(&blob)?;
+    let worker = Worker::new(&script_url)?;
+
+    let onmessage = Closure::wrap(Box::new(move |event: MessageEvent| {
+        let data = event.data();
+        let field = |key: &str| Reflect::get(&data, &key.into()).unwrap_or(JsValue::UNDEFINED);
+        if field("id").as_f64() != Some(CONNECTION.with(Cell::get).into()) {
+            return;
+        }
+        let Some(kind) = field("type").as_string() else {
+            return;
+        };
+        if let Some(state) = field("readyState").as_f64() {
+            READY_STATE.with(|ready_state| ready_state.set(Some(state as u16)));
+        }
+        let init = MessageEventInit::new();
+        init.set_data(&field("data"));
+        let Ok(event) = MessageEvent::new_with_event_init_dict(&kind, &init) else {
+            return;
+        };
+        // Cloned first, as the listeners may replace the connection
+        let relay = RELAY.with(|relay| relay.borrow().clone());
+        if let Some(relay) = relay {
+            let _ = relay.dispatch_event(&event);
+        }
+    }) as Box<dyn FnMut(_)>);
+    worker.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+    onmessage.forget();
+
+    WORKER.with(|cell| *cell.borrow_mut() = Some(worker.clone()));
+    Ok(worker)
+}
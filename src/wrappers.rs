@@ -0,0 +1,82 @@
+use leptos::prelude::*;
+
+use crate::compat::Local;
+use crate::SseSignal;
+
+/// Converts the signals returned by this crate into a [`Signal`].
+///
+/// The signals already convert with `.into()`, as component props declared with
+/// `#[prop(into)] value: Signal<T>` accept, but `.into_signal()` reads better where the type
+/// can't be inferred, and a [`Signal`] also converts into the older `MaybeSignal`:
+///
+/// ```
+/// use leptos::prelude::*;
+/// use leptos_sse::{create_sse_signal, SseSignalExt};
+///
+/// #[component]
+/// fn Gauge(#[prop(into)] value: Signal<f64>) -> impl IntoView {
+///     view! { <p>{move || format!("{:.1}", value.get())}</p> }
+/// }
+///
+/// #[component]
+/// pub fn App() -> impl IntoView {
+///     let load = create_sse_signal::<f64>("load").into_signal();
+///     let percent = Signal::derive(move || load.get() * 100.0);
+///     view! {
+///         <Gauge value=load />
+///         <Gauge value=percent />
+///     }
+/// }
+/// ```
+pub trait SseSignalExt<T, S = SyncStorage>
+where
+    S: Storage<T>,
+{
+    /// Wraps the signal in a [`Signal`].
+    fn into_signal(self) -> Signal<T, S>;
+}
+
+impl<T> SseSignalExt<T> for ReadSignal<T>
+where
+    T: Send + Sync + 'static,
+{
+    fn into_signal(self) -> Signal<T> {
+        self.into()
+    }
+}
+
+impl<T> SseSignalExt<T, Local> for ReadSignal<T, Local>
+where
+    T: 'static,
+{
+    fn into_signal(self) -> Signal<T, Local> {
+        self.into()
+    }
+}
+
+impl<T> SseSignalExt<T> for ArcReadSignal<T>
+where
+    T: Send + Sync + 'static,
+{
+    fn into_signal(self) -> Signal<T> {
+        self.into()
+    }
+}
+
+impl<T> SseSignalExt<T> for SseSignal<T>
+where
+    T: Send + Sync + 'static,
+{
+    fn into_signal(self) -> Signal<T> {
+        self.signal().into()
+    }
+}
+
+impl<T> SseSignalExt<T> for RwSignal<T>
+where
+    T: Send + Sync + 'static,
+{
+    fn into_signal(self) -> Signal<T> {
+        self.into()
+    }
+}
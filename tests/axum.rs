@@ -0,0 +1,445 @@
+// End-to-end tests of the axum integration: a real server with an SSE route, read by a plain
+// HTTP client over TCP, with the updates published through an `SseRegistry`.
+
+use std::cmp::Ordering;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use axum::response::sse::Sse;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Extension, Router};
+use futures::{stream, StreamExt};
+use json_patch::Patch;
+use leptos_sse::{
+    ServerSentEvents, ServerSignalUpdate, SseAck, SseClientId, SseRegistry, VectorClock,
+};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time;
+
+/// How long to wait for the server before failing a test.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Default, Serialize)]
+struct Count {
+    value: u32,
+    label: String,
+}
+
+async fn counter(Extension(registry): Extension<SseRegistry>) -> impl IntoResponse {
+    Sse::new(registry.events::<Count>("counter").unwrap())
+}
+
+async fn pair(Extension(registry): Extension<SseRegistry>) -> impl IntoResponse {
+    Sse::new(registry.batched_events(["a", "b"]))
+}
+
+/// Serves the SSE routes of `registry` on a random local port.
+async fn serve(registry: SseRegistry) -> SocketAddr {
+    let app = Router::new()
+        .route("/sse", get(counter))
+        .route("/sse/pair", get(pair))
+        .layer(Extension(registry));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    addr
+}
+
+/// A minimal SSE client, reading the events of a response as they arrive.
+struct SseClient {
+    reader: BufReader<TcpStream>,
+}
+
+/// An event of an SSE response.
+#[derive(Debug)]
+struct SseEvent {
+    event: Option<String>,
+    data: String,
+}
+
+impl SseClient {
+    /// Requests `path`, and reads the head of the response.
+    ///
+    /// HTTP/1.0 keeps the body free of chunked transfer encoding, the server closes the
+    /// connection once the stream ends.
+    async fn connect(addr: SocketAddr, path: &str) -> Self {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!("GET {path} HTTP/1.0\r\nAccept: text/event-stream\r\n\r\n");
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut client = SseClient {
+            reader: BufReader::new(stream),
+        };
+        let status = client.line().await.expect("no response");
+        assert!(status.contains(" 200 "), "unexpected status: {status}");
+        let mut content_type = None;
+        loop {
+            let header = client.line().await.expect("truncated response head");
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-type") {
+                    content_type = Some(value.trim().to_string());
+                }
+            }
+        }
+        assert_eq!(content_type.as_deref(), Some("text/event-stream"));
+        client
+    }
+
+    /// Reads a line, without its line ending, or `None` once the response ends.
+    async fn line(&mut self) -> Option<String> {
+        let mut line = String::new();
+        let read = time::timeout(TIMEOUT, self.reader.read_line(&mut line))
+            .await
+            .expect("timed out waiting for the server")
+            .unwrap();
+        if read == 0 {
+            return None;
+        }
+        let line = line.strip_suffix('\n').unwrap_or(&line);
+        Some(line.strip_suffix('\r').unwrap_or(line).to_string())
+    }
+
+    /// Reads the next event carrying data, skipping comments such as keep-alives.
+    async fn next_event(&mut self) -> Option<SseEvent> {
+        let mut event = None;
+        let mut data = Vec::new();
+        loop {
+            let line = self.line().await?;
+            if line.is_empty() {
+                if !data.is_empty() {
+                    return Some(SseEvent {
+                        event,
+                        data: data.join("\n"),
+                    });
+                }
+                event = None;
+                continue;
+            }
+            let (field, value) = line.split_once(':').unwrap_or((&line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "event" => event = Some(value.to_string()),
+                "data" => data.push(value.to_string()),
+                _ => {}
+            }
+        }
+    }
+
+    /// Reads the next update, which must be sent as a plain message.
+    async fn next_update(&mut self) -> ServerSignalUpdate {
+        let event = self.next_event().await.expect("the stream ended");
+        assert_eq!(event.event, None, "unexpected event: {event:?}");
+        serde_json::from_str(&event.data).unwrap()
+    }
+}
+
+fn patch(value: Value) -> Patch {
+    serde_json::from_value(value).unwrap()
+}
+
+#[tokio::test]
+async fn streams_registry_updates() {
+    let registry = SseRegistry::new();
+    let addr = serve(registry.clone()).await;
+    let mut client = SseClient::connect(addr, "/sse").await;
+
+    let mut doc = serde_json::to_value(Count::default()).unwrap();
+    let values = [
+        Count {
+            value: 1,
+            label: "one".to_string(),
+        },
+        Count {
+            value: 2,
+            label: "one".to_string(),
+        },
+        Count {
+            value: 3,
+            label: "three".to_string(),
+        },
+    ];
+    let mut updates = Vec::new();
+    for value in &values {
+        registry.publish("counter", value).unwrap();
+        let update = client.next_update().await;
+        json_patch::patch(&mut doc, update.patch()).unwrap();
+        assert_eq!(doc, serde_json::to_value(value).unwrap());
+        updates.push(update);
+    }
+
+    let seqs: Vec<_> = updates.iter().map(ServerSignalUpdate::seq).collect();
+    assert_eq!(seqs, [Some(1), Some(2), Some(3)]);
+    assert!(updates.iter().all(|update| update.name() == "counter"));
+    assert_eq!(
+        updates[1].patch(),
+        &patch(json!([{ "op": "replace", "path": "/value", "value": 2 }]))
+    );
+    assert_eq!(
+        updates[2].patch(),
+        &patch(json!([
+            { "op": "replace", "path": "/label", "value": "three" },
+            { "op": "replace", "path": "/value", "value": 3 },
+        ]))
+    );
+}
+
+#[tokio::test]
+async fn sends_the_latest_value_on_connect() {
+    let registry = SseRegistry::new();
+    registry
+        .publish(
+            "counter",
+            &Count {
+                value: 7,
+                label: "seven".to_string(),
+            },
+        )
+        .unwrap();
+    let addr = serve(registry.clone()).await;
+    let mut client = SseClient::connect(addr, "/sse").await;
+
+    let update = client.next_update().await;
+    assert_eq!(update.seq(), Some(1));
+    let mut doc = serde_json::to_value(Count::default()).unwrap();
+    json_patch::patch(&mut doc, update.patch()).unwrap();
+    assert_eq!(doc, json!({ "value": 7, "label": "seven" }));
+}
+
+#[tokio::test]
+async fn batches_transactions() {
+    let registry = SseRegistry::new();
+    let addr = serve(registry.clone()).await;
+    let mut client = SseClient::connect(addr, "/sse/pair").await;
+
+    registry
+        .transaction(|tx| {
+            tx.publish("a", &1)?;
+            tx.publish("b", &2)
+        })
+        .unwrap();
+    let event = client.next_event().await.expect("the stream ended");
+    assert_eq!(event.event.as_deref(), Some("batch"));
+    let updates: Vec<ServerSignalUpdate> = serde_json::from_str(&event.data).unwrap();
+    let names: Vec<_> = updates.iter().map(ServerSignalUpdate::name).collect();
+    assert_eq!(names, ["a", "b"]);
+    assert_eq!(
+        updates[1].patch(),
+        &patch(json!([{ "op": "replace", "path": "", "value": 2 }]))
+    );
+}
+
+#[tokio::test]
+async fn unsubscribes_disconnected_clients() {
+    let registry = SseRegistry::new();
+    let addr = serve(registry.clone()).await;
+    let client = SseClient::connect(addr, "/sse").await;
+    let subscribers = || {
+        registry
+            .signal_stats()
+            .iter()
+            .find(|stats| stats.name == "counter")
+            .map_or(0, |stats| stats.subscribers)
+    };
+    assert_eq!(subscribers(), 1);
+
+    // The server notices the closed connection when it next writes to it
+    drop(client);
+    time::timeout(TIMEOUT, async {
+        let mut value = 0;
+        while subscribers() > 0 {
+            value += 1;
+            registry
+                .publish(
+                    "counter",
+                    &Count {
+                        value,
+                        label: String::new(),
+                    },
+                )
+                .unwrap();
+            time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("the stream was never dropped");
+}
+
+#[tokio::test]
+async fn flushes_once_the_value_is_sent_and_acknowledged() {
+    let registry = SseRegistry::new();
+    let connection = registry.connect("test");
+    connection.set_client(SseClientId(Some("client".to_string())));
+    let mut events = Box::pin(registry.events::<u32>("n").unwrap().track(&connection));
+    registry.publish("n", &1).unwrap();
+    let flush = tokio::spawn({
+        let registry = registry.clone();
+        async move { registry.flush("n", TIMEOUT).await }
+    });
+
+    // Neither sent nor acknowledged
+    time::sleep(Duration::from_millis(20)).await;
+    assert!(!flush.is_finished());
+    let _sent = events.next().await.unwrap().unwrap();
+    // Sent, but not acknowledged
+    time::sleep(Duration::from_millis(20)).await;
+    assert!(!flush.is_finished());
+
+    let ack = SseAck {
+        client: "client".to_string(),
+        seqs: [("n".to_string(), 1)].into(),
+    };
+    assert!(registry.ack(&ack));
+    let flushed = time::timeout(Duration::from_secs(1), flush).await;
+    assert!(flushed.expect("the flush was never woken").unwrap());
+    // Already acknowledged
+    assert!(registry.flush("n", Duration::ZERO).await);
+}
+
+#[tokio::test]
+async fn keeps_the_value_of_a_guard_dropped_by_a_panic() {
+    let registry = SseRegistry::new();
+    registry.publish("n", &1).unwrap();
+    let sender = registry.sender::<u32>("n");
+    let writer = tokio::spawn(async move {
+        let mut guard = sender.write().await.unwrap();
+        *guard = 2;
+        panic!("the write was interrupted");
+    });
+    assert!(writer.await.unwrap_err().is_panic());
+    assert_eq!(registry.value("n"), Some(json!(1)));
+
+    // The guard of the panicked writer was released
+    let mut guard = registry.sender::<u32>("n").write().await.unwrap();
+    *guard = 3;
+    drop(guard);
+    assert_eq!(registry.value("n"), Some(json!(3)));
+}
+
+#[tokio::test]
+async fn keeps_the_published_value_when_a_stream_sends_a_stale_one() {
+    let registry = SseRegistry::new();
+    let connection = registry.connect("test");
+    registry.publish("n", &2).unwrap();
+    // A stream still sending the value published before
+    let stale = stream::iter([Ok::<u32, axum::BoxError>(1)]);
+    let mut events = Box::pin(
+        ServerSentEvents::new("n", stale)
+            .unwrap()
+            .track(&connection),
+    );
+    let _sent = events.next().await.unwrap().unwrap();
+    assert_eq!(registry.value("n"), Some(json!(2)));
+    registry.update("n", |n: &mut u32| *n += 1).unwrap();
+    assert_eq!(registry.value("n"), Some(json!(3)));
+}
+
+#[test]
+fn compares_vector_clocks() {
+    let mut east = VectorClock::default();
+    let mut west = VectorClock::default();
+    assert_eq!(east.compare(&west), Some(Ordering::Equal));
+
+    east.increment("east");
+    assert_eq!(east.compare(&west), Some(Ordering::Greater));
+    assert_eq!(west.compare(&east), Some(Ordering::Less));
+
+    west.increment("west");
+    assert_eq!(east.compare(&west), None);
+    assert_eq!(west.compare(&east), None);
+
+    west.merge(&east);
+    assert_eq!(west.get("east"), 1);
+    assert_eq!(west.compare(&east), Some(Ordering::Greater));
+    east.merge(&west);
+    assert_eq!(east.compare(&west), Some(Ordering::Equal));
+}
+
+/// A registry with the signal `settings` in merge mode, written by `replica`.
+fn replica(replica: &str) -> SseRegistry {
+    let registry = SseRegistry::new();
+    registry.crdt("settings", replica);
+    registry
+}
+
+#[test]
+fn merges_crdt_states_in_any_order() {
+    let east = replica("east");
+    east.publish(
+        "settings",
+        &json!({ "theme": "dark", "language": "en", "zoom": 1 }),
+    )
+    .unwrap();
+    let initial = east.crdt_state("settings").unwrap();
+    let (west, north) = (replica("west"), replica("north"));
+    west.merge("settings", &initial);
+    north.merge("settings", &initial);
+
+    // Concurrent writes, with `zoom` removed by two replicas and changed by the third
+    east.publish("settings", &json!({ "theme": "light", "language": "en" }))
+        .unwrap();
+    west.publish("settings", &json!({ "theme": "dark", "language": "fr" }))
+        .unwrap();
+    north
+        .publish(
+            "settings",
+            &json!({ "theme": "dark", "language": "en", "zoom": 2 }),
+        )
+        .unwrap();
+    let states = [&east, &west, &north].map(|registry| registry.crdt_state("settings").unwrap());
+
+    let orders = [
+        [0, 1, 2],
+        [0, 2, 1],
+        [1, 0, 2],
+        [1, 2, 0],
+        [2, 0, 1],
+        [2, 1, 0],
+    ];
+    let merged = orders.map(|order| {
+        let registry = replica("south");
+        for index in order {
+            registry.merge("settings", &states[index]);
+            // Merging a state again changes nothing
+            assert!(registry.merge("settings", &states[index]));
+        }
+        registry.value("settings").unwrap()
+    });
+    assert!(merged.iter().all(|value| *value == merged[0]));
+    // Concurrent writes of the same field are ordered by replica name
+    assert_eq!(merged[0], json!({ "theme": "light", "language": "fr" }));
+
+    // Every replica converges to the same value
+    for registry in [&east, &west, &north] {
+        for state in &states {
+            registry.merge("settings", state);
+        }
+        assert_eq!(registry.value("settings").unwrap(), merged[0]);
+    }
+}
+
+#[test]
+fn keeps_removed_fields_removed_when_merging_older_states() {
+    let east = replica("east");
+    east.publish("settings", &json!({ "theme": "dark", "zoom": 1 }))
+        .unwrap();
+    let before = east.crdt_state("settings").unwrap();
+    east.publish("settings", &json!({ "theme": "dark" }))
+        .unwrap();
+    let after = east.crdt_state("settings").unwrap();
+
+    let west = replica("west");
+    west.merge("settings", &after);
+    west.merge("settings", &before);
+    assert_eq!(west.value("settings"), Some(json!({ "theme": "dark" })));
+    // The removal of `zoom` is sent on as a tombstone
+    let north = replica("north");
+    north.merge("settings", &before);
+    north.merge("settings", &west.crdt_state("settings").unwrap());
+    assert_eq!(north.value("settings"), Some(json!({ "theme": "dark" })));
+}
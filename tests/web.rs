@@ -0,0 +1,181 @@
+// Tests of the client pipeline in a headless browser, with synthetic messages dispatched on the
+// EventSource given to `provide_sse_event_source`. Run them with
+// `wasm-pack test --headless --firefox`.
+
+#![cfg(target_arch = "wasm32")]
+
+use std::cell::OnceCell;
+
+use leptos::prelude::*;
+use leptos_sse::{
+    create_sse_arc_signal, create_sse_signal, provide_sse_event_source, remove_sse_document,
+    sse_documents, use_sse_diagnostics,
+};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen_test::{wasm_bindgen_test, wasm_bindgen_test_configure};
+use web_sys::{EventSource, MessageEvent, MessageEventInit};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+thread_local! {
+    static EVENT_SOURCE: OnceCell<EventSource> = const { OnceCell::new() };
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+struct Count {
+    value: i32,
+}
+
+/// Dispatches a message carrying `data` on the EventSource of the tests.
+///
+/// Nothing serves the url of the EventSource, so it never opens, and the only messages are
+/// the ones dispatched by the tests. The messages are handled before this returns.
+fn send(data: &str) {
+    EVENT_SOURCE.with(|cell| {
+        let es = cell.get_or_init(|| {
+            let es = EventSource::new("/__leptos_sse_test").unwrap();
+            provide_sse_event_source(&es).unwrap();
+            es
+        });
+        let init = MessageEventInit::new();
+        init.set_data(&data.into());
+        let event = MessageEvent::new_with_event_init_dict("message", &init).unwrap();
+        es.dispatch_event(&event).unwrap();
+    });
+}
+
+/// Sends an update replacing the document of the signal `name` with `value`.
+fn replace(name: &str, value: serde_json::Value) {
+    let patch = serde_json::json!([{ "op": "replace", "path": "", "value": value }]);
+    send(&serde_json::json!({ "name": name, "patch": patch }).to_string());
+}
+
+#[wasm_bindgen_test]
+fn routes_updates_by_name() {
+    let a = create_sse_arc_signal::<Count>("routing_a");
+    let b = create_sse_arc_signal::<Count>("routing_b");
+    send(r#"{"name":"routing_a","patch":[{"op":"replace","path":"/value","value":1}]}"#);
+    assert_eq!(a.get_untracked(), Count { value: 1 });
+    assert_eq!(b.get_untracked(), Count::default());
+
+    replace("routing_b", serde_json::json!({ "value": 2 }));
+    assert_eq!(a.get_untracked(), Count { value: 1 });
+    assert_eq!(b.get_untracked(), Count { value: 2 });
+}
+
+#[wasm_bindgen_test]
+fn queues_updates_for_signals_created_later() {
+    send(r#"{"name":"queued","patch":[{"op":"add","path":"/-","value":1}]}"#);
+    send(r#"{"name":"queued","patch":[{"op":"add","path":"/-","value":2}]}"#);
+    let items = create_sse_arc_signal::<Vec<i32>>("queued");
+    assert_eq!(items.get_untracked(), Vec::<i32>::new());
+
+    // The queued patches are applied before the next update
+    send(r#"{"name":"queued","patch":[{"op":"add","path":"/-","value":3}]}"#);
+    assert_eq!(items.get_untracked(), [1, 2, 3]);
+}
+
+#[wasm_bindgen_test]
+fn shares_the_document_of_signals_with_the_same_name() {
+    let first = create_sse_arc_signal::<Count>("shared");
+    let second = create_sse_arc_signal::<Count>("shared");
+    replace("shared", serde_json::json!({ "value": 5 }));
+    assert_eq!(first.get_untracked(), Count { value: 5 });
+    assert_eq!(second.get_untracked(), Count { value: 5 });
+
+    // A signal created later starts from the document the others received
+    let third = create_sse_arc_signal::<Count>("shared");
+    assert_eq!(third.get_untracked(), Count { value: 5 });
+    send(r#"{"name":"shared","patch":[{"op":"replace","path":"/value","value":6}]}"#);
+    assert_eq!(first.get_untracked(), Count { value: 6 });
+    assert_eq!(third.get_untracked(), Count { value: 6 });
+}
+
+#[wasm_bindgen_test]
+fn shares_the_document_between_kinds_of_signals() {
+    let first = create_sse_arc_signal::<Count>("mixed");
+    replace("mixed", serde_json::json!({ "value": 1 }));
+    let owner = Owner::new();
+    owner.with(|| create_sse_signal::<Count>("mixed"));
+
+    // The arena-free signal follows the document signal created after it
+    send(r#"{"name":"mixed","patch":[{"op":"replace","path":"/value","value":2}]}"#);
+    assert_eq!(first.get_untracked(), Count { value: 2 });
+    let second = create_sse_arc_signal::<Count>("mixed");
+    assert_eq!(second.get_untracked(), Count { value: 2 });
+    send(r#"{"name":"mixed","patch":[{"op":"replace","path":"/value","value":3}]}"#);
+    assert_eq!(second.get_untracked(), Count { value: 3 });
+}
+
+#[wasm_bindgen_test]
+fn ignores_replayed_updates() {
+    let items = create_sse_arc_signal::<Vec<i32>>("replayed");
+    send(r#"{"name":"replayed","patch":[{"op":"add","path":"/-","value":1}],"seq":1}"#);
+    send(r#"{"name":"replayed","patch":[{"op":"add","path":"/-","value":2}],"seq":2}"#);
+    send(r#"{"name":"replayed","patch":[{"op":"add","path":"/-","value":2}],"seq":2}"#);
+    assert_eq!(items.get_untracked(), [1, 2]);
+}
+
+#[wasm_bindgen_test]
+fn reports_patches_which_fail_to_apply() {
+    let diagnostics = use_sse_diagnostics();
+    let failed = diagnostics.get_untracked().failed_patches;
+    let broken = create_sse_arc_signal::<Count>("failing");
+    let other = create_sse_arc_signal::<Count>("failing_other");
+    replace("failing", serde_json::json!({ "value": 1 }));
+
+    send(r#"{"name":"failing","patch":[{"op":"remove","path":"/missing"}]}"#);
+    assert_eq!(diagnostics.get_untracked().failed_patches, failed + 1);
+    assert_eq!(broken.get_untracked(), Count { value: 1 });
+
+    // The failure doesn't affect other signals
+    replace("failing_other", serde_json::json!({ "value": 2 }));
+    assert_eq!(other.get_untracked(), Count { value: 2 });
+}
+
+#[wasm_bindgen_test]
+fn keeps_the_last_value_of_documents_of_another_type() {
+    let count = create_sse_arc_signal::<Count>("mistyped");
+    replace("mistyped", serde_json::json!({ "value": 1 }));
+    replace("mistyped", serde_json::json!("one"));
+    assert_eq!(count.get_untracked(), Count { value: 1 });
+
+    replace("mistyped", serde_json::json!({ "value": 2 }));
+    assert_eq!(count.get_untracked(), Count { value: 2 });
+}
+
+#[wasm_bindgen_test]
+fn removes_documents_without_signals() {
+    let owner = Owner::new();
+    owner.with(|| create_sse_signal::<Count>("removed"));
+    let kept = create_sse_arc_signal::<Count>("kept");
+    replace("removed", serde_json::json!({ "value": 1 }));
+    replace("kept", serde_json::json!({ "value": 1 }));
+    let signals = |name: &str| {
+        sse_documents()
+            .into_iter()
+            .find(|doc| doc.name == name)
+            .map(|doc| doc.signals)
+    };
+    assert_eq!(signals("removed"), Some(1));
+    assert!(!remove_sse_document("removed"));
+
+    owner.cleanup();
+    assert_eq!(signals("removed"), Some(0));
+    assert!(remove_sse_document("removed"));
+    assert_eq!(signals("removed"), None);
+    assert!(!remove_sse_document("kept"));
+    assert_eq!(kept.get_untracked(), Count { value: 1 });
+}
+
+#[wasm_bindgen_test]
+fn reports_malformed_messages() {
+    let diagnostics = use_sse_diagnostics();
+    let malformed = diagnostics.get_untracked().malformed_messages;
+    send("not json");
+    send(r#"{"name":"malformed","patch":{}}"#);
+    assert_eq!(
+        diagnostics.get_untracked().malformed_messages,
+        malformed + 2
+    );
+}